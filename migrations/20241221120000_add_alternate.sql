@@ -0,0 +1,2 @@
+-- Stores the alternate form (noted with "OR:") of a reading, if it has one
+ALTER TABLE reading ADD COLUMN alternate TEXT;
@@ -0,0 +1,7 @@
+-- Removes duplicate readings, keeping the most recently inserted reading of each type
+DELETE FROM reading WHERE id NOT IN (
+    SELECT MAX(id) FROM reading GROUP BY lectionary_id, reading_type
+);
+
+-- Each lectionary can only have one reading of each type
+CREATE UNIQUE INDEX IF NOT EXISTS reading_lectionary_type ON reading (lectionary_id, reading_type);
@@ -0,0 +1,2 @@
+-- Stores the footnote markers removed from the text of a reading, one per line
+ALTER TABLE reading ADD COLUMN footnotes TEXT;
@@ -0,0 +1,2 @@
+-- Stores the rank of the day (e.g. solemnity, memorial) when it can be determined from the name
+ALTER TABLE lectionary ADD COLUMN rank TEXT;
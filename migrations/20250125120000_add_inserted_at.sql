@@ -0,0 +1,2 @@
+-- Stores when each lectionary was inserted, in seconds since the Unix epoch
+ALTER TABLE lectionary ADD COLUMN inserted_at INTEGER;
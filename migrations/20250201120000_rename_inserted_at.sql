@@ -0,0 +1,2 @@
+-- The insertion time is the time the lectionary was fetched from the web
+ALTER TABLE lectionary RENAME COLUMN inserted_at TO fetched_at;
@@ -1,38 +1,121 @@
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use crate::date::DateFormat;
+use crate::rank::Rank;
+use log::LevelFilter;
 use serde::{Deserialize, Serialize};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Arguments {
+    /// Displays the readings for today if not given
     #[command(subcommand)]
-    pub command: Command,
+    pub command: Option<Command>,
 
     #[command(flatten)]
     pub common_args: CommonArguments,
+
+    /// Write the log file to the given path instead of the default one
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Don't write a log file
+    #[arg(long, global = true, conflicts_with = "log_file")]
+    pub no_log_file: bool,
+
+    /// Don't create the config file with the default settings when there isn't one
+    ///
+    /// The default settings are used without writing anything. Commands that write the config (config init, set, and upgrade)
+    /// still create it. Can also be set with the `LECTIO_DIEI_NO_CREATE_CONFIG` environment variable
+    #[arg(long, global = true)]
+    pub no_create_config: bool,
+
+    /// Log how long retrieving from the web, parsing, and each database query take
+    ///
+    /// Logged at info level, so use with --verbose to see them on the terminal
+    #[arg(long, global = true)]
+    pub timings: bool,
+
+    /// How a fatal error is written to STDERR
+    ///
+    /// With json, a single object with the error name, exit code, and message is written instead of the log line
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub error_format: ErrorFormat,
 }
 
-#[derive(Args, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Args, Clone)]
 pub struct CommonArguments {
+    /// Use the config file at the given path instead of the default one
+    ///
+    /// Fails if the file does not exist, unless the command creates it (e.g. config init)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
     /// Disables colors
     ///
     /// Output for STDERR and STDOUT will not print with ANSI color codes. Useful if terminal does not support colors or redirecting to file
     #[arg(long, global = true)]
     pub no_color: bool,
+
+    /// Only log errors to STDERR
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Log more to STDERR. Can be repeated (e.g. -vv) for more detail
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+}
+
+impl CommonArguments {
+    /// Gets the log level for the terminal, as determined by the quiet and verbose flags
+    #[must_use]
+    pub fn log_level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::Error;
+        }
+        match self.verbose {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
 }
 
 #[derive(Subcommand)]
 pub enum Command {
     /// Print the Reading to STDOUT
     Display {
-        /// Date to retrieve (Uses today if not specified)
+        /// Date to retrieve (Uses today if not specified). Should be in MMddYY format or the --date-format
+        ///
+        /// Can be given more than once to display each of the dates in order, e.g. --date 071424 --date 081524
         #[arg(short, long)]
-        date: Option<String>,
+        date: Vec<String>,
 
         #[command(flatten)]
         readings: DisplayReadingsArgs,
 
         #[command(flatten)]
         formatting: FormattingArgs,
+
+        #[command(flatten)]
+        retrieval: RetrievalArgs,
+
+        #[command(flatten)]
+        length: ReadingLengthArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Manage the database, including retrieving more readings
     Db {
@@ -40,11 +123,51 @@ pub enum Command {
         command: DatabaseCommand,
     },
     /// View and change the config
-    //TODO set config settings
     Config {
         #[command(subcommand)]
         command: ConfigCommand,
     },
+    /// Writes a shell completion script to STDOUT
+    ///
+    /// Example: lectio-diei completions bash > /usr/share/bash-completion/completions/lectio-diei
+    Completions {
+        /// Shell to generate the script for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Writes the version and the location of the config, database, and log files, with whether each exists and is writable
+    ///
+    /// Then retrieves today's readings page and reports whether the site could be reached, whether the readings were found on
+    /// the page, and which readings could be parsed. Nothing is stored
+    ///
+    /// Useful for bug reports. Reports each problem found instead of failing
+    Doctor {
+        /// Only check the files, without retrieving from the web
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Writes a man page for the application and each subcommand to a directory
+    ///
+    /// Intended for packaging. Example: lectio-diei man ./man
+    #[command(hide = true)]
+    Man {
+        /// Directory to write the pages to. Created if it does not exist
+        dir: PathBuf,
+    },
+}
+
+/// Display for today, using the config for everything else. Used when no command is given
+impl Default for Command {
+    fn default() -> Self {
+        Self::Display {
+            date: Vec::new(),
+            readings: DisplayReadingsArgs::default(),
+            formatting: FormattingArgs::default(),
+            retrieval: RetrievalArgs::default(),
+            length: ReadingLengthArgs::default(),
+            output: OutputArgs::default(),
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -52,7 +175,13 @@ pub enum DatabaseCommand {
     /// Removes specified date(s) from database if present.
     ///
     /// Writes number removed to STDOUT
+    ///
+    /// When run interactively, asks for confirmation before removing more than 10 dates
     Remove {
+        /// Does not ask for confirmation before removing
+        #[arg(short, long)]
+        yes: bool,
+
         /// Dates to remove. Should be in MMddYY format
         #[arg(trailing_var_arg(true), num_args(1..usize::MAX))]
         dates: Vec<String>,
@@ -62,38 +191,172 @@ pub enum DatabaseCommand {
     /// Writes num to STDOUT
     Count,
     /// Adds entries from the web to the database
-    //TODO add arguments to override config
-    Update,
+    ///
+    /// Uses the range of dates defined in the config unless overridden
+    Update {
+        /// Maximum number of simultaneous requests to the web. Overrides config
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+
+        /// Number of days before today to store. Overrides config
+        #[arg(long, value_name = "DAYS", conflicts_with = "date")]
+        past: Option<u32>,
+
+        /// Number of days after today to store. Today is always stored. Overrides config
+        #[arg(long, value_name = "DAYS", conflicts_with = "date")]
+        future: Option<u32>,
+
+        /// Store a single date instead of a range. Should be in MMddYY format
+        #[arg(short, long)]
+        date: Option<String>,
+
+        /// Only store Sundays within the range
+        #[arg(long, conflicts_with = "date")]
+        sundays_only: bool,
+
+        /// Only retry the dates that the last update failed to store
+        ///
+        /// The failed dates are remembered until an update stores all of its dates
+        #[arg(long, conflicts_with_all = ["past", "future", "date", "sundays_only"])]
+        retry_failed: bool,
+    },
     /// Shows all of the lectionary rows in the database
     ///
     /// Prints every row of the lectionary table, sorted by date, as "[date] [name]"
     #[command(alias = "list")]
-    Show,
+    Show {
+        /// Only show lectionaries of the given rank
+        ///
+        /// The rank is guessed from the name of the day when the lectionary is stored
+        #[arg(long, value_enum, value_name = "RANK")]
+        filter: Option<Rank>,
+
+        /// Only show lectionaries on or after the given date. Should be in MMddYY format
+        #[arg(long, value_name = "DATE")]
+        since: Option<String>,
+
+        /// Only show lectionaries before the given date. Should be in MMddYY format
+        #[arg(long, value_name = "DATE")]
+        before: Option<String>,
+
+        /// Order in which the lectionaries are shown
+        #[arg(long, value_enum, default_value_t)]
+        sort: ShowSort,
+
+        /// Also show when each lectionary was fetched, as "[date] [fetched at] [name]"
+        #[arg(long)]
+        timestamps: bool,
+
+        /// Format of the rows, e.g. for importing in to a spreadsheet
+        #[arg(long, value_enum, default_value_t)]
+        format: ShowFormat,
+    },
+    /// Shows the number of lectionaries, the range of dates, when the oldest and newest were fetched, and the average and
+    /// longest reading
+    Stats,
+    /// Writes every lectionary in the database as a JSON array, sorted by date
+    ///
+    /// Each lectionary is written as soon as it is read, so the whole database is never held in memory. Every reading is
+    /// included, using the display settings from the config (e.g. ascii)
+    Export {
+        /// Write to the given file instead of STDOUT
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Runs any pending migrations of the database schema
+    ///
+    /// Writes the version of the latest migration applied to STDOUT
+    Migrate,
     /// Deletes all data in the database
     ///
-    /// Writes number of rows removed to STDOUT
-    Purge,
+    /// Writes number of rows removed to STDOUT. When run interactively, asks for confirmation before removing more than 10 rows
+    Purge {
+        /// Writes the number of rows that would be removed to STDOUT without removing them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Does not ask for confirmation before removing
+        #[arg(short, long)]
+        yes: bool,
+    },
     /// Deletes old entries from the database
     ///
-    /// Uses values defined in the config
+    /// Uses values defined in the config. Writes number removed to STDOUT
+    /// When run interactively, asks for confirmation before removing more than 10 rows
     Clean {
+        /// Also removes entries after the configured number of future entries
+        ///
+        /// Without this, future entries are only removed if they are more than twice the configured number of days away
         #[arg[short, long]]
         all: bool,
+
+        /// Writes the dates that would be removed to STDOUT, one per line, without removing them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Writes the dates removed to STDOUT, one per line, instead of the number removed
+        #[arg(short, long)]
+        list: bool,
+
+        /// Does not ask for confirmation before removing
+        #[arg(short, long)]
+        yes: bool,
     },
     /// Equivalent of db clean + db update
-    Refresh,
+    Refresh {
+        /// Maximum number of simultaneous requests to the web. Overrides config
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+    },
     /// Stores specified dates in to the database if they are not present
     ///
+    /// Dates can be given individually, as a range with --from and --to, or both. The range does not depend on the config
+    ///
     /// Writes number of new entries to STDOUT
     Store {
-        #[arg(trailing_var_arg(true), num_args(1..usize::MAX))]
+        #[arg(trailing_var_arg(true), num_args(0..usize::MAX), required_unless_present = "from")]
         dates: Vec<String>,
+
+        /// First date of a range to store. Should be in MMddYY format
+        #[arg(long, value_name = "DATE", requires = "to")]
+        from: Option<String>,
+
+        /// Last date of a range to store, included. Should be in MMddYY format
+        #[arg(long, value_name = "DATE", requires = "from")]
+        to: Option<String>,
     },
 }
 
-#[derive(Subcommand, Copy, Clone)]
+/// Format of the rows written by db show
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ShowFormat {
+    /// One row per line, separated by spaces
+    #[default]
+    Text,
+    /// An array with an object for each row
+    Json,
+    /// Comma-separated values with a header line. Names containing commas are quoted
+    Csv,
+}
+
+/// Order of the rows written by db show
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ShowSort {
+    /// By date, earliest first
+    #[default]
+    Date,
+    /// By the name of the day, alphabetically
+    Name,
+    /// By when each lectionary was last fetched from the web, earliest first. Refreshing a stale lectionary moves it to the
+    /// end. Lectionaries fetched before this was recorded come first
+    Insertion,
+}
+
+#[derive(Subcommand, Clone)]
 pub enum ConfigCommand {
     /// Initializes the data at the default location
+    ///
+    /// Other commands create the config when there isn't one, unless --no-create-config is given. This always creates it
     Init {
         /// Overrides file if it exists
         #[arg(short, long)]
@@ -103,9 +366,29 @@ pub enum ConfigCommand {
     Upgrade,
     /// Writes the config to STDOUT
     Show,
+    /// Checks the config file for problems such as unknown keys and invalid values
+    ///
+    /// Writes each problem with its line number to STDOUT. Exits with an error if any problems are found
+    Check,
+    /// Writes a single value from the config to STDOUT
+    ///
+    /// Strings are written without quotes. Example: config get display.offline
+    Get {
+        /// Key to get in the form 'table.key' (e.g. display.offline)
+        key: String,
+    },
+    /// Sets a single value in the config file, preserving comments
+    ///
+    /// Example: config set display.offline true
+    Set {
+        /// Key to set in the form 'table.key' (e.g. display.offline)
+        key: String,
+        /// New value. Parsed as TOML if possible (e.g. 100, true), otherwise used as a string
+        value: String,
+    },
 }
 
-#[derive(Args, Copy, Clone)]
+#[derive(Args, Default, Copy, Clone)]
 #[group(required = false, multiple = false)]
 pub struct FormattingArgs {
     /// Format the lines so that each has a given maximum length
@@ -119,20 +402,257 @@ pub struct FormattingArgs {
     pub original_linebreaks: bool,
 }
 
-#[derive(Args)]
+#[derive(Args, Default, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct RetrievalArgs {
+    /// Only use readings already stored in the database
+    ///
+    /// Never attempts to retrieve from the web. Fails if the reading is not present in the database
+    #[arg(long)]
+    pub offline: bool,
+
+    /// If there are no readings for the date, use the nearest previous day that has readings
+    ///
+    /// Checks up to the given number of previous days (7 if not given)
+    #[arg(long, value_name = "DAYS", num_args = 0..=1, default_missing_value = "7")]
+    pub nearest: Option<u16>,
+
+    /// On days with more than one Mass (e.g. Christmas), display the Mass with a name containing the given text
+    ///
+    /// Uses the Mass during the Day if not given. Always retrieves from the web. Example: --mass vigil
+    #[arg(long, conflicts_with = "offline")]
+    pub mass: Option<String>,
+
+    /// Display whatever readings can be found instead of failing when the page is missing a required reading
+    ///
+    /// A warning is written for each missing reading. Readings retrieved this way are not stored in the database
+    #[arg(long)]
+    pub lenient: bool,
+
+    /// Also display each day after the date, up to and including the given date. Should be in MMddYY format or the --date-format
+    ///
+    /// Example: --format html --until 072124 --output week.html
+    #[arg(long, value_name = "DATE", conflicts_with = "web")]
+    pub until: Option<String>,
+
+    /// Format of the date and --until, as a chrono strftime format (e.g. "%d.%m.%Y"). Overrides the config
+    ///
+    /// Dates in the MMddYY format are always accepted too
+    #[arg(long, value_name = "FORMAT")]
+    pub date_format: Option<DateFormat>,
+
+    /// Use a temporary in-memory database, so nothing is read from or stored in the database file
+    ///
+    /// The readings are always retrieved from the web
+    #[arg(long, conflicts_with = "offline")]
+    pub ephemeral: bool,
+
+    /// Display the day with a name containing the given text (ignoring case) instead of a date. Example: --find assumption
+    ///
+    /// Only searches readings already stored in the database. If more than one day matches, their dates and names are written
+    /// to STDOUT instead
+    #[arg(long, value_name = "TEXT", conflicts_with_all = ["date", "until", "ephemeral", "web"])]
+    pub find: Option<String>,
+
+    /// Load pages saved in the cache directory (e.g. ~/.cache/lectio-diei/html) instead of retrieving them from the site
+    ///
+    /// Pages that are not saved yet are retrieved and saved. Useful for debugging parse issues without repeated requests
+    #[arg(long, conflicts_with = "offline")]
+    pub use_cache: bool,
+
+    /// Don't store readings retrieved from the web in the database, e.g. for a one-off look at a far-off date
+    ///
+    /// Readings already stored are still used
+    #[arg(long, conflicts_with_all = ["offline", "ephemeral"])]
+    pub no_store: bool,
+
+    /// Parse the readings from a page saved to the given file instead of retrieving them. Use - to read the page from STDIN
+    ///
+    /// The readings are displayed for the date given with --date (today if not given) and are not stored in the database. Only
+    /// one --date can be given
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["offline", "nearest", "mass", "until", "ephemeral", "find", "use_cache", "web", "raw_html", "show_source"]
+    )]
+    pub from_file: Option<PathBuf>,
+}
+
+#[derive(Args, Default, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct OutputArgs {
+    /// Write the readings to a file instead of STDOUT
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Open the readings on the USCCB site in the default web browser instead of displaying them
+    ///
+    /// Writes the URL to STDOUT if no browser can be launched
+    #[arg(long, conflicts_with = "output")]
+    pub web: bool,
+
+    /// When to use colors in the displayed readings
+    ///
+    /// Colors make the name of the day and the headings bold, and write the liturgical color in that color. With auto, colors are
+    /// used when writing to a terminal but not when writing to a file or a pipe
+    #[arg(long, value_enum, default_value_t)]
+    pub color: ColorWhen,
+
+    /// Only write the text of the reading, without the name of the day, headings, or separators
+    ///
+    /// Must be used with exactly one reading. Example: --raw --readings gospel
+    #[arg(long, requires = "readings", conflicts_with = "format")]
+    pub raw: bool,
+
+    /// Only write the name of the day and the name and location of each reading, without the text
+    #[arg(long, conflicts_with_all = ["raw", "format"])]
+    pub headings: bool,
+
+    /// How the readings are written. Overrides config
+    ///
+    /// With tts, only the text to be spoken is written, which is useful for piping into a text-to-speech engine
+    /// With html, every day displayed (see --until) is written as one document with a table of contents
+    /// With json, a single day is written as an object, and more than one day (see --until) as an array of objects
+    #[arg(long, value_enum)]
+    pub format: Option<DisplayFormat>,
+
+    /// Write JSON on a single line instead of indented. Only used with --format json
+    #[arg(long)]
+    pub compact: bool,
+
+    /// Write the HTML of the container of the readings on the site to STDOUT instead of displaying the readings
+    ///
+    /// For debugging changes to the markup of the site (e.g. to attach to an issue). Always retrieves from the web
+    #[arg(long, hide = true, conflicts_with_all = ["offline", "web", "output", "raw", "headings"])]
+    pub raw_html: bool,
+
+    /// Use the display settings from the named profile in the config (e.g. [profiles.export])
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Use the full names of books in reading locations (e.g. "Matthew 9:1-8" instead of "Mt 9:1-8")
+    #[arg(long)]
+    pub expand_refs: bool,
+
+    /// Show the footnote markers that were removed from the text of each reading after the reading
+    #[arg(long)]
+    pub footnotes: bool,
+
+    /// Line written around the name of the day and the text of each reading. Overrides config
+    #[arg(long, value_enum)]
+    pub separator: Option<SeparatorStyle>,
+
+    /// Replace curly quotes, dashes, and ellipses in the readings with ASCII characters. The stored readings are unchanged
+    #[arg(long)]
+    pub ascii: bool,
+
+    /// Remove liturgical responses (e.g. "The word of the Lord.") from the end of each reading. Overrides config
+    #[arg(long)]
+    pub no_responses: bool,
+
+    /// Number each line of the text of the readings, for referring to lines during discussion
+    ///
+    /// Lines are numbered as displayed, after wrapping. Numbering starts over for each reading
+    #[arg(long)]
+    pub number_lines: bool,
+
+    /// Write the URL of the page each day's readings were retrieved from to STDERR
+    ///
+    /// For readings already stored in the database, writes the URL of the readings page for the date instead
+    #[arg(long)]
+    pub show_source: bool,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayFormat {
+    /// Headings and separators for reading on a terminal
+    #[default]
+    Pretty,
+    /// Plain sentences without headings, separators, or locations
+    Tts,
+    /// A self-contained HTML document with a table of contents, e.g. for reading offline on an e-reader
+    Html,
+    /// JSON with the date, name, color, and selected readings of each day, e.g. for use by other programs
+    Json,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ColorWhen {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Args, Default, Copy, Clone)]
 #[group(required = false, multiple = false)]
+pub struct ReadingLengthArgs {
+    /// Use the longer form of readings that have an alternate
+    #[arg(long)]
+    pub long: bool,
+
+    /// Use the shorter form of readings that have an alternate
+    #[arg(long)]
+    pub short: bool,
+}
+
+#[derive(Args, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct DisplayReadingsArgs {
-    /// Displays the readings in the specified order
-    #[arg(short, long, alias="reading", value_enum, num_args=1..)]
+    /// Displays the readings in the specified order. A reading listed more than once is only displayed the first time
+    #[arg(short, long, alias="reading", value_enum, num_args=1.., conflicts_with_all = ["all", "day_only"])]
     pub readings: Option<Vec<ReadingArg>>,
 
-    /// Displays all readings in default order
-    #[arg(short, long)]
+    /// Displays all readings in default order, including the Alleluia
+    #[arg(short, long, conflicts_with = "day_only")]
     pub all: bool,
 
     /// Only display the name of the day
     #[arg(long)]
     pub day_only: bool,
+
+    /// Display the first reading. Can be combined with the other reading flags
+    ///
+    /// The readings flagged are displayed in the order they are read at Mass
+    #[arg(long, conflicts_with_all = READING_SELECTIONS)]
+    pub first: bool,
+
+    /// Display the responsorial psalm. Can be combined with the other reading flags
+    #[arg(long, conflicts_with_all = READING_SELECTIONS)]
+    pub psalm: bool,
+
+    /// Display the second reading. Can be combined with the other reading flags
+    #[arg(long, conflicts_with_all = READING_SELECTIONS)]
+    pub second: bool,
+
+    /// Display the alleluia. Can be combined with the other reading flags
+    #[arg(long, conflicts_with_all = READING_SELECTIONS)]
+    pub alleluia: bool,
+
+    /// Display the gospel. Can be combined with the other reading flags
+    #[arg(long, conflicts_with_all = READING_SELECTIONS)]
+    pub gospel: bool,
+}
+
+/// Arguments that choose the readings on their own, so can't be combined with the reading flags (e.g. --gospel)
+const READING_SELECTIONS: [&str; 3] = ["readings", "all", "day_only"];
+
+impl DisplayReadingsArgs {
+    /// The readings chosen with the reading flags, in the order they are read at Mass. Empty if none were given
+    #[must_use]
+    pub fn flagged_readings(&self) -> Vec<ReadingArg> {
+        [
+            (self.first, ReadingArg::Reading1),
+            (self.psalm, ReadingArg::Psalm),
+            (self.second, ReadingArg::Reading2),
+            (self.alleluia, ReadingArg::Alleluia),
+            (self.gospel, ReadingArg::Gospel),
+        ]
+        .into_iter()
+        .filter_map(|(flagged, reading)| flagged.then_some(reading))
+        .collect()
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize)]
@@ -145,6 +665,28 @@ pub enum ReadingArg {
     Alleluia,
 }
 
+/// Line written around the name of the day and the text of each reading
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeparatorStyle {
+    /// A line of dashes as wide as the name of the day
+    #[default]
+    Dashes,
+    /// A blank line
+    Blank,
+    /// No line
+    None,
+}
+
+/// Which form to use for readings that have an alternate
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadingLength {
+    #[default]
+    Long,
+    Short,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -154,4 +696,46 @@ mod tests {
     fn arguments_works() {
         Arguments::command().debug_assert();
     }
+
+    #[test]
+    fn command_optional_with_global_args() {
+        let args = Arguments::try_parse_from(["lectio-diei", "--no-color", "-v", "--no-log-file"]).unwrap();
+        assert!(args.command.is_none());
+        assert!(args.common_args.no_color);
+        assert_eq!(LevelFilter::Info, args.common_args.log_level());
+        assert!(args.no_log_file);
+    }
+
+    fn display_readings(args: &[&str]) -> Result<DisplayReadingsArgs, clap::Error> {
+        let args = Arguments::try_parse_from(["lectio-diei", "display"].iter().chain(args))?;
+        match args.command {
+            Some(Command::Display { readings, .. }) => Ok(readings),
+            _ => unreachable!("Parsed the display command"),
+        }
+    }
+
+    #[test]
+    fn date_repeatable() {
+        let args = Arguments::try_parse_from(["lectio-diei", "display", "--date", "071424", "-d", "081524"]).unwrap();
+        match args.command {
+            Some(Command::Display { date, .. }) => assert_eq!(vec!["071424", "081524"], date),
+            _ => unreachable!("Parsed the display command"),
+        }
+    }
+
+    #[test]
+    fn reading_flags_combine_in_mass_order() {
+        let readings = display_readings(&["--gospel", "--first"]).unwrap();
+        assert_eq!(vec![ReadingArg::Reading1, ReadingArg::Gospel], readings.flagged_readings());
+        assert!(display_readings(&[]).unwrap().flagged_readings().is_empty());
+    }
+
+    #[test]
+    fn reading_flags_conflict_with_other_selections() {
+        assert!(display_readings(&["--gospel", "--readings", "psalm"]).is_err());
+        assert!(display_readings(&["--psalm", "--all"]).is_err());
+        assert!(display_readings(&["--second", "--day-only"]).is_err());
+        assert!(display_readings(&["--all", "--day-only"]).is_err());
+        assert!(display_readings(&["--readings", "psalm", "--all"]).is_err());
+    }
 }
@@ -1,73 +1,392 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
 use log::*;
-use reqwest::{Client, StatusCode, Url};
+use reqwest::{header::RETRY_AFTER, Client, Response, StatusCode, Url};
 use scraper::Html;
 
+use crate::config::Config;
 use crate::date::DateId;
-use crate::html::{self, LectionaryHtmlError};
+use crate::html::{LectionaryHtmlError, PageSelectors};
 use crate::lectionary::Lectionary;
+use crate::logging;
+use crate::path;
+use crate::source::{LectionarySource, Usccb};
 
-const BASE_URL: &str = "https://bible.usccb.org";
+/// The USCCB site, used unless another base URL is configured
+pub const DEFAULT_BASE_URL: &str = "https://bible.usccb.org";
+/// Environment variable that overrides the base URL set in the config
+pub const BASE_URL_ENV_VAR: &str = "LECTIO_DIEI_BASE_URL";
+/// Number of times a request is retried after the site responds with 429 Too Many Requests
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+/// Longest the client will wait before retrying. If the site asks for a longer wait, the request fails instead
+const MAX_RETRY_DELAY: Duration = Duration::from_mins(1);
 
-/// Client for interacting with the USCCB site
-#[derive(Default, Clone)]
+/// Client for interacting with the USCCB site, or a mirror of it
+#[derive(Clone)]
 pub struct WebClient {
     client: Client,
+    /// Builds the URLs and parses the pages of the site
+    source: Arc<dyn LectionarySource>,
+    /// Stored without a trailing slash so that endpoints can be appended
+    base_url: String,
+    /// Keep whatever readings are found instead of failing when a required reading is missing
+    lenient: bool,
+    /// Directory that retrieved pages are saved to, if any
+    cache_dir: Option<PathBuf>,
+    /// Load pages from the cache directory when present instead of retrieving them
+    use_cache: bool,
+}
+
+impl Default for WebClient {
+    fn default() -> Self {
+        Self::new(&Url::parse(DEFAULT_BASE_URL).expect("Default base URL is valid"))
+    }
 }
 
 impl WebClient {
+    #[must_use]
+    pub fn new(base_url: &Url) -> Self {
+        Self {
+            client: Client::default(),
+            source: Arc::new(Usccb::default()),
+            base_url: base_url.as_str().trim_end_matches('/').to_owned(),
+            lenient: false,
+            cache_dir: None,
+            use_cache: false,
+        }
+    }
+
+    /// Sets whether pages missing a required reading are parsed leniently instead of failing
+    #[must_use]
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Sets the selectors used to find the readings on the pages of the site
+    #[must_use]
+    pub fn selectors(mut self, selectors: PageSelectors) -> Self {
+        self.source = Arc::new(Usccb::new(selectors));
+        self
+    }
+
+    /// Saves each retrieved page to `cache_dir`, named by the date (e.g. 071424.html). If `use_cache` is true, a saved page is
+    /// used instead of retrieving it again
+    #[must_use]
+    pub fn html_cache(mut self, cache_dir: PathBuf, use_cache: bool) -> Self {
+        self.cache_dir = Some(cache_dir);
+        self.use_cache = use_cache;
+        self
+    }
+
+    /// Sets whether pages saved in the cache directory are used instead of retrieving them from the site
+    ///
+    /// Uses the default cache directory if none was set with `html_cache`, so that pages that are not saved yet are saved
+    #[must_use]
+    pub fn use_cache(mut self, use_cache: bool) -> Self {
+        if use_cache && self.cache_dir.is_none() {
+            self.cache_dir = default_cache_dir();
+        }
+        self.use_cache = use_cache;
+        self
+    }
+
+    /// Creates a client using the base URL from the environment variable, then the config, then the default, and the
+    /// selectors from the config
+    ///
+    /// An invalid base URL is logged and the default is used instead. Pages are saved to the cache directory if the config
+    /// enables it
+    #[must_use]
+    pub(crate) fn from_env_or_config(config: &Config) -> Self {
+        let base_url = match env::var(BASE_URL_ENV_VAR) {
+            Ok(env_url) if !env_url.is_empty() => env_url,
+            _ => config.web.base_url.clone(),
+        };
+        let client = match Url::parse(&base_url) {
+            Ok(url) => {
+                if base_url != DEFAULT_BASE_URL {
+                    debug!("Using configured base URL '{url}'");
+                }
+                Self::new(&url)
+            }
+            Err(e) => {
+                error!("Invalid base URL '{base_url}' ({e}); Using '{DEFAULT_BASE_URL}'");
+                Self::default()
+            }
+        }
+        .selectors(PageSelectors::from_config(&config.selectors));
+        if !config.web.save_html {
+            return client;
+        }
+        match default_cache_dir() {
+            Some(cache_dir) => client.html_cache(cache_dir, false),
+            None => client,
+        }
+    }
+
+    /// Retrieves and parses the readings for a date. On holidays, uses the Mass during the Day
+    ///
+    /// # Errors
+    /// Returns a `WebGetError` if the page could not be retrieved or parsed
     pub async fn get_for_date_id(&self, date_id: DateId) -> Result<Lectionary, WebGetError> {
-        let url = Self::url_for_date(&date_id);
-        let document = self.get_document_from_url(url).await?;
+        let start = Instant::now();
+        let result = self.get_for_date_id_and_mass(date_id.clone(), None).await;
+        logging::log_timing(format_args!("Retrieving {date_id} from the web"), start);
+        result
+    }
+
+    /// Like `get_for_date_id`, but on days with more than one Mass, uses the Mass with a label containing `mass` (ignoring case)
+    ///
+    /// If `mass` is None, uses the Mass during the Day
+    ///
+    /// # Errors
+    /// Returns a `WebGetError` if the page could not be retrieved or parsed, or if no Mass matches `mass`
+    pub async fn get_for_date_id_and_mass(&self, date_id: DateId, mass: Option<&str>) -> Result<Lectionary, WebGetError> {
+        let Some(mass) = mass else {
+            let (url, document) = self.get_page_for_date_id(&date_id).await?;
+            return self.parse_lectionary(date_id, &document, &url);
+        };
+        let (url, document) = self.get_date_page(&date_id).await?;
+        let links = self.source.mass_links(&document);
+        if links.is_empty() {
+            warn!("{date_id} only has one Mass. Ignoring requested Mass '{mass}'");
+            return self.parse_lectionary(date_id, &document, &url);
+        }
+        let lowercase_mass = mass.to_lowercase();
+        let Some(link) = links.iter().find(|link| link.label.to_lowercase().contains(&lowercase_mass)) else {
+            return Err(WebGetError::MassNotFound {
+                requested: mass.to_owned(),
+                available: links.into_iter().map(|link| link.label).collect(),
+            });
+        };
+        info!("Using the link for '{}' on {date_id}", link.label);
+        let url = self.url_for_link(link.endpoint);
+        let document = self.get_document_from_url(url.clone()).await?;
+        self.parse_lectionary(date_id, &document, &url)
+    }
 
-        if let Some(endpoint) = html::get_holiday_day_reading_link(&document) {
+    /// Gets the readings page for the date without parsing it. On days with more than one Mass, gets the Mass during the Day
+    ///
+    /// Returns the URL the page was retrieved from along with the page
+    pub(crate) async fn get_page_for_date_id(&self, date_id: &DateId) -> Result<(Url, Html), WebGetError> {
+        let (url, document) = self.get_date_page(date_id).await?;
+        if let Some(endpoint) = self.source.day_mass_endpoint(&document) {
             info!("{date_id} seems to be a holiday. Using the link for the daytime reading");
-            let url = Self::url_for_link(endpoint);
-            let document = self.get_document_from_url(url).await?;
-            return Lectionary::create_from_html(date_id, &document).map_err(WebGetError::ParseError)
+            let url = self.url_for_link(endpoint);
+            let document = self.get_document_from_url(url.clone()).await?;
+            return Ok((url, document));
+        }
+        Ok((url, document))
+    }
+
+    /// Gets the page at the URL for the date, without following the link to any of its Masses
+    async fn get_date_page(&self, date_id: &DateId) -> Result<(Url, Html), WebGetError> {
+        let url = self.url_for_date(date_id);
+        match self.get_document_from_url(url.clone()).await {
+            Err(WebGetError::ErrorStatus(StatusCode::NOT_FOUND)) => Err(WebGetError::NotAvailable { date: date_id.clone() }),
+            result => Ok((url, result?)),
         }
+    }
+
+    /// Gets the inner HTML of the container of the readings on the page for the date, without parsing it in to a `Lectionary`
+    ///
+    /// On days with more than one Mass, uses the Mass during the Day
+    pub(crate) async fn get_container_html(&self, date_id: &DateId) -> Result<String, WebGetError> {
+        let (url, document) = self.get_page_for_date_id(date_id).await?;
+        debug!("Selecting the container of the readings from {url}");
+        self.source
+            .container_html(&document)
+            .ok_or_else(|| WebGetError::ParseError(LectionaryHtmlError::NoContainerFound { date: date_id.clone() }))
+    }
 
-        Lectionary::create_from_html(date_id, &document).map_err(WebGetError::ParseError)
+    /// Parses the page that was retrieved from `url`, which is kept as the source of the lectionary
+    fn parse_lectionary(&self, date_id: DateId, document: &Html, url: &Url) -> Result<Lectionary, WebGetError> {
+        info!("Parsing readings for {date_id} from {url}");
+        let start = Instant::now();
+        let result = self.source.parse_lectionary(date_id, document, self.lenient);
+        logging::log_timing(format_args!("Finding the readings in {url}"), start);
+        result
+            .map(|lectionary| lectionary.with_source_url(url.to_string()))
+            .map_err(WebGetError::ParseError)
     }
 
+    /// If the site responds with 429 Too Many Requests, waits as long as its Retry-After header asks and tries again
+    ///
+    /// Loads the page from the cache directory instead if it is enabled and has the page. Otherwise saves the page there
     async fn get_document_from_url(&self, url: Url) -> Result<Html, WebGetError> {
-        debug!("Sending GET request to {}", url);
-        let response = self.client.get(url).send().await.map_err(WebGetError::ClientError)?;
+        let cache_path = self.cache_path_for_url(&url);
+        if let Some(cache_path) = cache_path.as_ref().filter(|_| self.use_cache) {
+            match fs::read_to_string(cache_path) {
+                Ok(cached_text) => {
+                    info!("Using saved page '{}' instead of {url}", cache_path.to_string_lossy());
+                    return Ok(Html::parse_document(&cached_text));
+                }
+                Err(e) => debug!("No saved page for {url} ({e}); Retrieving from the site"),
+            }
+        }
+
+        let start = Instant::now();
+        let mut retries = 0;
+        let response = loop {
+            debug!("Sending GET request to {}", url);
+            let response = self.client.get(url.clone()).send().await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS || retries == MAX_RATE_LIMIT_RETRIES {
+                break response;
+            }
+            let delay = retry_after(&response, Utc::now()).unwrap_or_else(|| backoff_with_jitter(retries));
+            if delay > MAX_RETRY_DELAY {
+                warn!(
+                    "Rate limited by the site, which asked to wait {} seconds; Not retrying",
+                    delay.as_secs()
+                );
+                break response;
+            }
+            warn!("Rate limited by the site; Retrying in {:.1} seconds", delay.as_secs_f64());
+            tokio::time::sleep(delay).await;
+            retries += 1;
+        };
         if !response.status().is_success() {
             return Err(WebGetError::ErrorStatus(response.status()));
         }
 
         let response_text = response.text().await.map_err(WebGetError::ResponseError)?;
-        Ok(Html::parse_document(&response_text))
+        logging::log_timing(format_args!("Request for {url}"), start);
+        if let Some(cache_path) = cache_path {
+            match fs::write(&cache_path, &response_text) {
+                Ok(()) => debug!("Saved page for {url} to '{}'", cache_path.to_string_lossy()),
+                Err(e) => warn!("Failed to save page for {url} to '{}': {e}", cache_path.to_string_lossy()),
+            }
+        }
+        let start = Instant::now();
+        let document = Html::parse_document(&response_text);
+        logging::log_timing(format_args!("Parsing the HTML of {url}"), start);
+        Ok(document)
+    }
+
+    /// Where the page at `url` is saved, named after the last segment of its path (e.g. 071424.cfm is saved as 071424.html)
+    ///
+    /// None if the cache is disabled or the URL has no usable file name
+    fn cache_path_for_url(&self, url: &Url) -> Option<PathBuf> {
+        let cache_dir = self.cache_dir.as_ref()?;
+        let file_name = url.path_segments()?.next_back().filter(|segment| !segment.is_empty())?;
+        let stem = file_name.strip_suffix(".cfm").unwrap_or(file_name);
+        Some(cache_dir.join(format!("{stem}.html")))
     }
 
-    fn url_for_date(date_id: &DateId) -> Url {
-        let url_string = format!("{BASE_URL}/bible/readings/{date_id}.cfm");
+    /// # Panics
+    /// Will not panic since the base URL is always valid
+    #[must_use]
+    pub fn url_for_date(&self, date_id: &DateId) -> Url {
+        let url_string = format!("{}{}", self.base_url, self.source.endpoint_for_date(date_id));
         Url::parse(&url_string).expect("Formatted string is valid URL")
     }
 
     // Can be given either a full url or a relative one
-    fn url_for_link(link: &str) -> Url {
+    fn url_for_link(&self, link: &str) -> Url {
         if let Ok(url) = Url::parse(link) {
             url
         } else {
             let mut url_string = String::new();
-            url_string.push_str(BASE_URL);
+            url_string.push_str(&self.base_url);
             url_string.push_str(link);
             Url::parse(&url_string).expect("Base URL plus endpoint must be valid URL")
         }
     }
 }
 
+/// The directory pages are saved to. None if it can't be created, which is logged
+fn default_cache_dir() -> Option<PathBuf> {
+    path::create_and_get_cache_path()
+        .inspect_err(|e| warn!("Could not get cache directory ({e}); Pages will not be saved or loaded"))
+        .ok()
+}
+
+/// Gets the delay requested by the Retry-After header of the response, if it has a valid one
+fn retry_after(response: &Response, now: DateTime<Utc>) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, now)
+}
+
+/// Parses a Retry-After header value, which is either a number of seconds or an HTTP date (e.g. "Wed, 21 Oct 2015 07:28:00 GMT")
+///
+/// A date in the past means no delay
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&Utc) - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Delay before a retry when the site does not say how long to wait: 1, 2, 4... seconds plus up to half a second of jitter
+///
+/// The jitter keeps the requests of a bulk update from all being retried at the same moment
+fn backoff_with_jitter(retries: u32) -> Duration {
+    let jitter_millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.subsec_millis() % 500);
+    Duration::from_secs(1 << retries.min(5)) + Duration::from_millis(u64::from(jitter_millis))
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum WebGetError {
+    /// Could not connect to the site, including DNS failures and timeouts
+    #[error("Network unreachable on GET request: ({0})")]
+    NetworkUnreachable(#[source] reqwest::Error),
     #[error("Web client error on GET request: ({0})")]
     ClientError(#[source] reqwest::Error),
     #[error("Error status code on GET request: {0}")]
     ErrorStatus(StatusCode),
+    /// The site has no page for the date, typically because the readings have not been posted yet
+    #[error("Readings for {date} are not yet available")]
+    NotAvailable { date: DateId },
     #[error("Error reading response: ({0})")]
     ResponseError(#[source] reqwest::Error),
     #[error("Error creating lectionary from html: ({0})")]
     ParseError(#[source] LectionaryHtmlError),
+    #[error("No Mass matching '{requested}'. Available Masses: {}", available.join(", "))]
+    MassNotFound { requested: String, available: Vec<String> },
+}
+
+impl WebGetError {
+    /// True if the failure was caused by being unable to reach the site, meaning a retry may succeed later
+    #[must_use]
+    pub fn is_network_unreachable(&self) -> bool {
+        matches!(self, Self::NetworkUnreachable(_))
+    }
+
+    /// True if the site has no page for the date, meaning the readings may be posted later
+    #[must_use]
+    pub fn is_not_available(&self) -> bool {
+        matches!(self, Self::NotAvailable { .. })
+    }
+
+    /// True if the site has no readings page for the date, either because it was not found or it is missing the readings
+    #[must_use]
+    pub fn is_missing_page(&self) -> bool {
+        match self {
+            Self::ErrorStatus(status) => *status == StatusCode::NOT_FOUND,
+            Self::NotAvailable { .. }
+            | Self::ParseError(LectionaryHtmlError::NoContainerFound { .. } | LectionaryHtmlError::MissingReading { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for WebGetError {
+    fn from(value: reqwest::Error) -> Self {
+        if value.is_connect() || value.is_timeout() {
+            Self::NetworkUnreachable(value)
+        } else {
+            Self::ClientError(value)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -77,21 +396,179 @@ mod tests {
     #[test]
     fn correct_url_for_date() {
         let date_id = DateId::checked_from_str("072024").unwrap();
-        let url = WebClient::url_for_date(&date_id);
-        assert_eq!(url.origin().ascii_serialization(), BASE_URL);
+        let url = WebClient::default().url_for_date(&date_id);
+        assert_eq!(url.origin().ascii_serialization(), DEFAULT_BASE_URL);
         assert_eq!(url.path(), "/bible/readings/072024.cfm");
     }
 
     #[test]
     fn correct_url_for_endpoint() {
-        let url = WebClient::url_for_link("/example/endpoint");
-        assert_eq!(url.origin().ascii_serialization(), BASE_URL);
+        let url = WebClient::default().url_for_link("/example/endpoint");
+        assert_eq!(url.origin().ascii_serialization(), DEFAULT_BASE_URL);
         assert_eq!(url.path(), "/example/endpoint");
     }
 
+    #[tokio::test]
+    async fn connection_failure_is_network_unreachable() {
+        // Nothing should be listening on port 1
+        let error = Client::new().get("http://127.0.0.1:1").send().await.unwrap_err();
+        assert!(WebGetError::from(error).is_network_unreachable());
+    }
+
+    /// Serves a single response with the given body from a local server, returning the base URL of the server
+    async fn serve_once(body: String) -> Url {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        base_url
+    }
+
+    #[tokio::test]
+    async fn get_from_local_server() {
+        let body = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        let client = WebClient::new(&serve_once(body).await);
+        let date_id = DateId::checked_from_str("071424").unwrap();
+        let lectionary = client.get_for_date_id(date_id.clone()).await.unwrap();
+        assert_eq!(&date_id, lectionary.get_id());
+        assert_eq!("Fifteenth Sunday in Ordinary Time", lectionary.get_day_name());
+        assert_eq!(Some(client.url_for_date(&date_id).as_str()), lectionary.get_source_url());
+    }
+
+    #[test]
+    fn url_uses_base_url() {
+        let client = WebClient::new(&Url::parse("http://127.0.0.1:8080/mirror/").unwrap());
+        let date_id = DateId::checked_from_str("072024").unwrap();
+        assert_eq!(
+            "http://127.0.0.1:8080/mirror/bible/readings/072024.cfm",
+            client.url_for_date(&date_id).as_str()
+        );
+    }
+
+    #[test]
+    fn not_found_is_missing_page() {
+        assert!(WebGetError::ErrorStatus(StatusCode::NOT_FOUND).is_missing_page());
+        assert!(!WebGetError::ErrorStatus(StatusCode::INTERNAL_SERVER_ERROR).is_missing_page());
+    }
+
+    #[test]
+    fn retry_after_seconds_or_date() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(Some(Duration::from_secs(45)), parse_retry_after("45", now));
+        assert_eq!(Some(Duration::from_secs(0)), parse_retry_after(" 0 ", now));
+        assert_eq!(
+            Some(Duration::from_secs(90)),
+            parse_retry_after("Wed, 21 Oct 2015 07:29:30 GMT", now)
+        );
+        assert_eq!(Some(Duration::ZERO), parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now));
+        assert_eq!(None, parse_retry_after("soon", now));
+        assert_eq!(None, parse_retry_after("-5", now));
+    }
+
+    #[test]
+    fn backoff_doubles_with_bounded_jitter() {
+        for retries in 0..3 {
+            let delay = backoff_with_jitter(retries);
+            let base = Duration::from_secs(1 << retries);
+            assert!(delay >= base && delay < base + Duration::from_millis(500));
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_after_too_many_requests() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        let body = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+
+        let client = WebClient::new(&Url::parse(&server.uri()).unwrap());
+        let lectionary = client.get_for_date_id(DateId::checked_from_str("071424").unwrap()).await.unwrap();
+        assert_eq!("Fifteenth Sunday in Ordinary Time", lectionary.get_day_name());
+    }
+
+    #[tokio::test]
+    async fn saves_and_loads_cached_pages() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let cache_dir = env::temp_dir().join(format!("lectio-diei-html-cache-{}", std::process::id()));
+        fs::create_dir_all(&cache_dir).unwrap();
+        let date_id = DateId::checked_from_str("071424").unwrap();
+
+        let saving_client = WebClient::new(&Url::parse(&server.uri()).unwrap()).html_cache(cache_dir.clone(), false);
+        saving_client.get_for_date_id(date_id.clone()).await.unwrap();
+        assert!(cache_dir.join("071424.html").is_file());
+
+        let cached_client = WebClient::new(&Url::parse(&server.uri()).unwrap()).html_cache(cache_dir.clone(), true);
+        let lectionary = cached_client.get_for_date_id(date_id).await.unwrap();
+        assert_eq!("Fifteenth Sunday in Ordinary Time", lectionary.get_day_name());
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn container_html_is_unparsed() {
+        let body = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        let client = WebClient::new(&serve_once(body).await);
+        let container_html = client
+            .get_container_html(&DateId::checked_from_str("071424").unwrap())
+            .await
+            .unwrap();
+        assert!(container_html.contains("Fifteenth Sunday in Ordinary Time"));
+        assert!(container_html.contains("<div"));
+
+        let client = WebClient::new(&serve_once(String::from("<html><body><p>Not found</p></body></html>")).await);
+        let error = client
+            .get_container_html(&DateId::checked_from_str("071424").unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            WebGetError::ParseError(LectionaryHtmlError::NoContainerFound { .. })
+        ));
+    }
+
+    #[test]
+    fn not_available_is_missing_page() {
+        let error = WebGetError::NotAvailable {
+            date: DateId::checked_from_str("072024").unwrap(),
+        };
+        assert!(error.is_not_available());
+        assert!(error.is_missing_page());
+        assert_eq!("Readings for 072024 are not yet available", error.to_string());
+        assert!(!WebGetError::ErrorStatus(StatusCode::NOT_FOUND).is_not_available());
+    }
+
     #[test]
     fn correct_url_for_absolute() {
-        let url = WebClient::url_for_link("https://example.com/example/endpoint");
+        let url = WebClient::default().url_for_link("https://example.com/example/endpoint");
         assert_eq!(url.origin().ascii_serialization(), "https://example.com");
         assert_eq!(url.path(), "/example/endpoint");
     }
@@ -132,7 +132,7 @@ impl ColorfulLogger {
         // On error, record level
         if record.level() == Level::Error {
             write!(terminal_stream, "{}: ", record.level())?;
-        };
+        }
 
         writeln!(terminal_stream, "{}", record.args())?;
 
@@ -159,8 +159,9 @@ impl ColorfulLogger {
 }
 
 impl Log for ColorfulLogger {
+    /// Info, Debug, and Trace records are only enabled for this crate so that dependencies don't flood the terminal
     fn enabled(&self, metadata: &Metadata<'_>) -> bool {
-        metadata.level() <= self.level
+        metadata.level() <= self.level && (metadata.level() <= Level::Warn || metadata.target().starts_with(env!("CARGO_CRATE_NAME")))
     }
 
     fn log(&self, record: &Record<'_>) {
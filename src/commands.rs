@@ -1,18 +1,35 @@
-use chrono::{Local, ParseError, TimeDelta};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::sync::Arc;
+
+use chrono::{DateTime, Local, ParseError, TimeDelta};
+use clap::CommandFactory;
+use clap_complete::Shell;
+use futures_util::TryStreamExt;
 use log::*;
+use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
 
-use crate::args::{CommonArguments, ConfigCommand, FormattingArgs};
+use crate::args::{
+    Arguments, Command, CommonArguments, ConfigCommand, FormattingArgs, OutputArgs, ReadingLengthArgs, RetrievalArgs, ShowFormat, ShowSort,
+};
 use crate::client::WebClient;
-use crate::config::{Config, DbConfig, InitConfigError, ReadConfigError};
-use crate::db::{DatabaseGetError, DatabaseInitError};
-use crate::display::DisplaySettings;
-use crate::orchestration::{DatabaseError, RetrievalError};
+use crate::config::{CheckConfigError, Config, DbConfig, InitConfigError, ProfileError, ReadConfigError, SetConfigError, UnknownKeyError};
+use crate::db::{DatabaseGetError, DatabaseInitError, LectionaryRow};
+use crate::display::{self, DisplaySettings};
+use crate::html::PageSelectors;
+use crate::orchestration::{DatabaseError, ParseFileError, RetrievalError, StoreOutcome};
 use crate::{
     args::{DatabaseCommand, DisplayReadingsArgs},
     date::DateId,
     db::DatabaseHandle,
+    export,
+    lectionary::Lectionary,
     orchestration,
+    path::{self, PathError},
+    rank::Rank,
 };
 
 /// Command: display
@@ -21,41 +38,245 @@ use crate::{
 /// # Errors
 ///  Returns an `ApplicationError` if the command encounterd a fatal error
 pub async fn display(
-    maybe_date_string: Option<String>,
+    date_strings: Vec<String>,
     readings: DisplayReadingsArgs,
     formatting: FormattingArgs,
+    retrieval: RetrievalArgs,
+    length: ReadingLengthArgs,
+    output: OutputArgs,
     args: CommonArguments,
 ) -> Result<(), ApplicationError> {
-    let date_id = if let Some(date_string) = maybe_date_string {
-        DateId::checked_from_str(&date_string).map_err(ArgumentError::InvalidDate)?
-    } else {
+    let config_path = args.config.as_deref();
+    let mut config = Config::from_file_or_default(config_path);
+    if let Some(profile) = &output.profile {
+        config = config.with_profile(profile)?;
+    }
+    let date_format = retrieval.date_format.clone().unwrap_or_else(|| config.display.date_format.clone());
+
+    let date_ids = if let Some(text) = &retrieval.find {
+        let Some(date_id) = find_day(text, config_path).await? else {
+            return Ok(());
+        };
+        vec![date_id]
+    } else if date_strings.is_empty() {
         let today = DateId::today();
         info!("No date specified. Using '{}'", today);
-        today
+        vec![today]
+    } else {
+        date_strings
+            .iter()
+            .map(|date_string| DateId::parse_with_format(date_string, &date_format))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ArgumentError::InvalidDate)?
+    };
+
+    let single_reading = match &readings.readings {
+        Some(list) => display::dedup_readings(list).len() == 1,
+        None => readings.flagged_readings().len() == 1,
     };
+    if output.raw && !single_reading {
+        return Err(ArgumentError::RawRequiresOneReading.into());
+    }
+    // The page is only read once, so it can only be shown for one date
+    if retrieval.from_file.is_some() && date_ids.len() > 1 {
+        return Err(ArgumentError::FromFileWithSeveralDates.into());
+    }
+    let date_ids = if let Some(until_string) = &retrieval.until {
+        let [date_id] = date_ids.as_slice() else {
+            return Err(ArgumentError::UntilWithSeveralDates.into());
+        };
+        let until = DateId::parse_with_format(until_string, &date_format).map_err(ArgumentError::InvalidDate)?;
+        if &until < date_id {
+            return Err(ArgumentError::UntilBeforeDate.into());
+        }
+        DateId::get_range(date_id, &until)
+    } else {
+        date_ids
+    };
+
+    let client = WebClient::from_env_or_config(&config)
+        .lenient(retrieval.lenient)
+        .use_cache(retrieval.use_cache);
+    if output.web {
+        for date_id in &date_ids {
+            open_in_browser(&client, date_id);
+        }
+        return Ok(());
+    }
+
+    if output.raw_html {
+        for date_id in &date_ids {
+            print_raw_html(&client, date_id).await?;
+        }
+        return Ok(());
+    }
+    let selectors = PageSelectors::from_config(&config.selectors);
+    let lenient = retrieval.lenient;
+    let ephemeral = retrieval.ephemeral;
+    let from_file = retrieval.from_file.clone();
+    let settings = DisplaySettings::from_config_and_args(config, readings, formatting, retrieval, length, &output, &args);
 
-    let config = Config::from_file_or_default();
-    let settings = DisplaySettings::from_config_and_args(config, readings, formatting, args);
+    let mut lectionaries = Vec::with_capacity(date_ids.len());
+    for date_id in date_ids {
+        let lectionary = if let Some(path) = &from_file {
+            orchestration::parse_file(date_id.clone(), path, &selectors, lenient)?
+        } else if let Some(mass) = &settings.mass {
+            orchestration::fetch_mass(date_id.clone(), mass, &client).await?
+        } else {
+            orchestration::retrieve_nearest_lectionary(
+                date_id.clone(),
+                &client,
+                settings.offline,
+                ephemeral,
+                settings.store,
+                settings.nearest,
+                config_path,
+            )
+            .await?
+        };
+        if lectionary.get_id() != &date_id {
+            warn!("Displaying the readings for '{}' instead of '{date_id}'", lectionary.get_id());
+        }
+        if output.show_source {
+            print_source(&client, &lectionary);
+        }
+        lectionaries.push(lectionary);
+    }
+    write_lectionaries(&lectionaries, &settings, output.output).map_err(ApplicationError::from)
+}
 
-    orchestration::retrieve_and_display(date_id, settings)
+/// Searches the database for the day with a name containing `text`
+///
+/// If more than one day matches, writes each of them to STDOUT and returns None
+async fn find_day(text: &str, config_path: Option<&Path>) -> Result<Option<DateId>, ApplicationError> {
+    let db = DatabaseHandle::new(config_path).await.map_err(DatabaseError::from)?;
+    let mut rows = db
+        .find_by_name(text)
         .await
-        .map_err(ApplicationError::RetrievalError)
+        .map_err(|e| DatabaseError::from(DatabaseGetError::from(e)))?;
+    match rows.len() {
+        0 => Err(ArgumentError::NoDayFound(text.to_owned()).into()),
+        1 => {
+            let row = rows.remove(0);
+            info!("Found '{}' on {}", row.name, row.id);
+            Ok(Some(row.id))
+        }
+        count => {
+            warn!("{count} stored days have a name containing '{text}'. Use --date with one of them");
+            for row in rows {
+                println!("{} {}", row.id, row.name);
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Writes the URL the lectionary was retrieved from to STDERR, so that it doesn't end up in the displayed readings
+fn print_source(client: &WebClient, lectionary: &Lectionary) {
+    if let Some(url) = lectionary.get_source_url() {
+        eprintln!("Source for {}: {url}", lectionary.get_id());
+    } else {
+        let url = client.url_for_date(lectionary.get_id());
+        eprintln!("Source for {}: {url} (stored in the database)", lectionary.get_id());
+    }
+}
+
+/// Writes the HTML of the container of the readings on the page for the date to STDOUT, for attaching to bug reports
+async fn print_raw_html(client: &WebClient, date_id: &DateId) -> Result<(), ApplicationError> {
+    warn!("--raw-html is for debugging. The output is the markup of the site, not the readings");
+    let container_html = client.get_container_html(date_id).await.map_err(RetrievalError::from)?;
+    println!("{container_html}");
+    Ok(())
+}
+
+/// Opens the USCCB page for the date in the default browser. Falls back to writing the URL to STDOUT
+fn open_in_browser(client: &WebClient, date_id: &DateId) {
+    let url = client.url_for_date(date_id);
+    info!("Opening {url} in the default browser");
+    if let Err(e) = webbrowser::open(url.as_str()) {
+        warn!("Could not open a web browser: {e}");
+        println!("{url}");
+    }
+}
+
+/// Writes the lectionaries to the file at `output_path`, or to STDOUT if there is no path
+fn write_lectionaries(lectionaries: &[Lectionary], settings: &DisplaySettings, output_path: Option<PathBuf>) -> Result<(), OutputError> {
+    let mut writer: Box<dyn Write> = if let Some(path) = output_path {
+        let file = File::create(&path).map_err(|source| OutputError::CannotCreate { path, source })?;
+        Box::new(BufWriter::new(file))
+    } else {
+        Box::new(io::stdout().lock())
+    };
+    display::print_lectionaries(lectionaries, settings, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Global option: --config
+///
+/// Returns `path` resolved against the current directory, to be passed to everything that reads the config
+/// # Errors
+/// Returns an `ApplicationError` if the path can't be resolved, or if there is no file at `path` and the command doesn't create one
+pub fn use_config_path(path: &Path, command: &Command) -> Result<PathBuf, ApplicationError> {
+    Config::use_config_path(path, !writes_config(command)).map_err(ApplicationError::from)
+}
+
+/// Global option: --no-create-config
+///
+/// Unless `no_create_config` is true, creates the config file with the default settings when there isn't one. Commands that
+/// write the config create it themselves, so nothing is created for them
+pub fn create_missing_config(no_create_config: bool, config_path: Option<&Path>, command: &Command) {
+    if !no_create_config && !writes_config(command) {
+        Config::create_if_missing(config_path);
+    }
+}
+
+/// True for the commands that create the config file if there isn't one
+fn writes_config(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::Config {
+            command: ConfigCommand::Init { .. } | ConfigCommand::Upgrade | ConfigCommand::Set { .. }
+        }
+    )
 }
 
 /// Command: db
 ///
 /// # Errors
 /// Returns an `ApplicationError` if the command encounterd a fatal error
-pub async fn handle_db_command(subcommand: DatabaseCommand) -> Result<(), ApplicationError> {
+pub async fn handle_db_command(subcommand: DatabaseCommand, args: CommonArguments) -> Result<(), ApplicationError> {
+    // Progress is written to STDERR and would clutter the logs of scripts, so it is only shown when STDERR is a terminal
+    let show_progress = !args.quiet && io::stderr().is_terminal();
+    let config_path = args.config.as_deref();
     match subcommand {
-        DatabaseCommand::Remove { dates } => remove_entries(dates).await.map_err(ApplicationError::from),
-        DatabaseCommand::Count => count_entries().await.map_err(ApplicationError::from),
-        DatabaseCommand::Update => update_db().await.map_err(ApplicationError::from),
-        DatabaseCommand::Show => show_db().await.map_err(ApplicationError::from),
-        DatabaseCommand::Purge => purge_db().await.map_err(ApplicationError::from),
-        DatabaseCommand::Clean { all } => clean_db(all).await.map_err(ApplicationError::from),
-        DatabaseCommand::Refresh => refresh_db().await.map_err(ApplicationError::from),
-        DatabaseCommand::Store { dates } => add_entries(dates).await.map_err(ApplicationError::from),
+        DatabaseCommand::Remove { yes, dates } => remove_entries(config_path, dates, yes).await.map_err(ApplicationError::from),
+        DatabaseCommand::Count => count_entries(config_path).await.map_err(ApplicationError::from),
+        DatabaseCommand::Update {
+            jobs,
+            past,
+            future,
+            date,
+            sundays_only,
+            retry_failed,
+        } => update_db(config_path, jobs, past, future, date, sundays_only, retry_failed, show_progress).await,
+        DatabaseCommand::Show {
+            filter,
+            since,
+            before,
+            sort,
+            timestamps,
+            format,
+        } => show_db(config_path, filter, since, before, sort, timestamps, format).await,
+        DatabaseCommand::Stats => stats_db(config_path).await.map_err(ApplicationError::from),
+        DatabaseCommand::Export { output } => export_db(output, &args).await,
+        DatabaseCommand::Migrate => migrate_db(config_path).await.map_err(ApplicationError::from),
+        DatabaseCommand::Purge { dry_run, yes } => purge_db(config_path, dry_run, yes).await.map_err(ApplicationError::from),
+        DatabaseCommand::Clean { all, dry_run, list, yes } => {
+            clean_db(config_path, all, dry_run, list, yes).await.map_err(ApplicationError::from)
+        }
+        DatabaseCommand::Refresh { jobs } => refresh_db(config_path, jobs, show_progress).await.map_err(ApplicationError::from),
+        DatabaseCommand::Store { dates, from, to } => add_entries(config_path, dates, from, to, show_progress).await,
     }
 }
 
@@ -63,22 +284,134 @@ pub async fn handle_db_command(subcommand: DatabaseCommand) -> Result<(), Applic
 ///
 /// # Errors
 /// Returns an `ApplicationError` if the config encountered a fatal error
-pub fn handle_config_command(subcommand: ConfigCommand) -> Result<(), ApplicationError> {
+pub fn handle_config_command(subcommand: ConfigCommand, config_path: Option<&Path>) -> Result<(), ApplicationError> {
     match subcommand {
-        ConfigCommand::Init { force } => init_config(force).map_err(ApplicationError::from),
-        ConfigCommand::Upgrade => upgrade_config().map_err(ApplicationError::ReadConfigError),
-        ConfigCommand::Show => {
-            show_config();
-            Ok(())
+        ConfigCommand::Init { force } => init_config(config_path, force).map_err(ApplicationError::from),
+        ConfigCommand::Upgrade => upgrade_config(config_path).map_err(ApplicationError::ReadConfigError),
+        ConfigCommand::Show => show_config(config_path).map_err(ApplicationError::ReadConfigError),
+        ConfigCommand::Check => check_config(config_path).map_err(ApplicationError::from),
+        ConfigCommand::Get { key } => get_config(config_path, &key).map_err(ApplicationError::from),
+        ConfigCommand::Set { key, value } => set_config(config_path, &key, &value).map_err(ApplicationError::from),
+    }
+}
+
+/// Command: completions
+///
+/// Writes the completion script for `shell` to STDOUT
+pub fn print_completions(shell: Shell) {
+    write_completions(shell, &mut io::stdout());
+}
+
+/// Command: doctor
+///
+/// Writes the version and the location of each file used, with whether it exists and is writable, to STDOUT. Unless
+/// `offline`, then checks each step of retrieving today's readings from the web
+///
+/// Never fails. If a location can't be resolved or a step fails, the reason is written instead
+pub async fn doctor(config_path: Option<&Path>, log_file: Option<&Path>, no_log_file: bool, offline: bool) {
+    println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    println!("{}", location_line("Config", Config::get_path(config_path)));
+    println!("{}", location_line("Database", DatabaseHandle::get_db_path(config_path)));
+    if no_log_file {
+        println!("Log: disabled");
+    } else {
+        let log_path = log_file.map_or_else(path::create_and_get_log_path, path::create_and_resolve_path);
+        println!("{}", location_line("Log", log_path));
+    }
+    if !offline {
+        let config = Config::from_file_or_default(config_path);
+        let selectors = PageSelectors::from_config(&config.selectors);
+        for line in web_check_lines(&WebClient::from_env_or_config(&config), &selectors, DateId::today()).await {
+            println!("{line}");
+        }
+    }
+}
+
+/// Retrieves the readings page for the date and reports on each step separately: reaching the site, finding the readings on
+/// the page, and parsing each reading. Nothing is stored
+async fn web_check_lines(client: &WebClient, selectors: &PageSelectors, date_id: DateId) -> Vec<String> {
+    let mut lines = Vec::new();
+    let (url, document) = match client.get_page_for_date_id(&date_id).await {
+        Ok(page) => page,
+        Err(e) => {
+            lines.push(format!("Site: could not retrieve {} ({e})", client.url_for_date(&date_id)));
+            return lines;
         }
+    };
+    lines.push(format!("Site: retrieved {url}"));
+    let lectionary = match Lectionary::create_from_html_lenient(date_id, &document, selectors) {
+        Ok(lectionary) => lectionary,
+        Err(e) => {
+            lines.push(format!("Page: readings not found ({e})"));
+            return lines;
+        }
+    };
+    lines.push(format!("Page: readings found for '{}'", lectionary.get_day_name()));
+    let parsed: Vec<String> = lectionary.readings().map(|(name, _)| name.to_string()).collect();
+    lines.push(format!("Readings parsed: {}", list_or_none(&parsed)));
+    let missing: Vec<String> = lectionary.missing_readings().iter().map(ToString::to_string).collect();
+    lines.push(format!("Readings missing: {}", list_or_none(&missing)));
+    lines
+}
+
+fn list_or_none(items: &[String]) -> String {
+    if items.is_empty() {
+        String::from("none")
+    } else {
+        items.join(", ")
+    }
+}
+
+fn location_line(label: &str, location: Result<PathBuf, PathError>) -> String {
+    match location {
+        Ok(path) => format!("{label}: {} ({})", path.to_string_lossy(), file_status(&path)),
+        Err(e) => format!("{label}: could not be resolved ({e})"),
     }
 }
 
+/// Whether the file exists and can be written, without changing it
+fn file_status(path: &Path) -> String {
+    if !path.exists() {
+        return String::from("does not exist");
+    }
+    if !path.is_file() {
+        return String::from("exists, not a file");
+    }
+    match OpenOptions::new().append(true).open(path) {
+        Ok(_) => String::from("exists, writable"),
+        Err(e) => format!("exists, not writable: {e}"),
+    }
+}
+
+/// Command: man
+///
+/// Writes a man page for the application and each subcommand to `dir`
+/// # Errors
+/// Returns a `ManPageError` if the directory can't be created or a page can't be written
+pub fn write_man_pages(dir: &Path) -> Result<(), ManPageError> {
+    std::fs::create_dir_all(dir).map_err(|source| ManPageError::CannotCreateDir {
+        path: dir.to_owned(),
+        source,
+    })?;
+    clap_mangen::generate_to(Arguments::command(), dir).map_err(|source| ManPageError::WriteError {
+        path: dir.to_owned(),
+        source,
+    })?;
+    info!("Wrote man pages to '{}'", dir.display());
+    Ok(())
+}
+
+fn write_completions(shell: Shell, out: &mut dyn Write) {
+    let mut command = Arguments::command();
+    let bin_name = command.get_name().to_owned();
+    clap_complete::generate(shell, &mut command, bin_name, out);
+}
+
 /// Subcommand: db count
 ///
 /// Counts number of lectionaries and prints that to STDOUT
-async fn count_entries() -> Result<(), DatabaseError> {
-    let db = DatabaseHandle::new().await?;
+async fn count_entries(config_path: Option<&Path>) -> Result<(), DatabaseError> {
+    let db = DatabaseHandle::new(config_path).await?;
     let count = db.get_lectionary_count().await.map_err(DatabaseGetError::from)?;
 
     println!("{count}");
@@ -88,9 +421,15 @@ async fn count_entries() -> Result<(), DatabaseError> {
 /// Subcommand: db remove
 ///
 /// Removes a list of entries. Sends removed count to STDOUT
-async fn remove_entries(date_strings: Vec<String>) -> Result<(), DatabaseInitError> {
+/// Unless `yes` is true, may ask for confirmation first (see `confirm_removal`)
+async fn remove_entries(config_path: Option<&Path>, date_strings: Vec<String>, yes: bool) -> Result<(), DatabaseInitError> {
     let date_ids: Vec<DateId> = convert_valid_date_list(&date_strings);
-    let db = DatabaseHandle::new().await?;
+    if should_confirm(yes) && !confirm_removal(date_ids.len() as u64) {
+        info!("Remove aborted");
+        println!("0");
+        return Ok(());
+    }
+    let db = DatabaseHandle::new(config_path).await?;
     let mut removed_count = 0;
     for id in date_ids {
         let remove_result = db.remove_lectionary(&id).await;
@@ -101,7 +440,7 @@ async fn remove_entries(date_strings: Vec<String>) -> Result<(), DatabaseInitErr
             }
             Ok(false) => info!("Tried to remove lectionary '{id}' but it was not present"),
             Err(e) => error!("Failed to remove lectionary '{id}': {e}"),
-        };
+        }
     }
 
     println!("{removed_count}");
@@ -110,16 +449,40 @@ async fn remove_entries(date_strings: Vec<String>) -> Result<(), DatabaseInitErr
 
 /// Subcommand: db store
 ///
-/// Stores a list of entries. Sends added count to STDOUT
-async fn add_entries(date_strings: Vec<String>) -> Result<(), DatabaseInitError> {
-    let date_ids = convert_valid_date_list(&date_strings);
-    let db = DatabaseHandle::new().await?;
-    let web_client = WebClient::default();
-    let added = ensure_list_stored(&db, date_ids, &web_client).await;
-
-    println!("{added}");
+/// Stores a list of entries, along with the range from `from` to `to` if given. Sends added count to STDOUT
+async fn add_entries(
+    config_path: Option<&Path>,
+    date_strings: Vec<String>,
+    from: Option<String>,
+    to: Option<String>,
+    show_progress: bool,
+) -> Result<(), ApplicationError> {
+    let mut date_ids = convert_valid_date_list(&date_strings);
+    if let (Some(from), Some(to)) = (from, to) {
+        date_ids.extend(parse_store_range(&from, &to)?);
+    }
+    date_ids.sort();
+    date_ids.dedup();
+    let db = DatabaseHandle::new(config_path).await?;
+    let config = Config::from_file_or_default(config_path);
+    let web_client = WebClient::from_env_or_config(&config);
+    let max_concurrent = config.database.max_concurrent_requests;
+    let counts = ensure_list_stored(&db, date_ids, &web_client, max_concurrent, show_progress, None).await;
+
+    println!("{}", counts.added);
     Ok(())
 }
+
+/// Parses the dates given to --from and --to, returning every date between them, including both
+fn parse_store_range(from: &str, to: &str) -> Result<Vec<DateId>, ArgumentError> {
+    let first = DateId::checked_from_str(from)?;
+    let last = DateId::checked_from_str(to)?;
+    if last < first {
+        return Err(ArgumentError::ToBeforeFrom);
+    }
+    Ok(DateId::get_range(&first, &last))
+}
+
 /// Converts a list of Strings to a list (typically arguments) to a list of `DateIds`
 /// Ignores invalid strings with a warning
 fn convert_valid_date_list(date_strings: &[String]) -> Vec<DateId> {
@@ -136,27 +499,119 @@ fn convert_valid_date_list(date_strings: &[String]) -> Vec<DateId> {
         .collect()
 }
 
+/// Subcommand: db migrate
+///
+/// Runs any pending migrations and writes the version of the latest migration applied
+async fn migrate_db(config_path: Option<&Path>) -> Result<(), DatabaseError> {
+    let db = DatabaseHandle::new(config_path).await?;
+    db.migrate().await.map_err(DatabaseInitError::MigrationError)?;
+    if let Some(version) = db.get_schema_version().await.map_err(DatabaseGetError::from)? {
+        println!("{version}");
+    } else {
+        warn!("No migrations have been applied to the database");
+    }
+    Ok(())
+}
+
 /// Subcommand: db purge
 ///
 /// Removes all rows from the database and writes the number of rows removed
-async fn purge_db() -> Result<(), DatabaseError> {
-    let db = DatabaseHandle::new().await?;
+/// If `dry_run` is true, writes the number of rows that would be removed instead
+/// Unless `yes` is true, may ask for confirmation first (see `confirm_removal`)
+async fn purge_db(config_path: Option<&Path>, dry_run: bool, yes: bool) -> Result<(), DatabaseError> {
+    let db = DatabaseHandle::new(config_path).await?;
+    if dry_run || should_confirm(yes) {
+        let count = db.get_lectionary_count().await.map_err(DatabaseGetError::from)?;
+        if dry_run {
+            println!("{count}");
+            return Ok(());
+        }
+        if !confirm_removal(count) {
+            info!("Purge aborted");
+            println!("0");
+            return Ok(());
+        }
+    }
     let entries_removed = db.remove_all().await.map_err(DatabaseError::DeleteError)?;
 
     println!("{entries_removed}");
     Ok(())
 }
 
+/// Number of rows the destructive db commands can remove without asking for confirmation
+const CONFIRMATION_THRESHOLD: u64 = 10;
+
+/// True if a destructive db command should ask for confirmation: `yes` was not given and both STDIN and STDOUT are terminals
+fn should_confirm(yes: bool) -> bool {
+    !yes && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Asks for confirmation before removing more than `CONFIRMATION_THRESHOLD` rows. Returns true if the rows may be removed
+///
+/// Should only be called if `should_confirm` is true
+fn confirm_removal(count: u64) -> bool {
+    count <= CONFIRMATION_THRESHOLD || confirm(&format!("Remove {count} lectionaries from the database?"))
+}
+
+/// Asks the user a yes or no question on STDERR and reads the answer from STDIN
+///
+/// Anything other than "y" (case-insensitive) counts as no
+fn confirm(question: &str) -> bool {
+    eprint!("{question} [y/N] ");
+    // If STDERR cannot be flushed, the answer is still read
+    let _ = io::stderr().flush();
+    read_confirmation(&mut io::stdin().lock())
+}
+
+fn read_confirmation(input: &mut dyn BufRead) -> bool {
+    let mut answer = String::new();
+    match input.read_line(&mut answer) {
+        Ok(_) => answer.trim().eq_ignore_ascii_case("y"),
+        Err(e) => {
+            warn!("Failed to read confirmation: {e}");
+            false
+        }
+    }
+}
+
 /// Subcommand: db clean
 ///
 /// Removes rows that are too old in accordance with the config file
 /// If all is true, also removes entries that are too far in the future
-async fn clean_db(all: bool) -> Result<(), DatabaseError> {
-    let db = DatabaseHandle::new().await?;
-    let config = Config::from_file_or_default();
-    let num_removed = clean_db_inner(&db, config.database, all).await?;
+/// Unless `yes` is true, may ask for confirmation first (see `confirm_removal`)
+#[allow(clippy::fn_params_excessive_bools)]
+async fn clean_db(config_path: Option<&Path>, all: bool, dry_run: bool, list: bool, yes: bool) -> Result<(), DatabaseError> {
+    let db = DatabaseHandle::new(config_path).await?;
+    let config = Config::from_file_or_default(config_path);
+    if dry_run || should_confirm(yes) {
+        let (earliest, latest) = clean_range(&config.database, all);
+        let ids = db
+            .ids_outside_range(&earliest, Some(&latest))
+            .await
+            .map_err(DatabaseGetError::from)?;
+        if dry_run {
+            for id in ids {
+                println!("{id}");
+            }
+            return Ok(());
+        }
+        if !confirm_removal(ids.len() as u64) {
+            info!("Clean aborted");
+            if !list {
+                println!("0");
+            }
+            return Ok(());
+        }
+    }
 
-    println!("{num_removed}");
+    let removed = clean_db_inner(&db, &config.database, all).await?;
+    if list {
+        for id in removed {
+            println!("{id}");
+        }
+    } else {
+        println!("{}", removed.len());
+    }
     Ok(())
 }
 
@@ -164,11 +619,62 @@ async fn clean_db(all: bool) -> Result<(), DatabaseError> {
 ///
 /// Retrieves entries from the web and stores in the database
 /// Entries retrieved will depend on the config settings
-async fn update_db() -> Result<(), DatabaseInitError> {
-    let db = DatabaseHandle::new().await?;
-    let web_client = WebClient::default();
-    let db_config = Config::from_file_or_default().database;
-    let num_added = update_db_inner(&db, db_config, &web_client).await;
+/// If jobs is given, it overrides the maximum number of concurrent requests from the config
+/// If past or future is given, it overrides the range of dates from the config. If date is given, only that date is stored
+/// If `sundays_only` is true, only the Sundays within the range are stored
+/// If `retry_failed` is true, only the dates that the last update failed to store are stored
+#[allow(clippy::fn_params_excessive_bools, clippy::too_many_arguments)]
+async fn update_db(
+    config_path: Option<&Path>,
+    jobs: Option<u16>,
+    past: Option<u32>,
+    future: Option<u32>,
+    date: Option<String>,
+    sundays_only: bool,
+    retry_failed: bool,
+    show_progress: bool,
+) -> Result<(), ApplicationError> {
+    let date_id = date
+        .map(|date_string| DateId::checked_from_str(&date_string))
+        .transpose()
+        .map_err(ArgumentError::InvalidDate)?;
+    let db = DatabaseHandle::new(config_path).await?;
+    let config = Config::from_file_or_default(config_path);
+    let web_client = WebClient::from_env_or_config(&config);
+    let mut db_config = config.database;
+    if let Some(jobs) = jobs {
+        db_config.max_concurrent_requests = jobs;
+    }
+    if let Some(past) = past {
+        db_config.past_entries = past;
+    }
+    if let Some(future) = future {
+        db_config.future_entries = future;
+    }
+    let num_added = if let Some(date_id) = date_id {
+        ensure_list_stored(
+            &db,
+            vec![date_id],
+            &web_client,
+            db_config.max_concurrent_requests,
+            show_progress,
+            None,
+        )
+        .await
+        .added
+    } else if sundays_only {
+        let mut date_ids = DateId::window(db_config.past_entries, db_config.future_entries);
+        date_ids.retain(DateId::is_sunday);
+        ensure_list_stored(&db, date_ids, &web_client, db_config.max_concurrent_requests, show_progress, None)
+            .await
+            .added
+    } else if retry_failed {
+        let date_ids = load_failed_updates();
+        info!("Retrying {} dates that failed to update", date_ids.len());
+        store_recording_failures(&db, date_ids, &web_client, &db_config, show_progress).await
+    } else {
+        update_db_inner(&db, db_config, &web_client, show_progress).await
+    };
 
     println!("{num_added}");
     Ok(())
@@ -177,40 +683,275 @@ async fn update_db() -> Result<(), DatabaseInitError> {
 /// Subcommand: db refresh
 ///
 /// Performs a clean, and then an update
-async fn refresh_db() -> Result<(), DatabaseInitError> {
-    let db = DatabaseHandle::new().await?;
-    let db_config = Config::from_file_or_default().database;
-    let num_removed = match clean_db_inner(&db, db_config.clone(), false).await {
-        Ok(num_removed) => num_removed,
+/// If `refresh_stale_days` is configured, present entries fetched longer ago than that are retrieved again. Their number is
+/// written on a third line
+async fn refresh_db(config_path: Option<&Path>, jobs: Option<u16>, show_progress: bool) -> Result<(), DatabaseInitError> {
+    let db = DatabaseHandle::new(config_path).await?;
+    let config = Config::from_file_or_default(config_path);
+    let web_client = WebClient::from_env_or_config(&config);
+    let mut db_config = config.database;
+    if let Some(jobs) = jobs {
+        db_config.max_concurrent_requests = jobs;
+    }
+    let num_removed = match clean_db_inner(&db, &db_config, false).await {
+        Ok(removed) => removed.len(),
         Err(e) => {
             error!("Encounterd error removing entries during refresh: {e}");
             0
         }
     };
-    let web_client = WebClient::default();
-    let num_added = update_db_inner(&db, db_config, &web_client).await;
+    let max_age = (db_config.refresh_stale_days > 0).then(|| TimeDelta::days(i64::from(db_config.refresh_stale_days)));
+    let date_ids = DateId::window(db_config.past_entries, db_config.future_entries);
+    let counts = ensure_list_stored(
+        &db,
+        date_ids,
+        &web_client,
+        db_config.max_concurrent_requests,
+        show_progress,
+        max_age,
+    )
+    .await;
 
     println!("{num_removed}");
-    println!("{num_added}");
+    println!("{}", counts.added);
+    if max_age.is_some() {
+        println!("{}", counts.refetched);
+    }
     Ok(())
 }
 
 /// Subcommand: db show
 ///
-/// Prints each lectionary row from the lectionary table of the database to STDOUT
-async fn show_db() -> Result<(), DatabaseError> {
-    let db = DatabaseHandle::new().await?;
-    let mut rows = db.get_lectionary_rows().await.map_err(DatabaseGetError::from)?;
-    rows.sort_unstable();
+/// Prints each lectionary row from the lectionary table of the database to STDOUT, optionally only those of one rank
+/// If since or before is given, only rows on or after since and before before are printed
+/// If timestamps is true, the time each was fetched is printed between the date and the name
+async fn show_db(
+    config_path: Option<&Path>,
+    filter: Option<Rank>,
+    since: Option<String>,
+    before: Option<String>,
+    sort: ShowSort,
+    timestamps: bool,
+    format: ShowFormat,
+) -> Result<(), ApplicationError> {
+    let parse_date = |date: Option<String>| {
+        date.map(|date_string| DateId::checked_from_str(&date_string))
+            .transpose()
+            .map_err(ArgumentError::InvalidDate)
+    };
+    let since = parse_date(since)?;
+    let before = parse_date(before)?;
+    let db = DatabaseHandle::new(config_path).await?;
+    let rows = match filter {
+        Some(rank) => db.get_lectionary_rows_by_rank(rank).await,
+        None => db.get_lectionary_rows().await,
+    }
+    .map_err(|e| DatabaseError::from(DatabaseGetError::from(e)))?;
+    let mut rows = rows_in_range(rows, since.as_ref(), before.as_ref());
+    sort_rows(&mut rows, sort);
+    match format {
+        ShowFormat::Text => {
+            for row in rows {
+                if timestamps {
+                    println!("{} {} {}", row.id, format_fetched_at(&row), row.name);
+                } else {
+                    println!("{} {}", row.id, row.name);
+                }
+            }
+        }
+        ShowFormat::Json => println!("{}", rows_json(&rows, timestamps)),
+        ShowFormat::Csv => print!("{}", rows_csv(&rows, timestamps)),
+    }
+    Ok(())
+}
+
+/// The rows as a pretty-printed JSON array. The fetch time is null if it wasn't recorded
+fn rows_json(rows: &[LectionaryRow], timestamps: bool) -> String {
+    let values: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            let mut value = serde_json::json!({ "date": row.id, "name": row.name });
+            if timestamps {
+                value["fetched_at"] = fetched_at_string(row).into();
+            }
+            value
+        })
+        .collect();
+    serde_json::to_string_pretty(&values).unwrap_or_default()
+}
+
+/// The rows as CSV with a header line, in the same column order as the text output. The fetch time is empty if it wasn't recorded
+fn rows_csv(rows: &[LectionaryRow], timestamps: bool) -> String {
+    let mut csv = String::from(if timestamps { "date,fetched_at,name\n" } else { "date,name\n" });
     for row in rows {
-        println!("{} {}", row.id, row.name);
+        csv.push_str(row.id.as_str());
+        csv.push(',');
+        if timestamps {
+            csv.push_str(&fetched_at_string(row).unwrap_or_default());
+            csv.push(',');
+        }
+        csv.push_str(&csv_field(&row.name));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quotes the field if it contains a comma, quote, or line break, doubling any quotes within it
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
     }
+}
+
+/// Subcommand: db export
+///
+/// Writes every lectionary in the database as a JSON array to the file at `output_path`, or to STDOUT if there is no path.
+/// Lectionaries are streamed from the database and written one at a time
+async fn export_db(output_path: Option<PathBuf>, args: &CommonArguments) -> Result<(), ApplicationError> {
+    let config_path = args.config.as_deref();
+    let db = DatabaseHandle::new(config_path).await.map_err(DatabaseError::from)?;
+    let readings = DisplayReadingsArgs {
+        all: true,
+        ..DisplayReadingsArgs::default()
+    };
+    let settings = DisplaySettings::from_config_and_args(
+        Config::from_file_or_default(config_path),
+        readings,
+        FormattingArgs::default(),
+        RetrievalArgs::default(),
+        ReadingLengthArgs::default(),
+        &OutputArgs::default(),
+        args,
+    );
+    let mut writer: Box<dyn Write> = if let Some(path) = output_path {
+        let file = File::create(&path).map_err(|source| OutputError::CannotCreate { path, source })?;
+        Box::new(BufWriter::new(file))
+    } else {
+        Box::new(io::stdout().lock())
+    };
+    let mut lectionaries = pin!(db.stream_lectionaries().await.map_err(DatabaseError::from)?);
+    let mut count = 0;
+    while let Some(lectionary) = lectionaries.try_next().await.map_err(DatabaseError::from)? {
+        export::write_json_array_element(&lectionary, &settings, count == 0, &mut writer).map_err(OutputError::from)?;
+        count += 1;
+    }
+    export::close_json_array(count == 0, &mut writer).map_err(OutputError::from)?;
+    writer.flush().map_err(OutputError::from)?;
+    info!("Exported {count} lectionaries");
     Ok(())
 }
 
+/// Subcommand: db stats
+///
+/// Prints the number of lectionaries, the range of dates, the oldest and newest fetch times, and the average and longest
+/// reading to STDOUT
+async fn stats_db(config_path: Option<&Path>) -> Result<(), DatabaseError> {
+    let db = DatabaseHandle::new(config_path).await?;
+    let rows = db.get_lectionary_rows().await.map_err(DatabaseGetError::from)?;
+    // Only one lectionary is held in memory at a time
+    let lengths = db
+        .stream_lectionaries()
+        .await?
+        .try_fold(ReadingLengths::default(), |mut lengths, lectionary| async move {
+            lengths.add(&lectionary);
+            Ok(lengths)
+        })
+        .await?;
+    for line in stats_lines(&rows).into_iter().chain(lengths.lines()) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn stats_lines(rows: &[LectionaryRow]) -> Vec<String> {
+    let mut lines = vec![format!("Lectionaries: {}", rows.len())];
+    if let (Some(earliest), Some(latest)) = (rows.iter().min(), rows.iter().max()) {
+        lines.push(format!("Dates: {} to {}", earliest.id, latest.id));
+    }
+    let fetched = rows.iter().filter(|row| row.fetched_at.is_some());
+    if let (Some(oldest), Some(newest)) = (
+        fetched.clone().min_by_key(|row| row.fetched_at),
+        fetched.max_by_key(|row| row.fetched_at),
+    ) {
+        lines.push(format!("Oldest fetch: {} ({})", format_fetched_at(oldest), oldest.id));
+        lines.push(format!("Newest fetch: {} ({})", format_fetched_at(newest), newest.id));
+    }
+    lines
+}
+
+/// Running totals of the lengths of the readings, so that lectionaries can be counted one at a time
+#[derive(Default)]
+struct ReadingLengths {
+    readings: usize,
+    total_words: usize,
+    total_chars: usize,
+    /// Words, characters, name, and date of the longest reading so far
+    longest: Option<(usize, usize, String, DateId)>,
+}
+
+impl ReadingLengths {
+    fn add(&mut self, lectionary: &Lectionary) {
+        for (name, reading) in lectionary.readings() {
+            let (words, chars) = (reading.word_count(), reading.char_count());
+            self.readings += 1;
+            self.total_words += words;
+            self.total_chars += chars;
+            // Later readings win ties
+            if self.longest.as_ref().is_none_or(|(longest_words, ..)| words >= *longest_words) {
+                self.longest = Some((words, chars, name.to_string(), lectionary.get_id().clone()));
+            }
+        }
+    }
+
+    /// Average length of the readings and the longest reading. Empty if there are no readings
+    fn lines(&self) -> Vec<String> {
+        let Some((words, chars, name, id)) = &self.longest else {
+            return Vec::new();
+        };
+        vec![
+            format!(
+                "Average reading: {} words, {} characters",
+                self.total_words / self.readings,
+                self.total_chars / self.readings
+            ),
+            format!("Longest reading: {words} words, {chars} characters ({name}, {id})"),
+        ]
+    }
+}
+
+/// Formats the fetch time of the row as a single word, or "unknown" if it wasn't recorded
+fn format_fetched_at(row: &LectionaryRow) -> String {
+    fetched_at_string(row).unwrap_or_else(|| String::from("unknown"))
+}
+
+/// The fetch time of the row, if it was recorded
+fn fetched_at_string(row: &LectionaryRow) -> Option<String> {
+    row.fetched_at_local().map(|time| time.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+/// Keeps only the rows on or after `since` and before `before`, when given
+fn rows_in_range(rows: Vec<LectionaryRow>, since: Option<&DateId>, before: Option<&DateId>) -> Vec<LectionaryRow> {
+    rows.into_iter()
+        .filter(|row| since.is_none_or(|since| &row.id >= since))
+        .filter(|row| before.is_none_or(|before| &row.id < before))
+        .collect()
+}
+
+/// Sorts the rows for db show. Ties are broken by date
+fn sort_rows(rows: &mut [LectionaryRow], sort: ShowSort) {
+    rows.sort_unstable();
+    match sort {
+        ShowSort::Date => {}
+        ShowSort::Name => rows.sort_by(|a, b| a.name.cmp(&b.name)),
+        ShowSort::Insertion => rows.sort_by_key(|row| row.fetched_at),
+    }
+}
+
 /// Subcomand: config init
-fn init_config(force: bool) -> Result<(), InitConfigError> {
-    match Config::initialize_default_config(force) {
+fn init_config(config_path: Option<&Path>, force: bool) -> Result<(), InitConfigError> {
+    match Config::initialize_default_config(config_path, force) {
         Ok(()) => {
             println!("success");
             Ok(())
@@ -227,8 +968,8 @@ fn init_config(force: bool) -> Result<(), InitConfigError> {
 }
 
 /// Subcommand: config upgrade
-fn upgrade_config() -> Result<(), ReadConfigError> {
-    let result = Config::upgrade_config();
+fn upgrade_config(config_path: Option<&Path>) -> Result<(), ReadConfigError> {
+    let result = Config::upgrade_config(config_path);
     if result.is_ok() {
         println!("success");
     }
@@ -236,66 +977,321 @@ fn upgrade_config() -> Result<(), ReadConfigError> {
 }
 
 /// Subcommand: config show
-fn show_config() {
-    let config = Config::from_file_or_default();
+///
+/// Fails if the config file is invalid rather than showing the defaults
+fn show_config(config_path: Option<&Path>) -> Result<(), ReadConfigError> {
+    let config = Config::from_file_strict(config_path)?;
     print!("{config}");
+    Ok(())
+}
+
+/// Subcommand: config check
+///
+/// Writes each problem to STDOUT
+fn check_config(config_path: Option<&Path>) -> Result<(), CheckConfigError> {
+    let problems = Config::check_file(config_path)?;
+    if problems.is_empty() {
+        println!("success");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{problem}");
+    }
+    Err(CheckConfigError::ProblemsFound(problems.len()))
+}
+
+/// Subcommand: config get
+///
+/// Fails if the config file is invalid rather than getting the default value
+fn get_config(config_path: Option<&Path>, key: &str) -> Result<(), GetConfigError> {
+    let config = Config::from_file_strict(config_path)?;
+    println!("{}", config.get_value(key)?);
+    Ok(())
+}
+
+/// Subcommand: config set
+fn set_config(config_path: Option<&Path>, key: &str, value: &str) -> Result<(), SetConfigError> {
+    Config::set_value(config_path, key, value)?;
+    println!("success");
+    Ok(())
 }
 
 /// Used by db clean and db refresh
-async fn clean_db_inner(db: &DatabaseHandle, db_config: DbConfig, all: bool) -> Result<u64, DatabaseError> {
-    let DbConfig {
-        past_entries,
-        future_entries,
-    } = db_config;
-    let earliest_date = Local::now() - TimeDelta::days(i64::from(past_entries));
-
-    let latest_date_id: Option<DateId> = if all {
-        let latest_date = Local::now() + TimeDelta::days(i64::from(future_entries));
-        Some(DateId::from_local_datetime(&latest_date))
+async fn clean_db_inner(db: &DatabaseHandle, db_config: &DbConfig, all: bool) -> Result<Vec<DateId>, DatabaseError> {
+    let (earliest, latest) = clean_range(db_config, all);
+    db.remove_outside_range(&earliest, Some(&latest))
+        .await
+        .map_err(DatabaseError::DeleteError)
+}
+
+/// The earliest and latest dates kept by a clean
+fn clean_range(db_config: &DbConfig, all: bool) -> (DateId, DateId) {
+    clean_range_from(&Local::now(), db_config, all)
+}
+
+/// The earliest and latest dates kept by a clean, relative to today
+///
+/// If all is true, entries after the configured future range are removed. Otherwise, entries are kept for twice the future range so
+/// that readings stored ahead of time (e.g. with db store) survive, while the database still stays bounded if the range shrinks
+fn clean_range_from(today: &DateTime<Local>, db_config: &DbConfig, all: bool) -> (DateId, DateId) {
+    let earliest_date = *today - TimeDelta::days(i64::from(db_config.past_entries));
+    let future_days = if all {
+        i64::from(db_config.future_entries)
     } else {
-        None
+        i64::from(db_config.future_entries) * 2
     };
-    let removed_count = db
-        .remove_outside_range(DateId::from_local_datetime(&earliest_date), latest_date_id)
-        .await
-        .map_err(DatabaseError::DeleteError)?;
-    Ok(removed_count)
+    let latest_date = *today + TimeDelta::days(future_days);
+    (
+        DateId::from_local_datetime(&earliest_date),
+        DateId::from_local_datetime(&latest_date),
+    )
 }
 
 /// Used by db udpate and db refresh
-async fn update_db_inner(db: &DatabaseHandle, db_config: DbConfig, web_client: &WebClient) -> u64 {
-    let date_ids = DateId::get_list(db_config.past_entries, db_config.future_entries);
-    ensure_list_stored(db, date_ids, web_client).await
+async fn update_db_inner(db: &DatabaseHandle, db_config: DbConfig, web_client: &WebClient, show_progress: bool) -> u64 {
+    let date_ids = DateId::window(db_config.past_entries, db_config.future_entries);
+    store_recording_failures(db, date_ids, web_client, &db_config, show_progress).await
+}
+
+/// Stores the dates, then remembers the ones that failed for db update --retry-failed. Returns the number added
+async fn store_recording_failures(
+    db: &DatabaseHandle,
+    date_ids: Vec<DateId>,
+    web_client: &WebClient,
+    db_config: &DbConfig,
+    show_progress: bool,
+) -> u64 {
+    let counts = ensure_list_stored(db, date_ids, web_client, db_config.max_concurrent_requests, show_progress, None).await;
+    if !counts.failed.is_empty() {
+        warn!(
+            "Failed to store {} dates. Use 'db update --retry-failed' to try them again",
+            counts.failed.len()
+        );
+    }
+    save_failed_updates(&counts.failed);
+    counts.added
+}
+
+/// Gets the dates that the last update failed to store. Empty if there were none, or they can't be read
+fn load_failed_updates() -> Vec<DateId> {
+    let result = path::create_and_get_failed_updates_path()
+        .map_err(|e| e.to_string())
+        .and_then(|path| read_failed_updates(&path).map_err(|e| e.to_string()));
+    result.unwrap_or_else(|e| {
+        warn!("Failed to read the dates that failed to update: {e}");
+        Vec::new()
+    })
+}
+
+/// Remembers the dates that failed to store, replacing the ones from the last update. Only logs on failure, since the dates were
+/// stored either way
+fn save_failed_updates(date_ids: &[DateId]) {
+    let result = path::create_and_get_failed_updates_path()
+        .map_err(|e| e.to_string())
+        .and_then(|path| write_failed_updates(&path, date_ids).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("Failed to save the dates that failed to update: {e}");
+    }
+}
+
+/// Reads one date per line. A missing file means there were no failures. Invalid lines are skipped with a warning
+fn read_failed_updates(path: &Path) -> io::Result<Vec<DateId>> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => {
+            let lines: Vec<String> = contents.lines().map(str::to_owned).collect();
+            Ok(convert_valid_date_list(&lines))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
 }
 
-async fn ensure_list_stored(db: &DatabaseHandle, date_ids: Vec<DateId>, web_client: &WebClient) -> u64 {
+/// Writes one date per line, or removes the file if there are none
+fn write_failed_updates(path: &Path, date_ids: &[DateId]) -> io::Result<()> {
+    if date_ids.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let mut contents = date_ids.iter().map(DateId::as_str).collect::<Vec<_>>().join("\n");
+    contents.push('\n');
+    std::fs::write(path, contents)
+}
+
+/// Number of lectionaries stored by `ensure_list_stored`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct StoreCounts {
+    /// Lectionaries that were not present
+    added: u64,
+    /// Stale lectionaries that were retrieved again
+    refetched: u64,
+    /// Dates that could not be stored, including those skipped when interrupted
+    failed: Vec<DateId>,
+}
+
+/// Stores each of the given ids, with at most `max_concurrent` tasks retrieving from the web at once
+///
+/// If `show_progress` is true, the number of completed tasks is written to STDERR as each one finishes
+/// If `max_age` is given, present lectionaries fetched longer ago than that are retrieved again
+///
+/// On Ctrl-C, no more retrievals are started and the ones in progress are allowed to finish storing. A second Ctrl-C
+/// abandons the ones in progress, whose transactions are rolled back
+async fn ensure_list_stored(
+    db: &DatabaseHandle,
+    date_ids: Vec<DateId>,
+    web_client: &WebClient,
+    max_concurrent: u16,
+    show_progress: bool,
+    max_age: Option<TimeDelta>,
+) -> StoreCounts {
+    let mut progress = Progress::new(date_ids.len(), show_progress);
+    let semaphore = Arc::new(Semaphore::new(usize::from(max_concurrent.max(1))));
     let mut tasks = JoinSet::new();
-    for id in date_ids {
+    for id in date_ids.clone() {
         let thread_db = db.clone();
         let thread_client = web_client.clone();
-        tasks.spawn(async move { orchestration::ensure_stored(id, &thread_db, &thread_client).await });
+        let thread_semaphore = Arc::clone(&semaphore);
+        tasks.spawn(async move {
+            // The semaphore is only closed when interrupted, in which case this retrieval is skipped
+            let Ok(_permit) = thread_semaphore.acquire().await else {
+                return None;
+            };
+            let outcome = orchestration::ensure_stored_fresh(id.clone(), &thread_db, &thread_client, max_age).await;
+            Some(outcome.map(|outcome| (id, outcome)))
+        });
     }
 
-    let mut count_added = 0;
+    let mut counts = StoreCounts::default();
+    let mut stored = Vec::new();
+    let mut interrupted = false;
+    let mut listening_for_signal = true;
 
-    while let Some(thread_result) = tasks.join_next().await {
-        match thread_result {
-            Err(e) => error!("Failed to store a lectionary (Thread panicked!): {}", e),
-            Ok(Err(e)) => error!("Failed to store a lectionary: ({})", e),
-            Ok(Ok(new)) => {
-                if new {
-                    count_added += 1;
+    loop {
+        tokio::select! {
+            maybe_result = tasks.join_next() => {
+                let Some(thread_result) = maybe_result else {
+                    break;
+                };
+                match thread_result {
+                    Err(e) if e.is_cancelled() => continue,
+                    Err(e) => error!("Failed to store a lectionary (Thread panicked!): {}", e),
+                    Ok(None) => continue,
+                    Ok(Some(Err(e))) => error!("Failed to store a lectionary: ({})", e),
+                    Ok(Some(Ok((id, outcome)))) => {
+                        match outcome {
+                            StoreOutcome::Added => counts.added += 1,
+                            StoreOutcome::Refetched => counts.refetched += 1,
+                            StoreOutcome::Present => {}
+                        }
+                        stored.push(id);
+                    }
                 }
+                progress.increment();
             }
+            signal = tokio::signal::ctrl_c(), if listening_for_signal => {
+                if let Err(e) = signal {
+                    error!("Failed to listen for Ctrl-C: {e}");
+                    listening_for_signal = false;
+                    continue;
+                } else if interrupted {
+                    warn!("Interrupted again. Abandoning lectionaries in progress");
+                    tasks.abort_all();
+                } else {
+                    warn!("Interrupted. Waiting for lectionaries in progress to be stored (Ctrl-C again to abandon them)");
+                    semaphore.close();
+                }
+                interrupted = true;
+            }
+        }
+    }
+    progress.finish();
+    if interrupted {
+        warn!("Stopped early after storing {} lectionaries", counts.added + counts.refetched);
+    }
+    stored.sort_unstable();
+    counts.failed = date_ids.into_iter().filter(|id| stored.binary_search(id).is_err()).collect();
+    counts
+}
+
+/// Progress of a number of tasks, written to STDERR as "N/M" on a single line that is overwritten as tasks complete
+struct Progress {
+    completed: usize,
+    total: usize,
+    enabled: bool,
+}
+
+impl Progress {
+    fn new(total: usize, enabled: bool) -> Self {
+        Self {
+            completed: 0,
+            total,
+            enabled,
+        }
+    }
+
+    fn increment(&mut self) {
+        self.completed += 1;
+        if self.enabled {
+            eprint!("\r{}", self.line());
         }
     }
-    count_added
+
+    /// Clears the line so that it doesn't mix with the output that follows
+    fn finish(&self) {
+        if self.enabled && self.completed > 0 {
+            eprint!("\r{}\r", " ".repeat(self.line().len()));
+        }
+    }
+
+    fn line(&self) -> String {
+        format!("{}/{}", self.completed, self.total)
+    }
+}
+
+/// Represents a failure to get a single value from the config
+#[derive(thiserror::Error, Debug)]
+pub enum GetConfigError {
+    #[error("{0}")]
+    ReadError(#[from] ReadConfigError),
+    #[error("{0}")]
+    UnknownKey(#[from] UnknownKeyError),
+}
+
+/// Represents a failure to write the displayed readings
+#[derive(thiserror::Error, Debug)]
+pub enum OutputError {
+    #[error("Cannot create output file '{}': {source}", path.display())]
+    CannotCreate { path: PathBuf, source: io::Error },
+    #[error("Failed to write output: {0}")]
+    WriteError(#[from] io::Error),
+}
+
+/// Represents a failure to write the man pages
+#[derive(thiserror::Error, Debug)]
+pub enum ManPageError {
+    #[error("Cannot create directory '{}': {source}", path.display())]
+    CannotCreateDir { path: PathBuf, source: io::Error },
+    #[error("Failed to write man pages to '{}': {source}", path.display())]
+    WriteError { path: PathBuf, source: io::Error },
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum ArgumentError {
     #[error("Invalid date Argument: ({0})")]
     InvalidDate(#[from] ParseError),
+    #[error("--raw must be used with exactly one reading")]
+    RawRequiresOneReading,
+    #[error("--until must not be before the date displayed")]
+    UntilBeforeDate,
+    #[error("--until can't be used when --date is given more than once")]
+    UntilWithSeveralDates,
+    #[error("--from-file can't be used when --date is given more than once")]
+    FromFileWithSeveralDates,
+    #[error("No day stored in the database has a name containing '{0}'")]
+    NoDayFound(String),
+    #[error("--to must not be before --from")]
+    ToBeforeFrom,
 }
 
 /// Represents a terminating error in the application. Each variant is associated with an exit code
@@ -314,6 +1310,20 @@ pub enum ApplicationError {
     InitConfigError(#[from] InitConfigError),
     #[error("Failed to Read Config file: ({0})")]
     ReadConfigError(#[from] ReadConfigError),
+    #[error("Failed to set config value: ({0})")]
+    SetConfigError(#[from] SetConfigError),
+    #[error("Failed to get config value: ({0})")]
+    GetConfigError(#[from] GetConfigError),
+    #[error("Invalid config: ({0})")]
+    CheckConfigError(#[from] CheckConfigError),
+    #[error("Can't write readings: ({0})")]
+    OutputError(#[from] OutputError),
+    #[error("Can't use profile: ({0})")]
+    ProfileError(#[from] ProfileError),
+    #[error("Can't write man pages: ({0})")]
+    ManPageError(#[from] ManPageError),
+    #[error("Can't display lectionary from file: ({0})")]
+    ParseFileError(#[from] ParseFileError),
 }
 
 impl ApplicationError {
@@ -322,12 +1332,52 @@ impl ApplicationError {
         match self {
             Self::BadArgument(_) => 3,
             Self::DatabaseError(_) => 4,
+            Self::RetrievalError(error) if error.is_network_unreachable() => 12,
+            Self::RetrievalError(error) if error.is_not_available() => 15,
             Self::RetrievalError(_) => 5,
             Self::InitConfigError(_) => 6,
             Self::ReadConfigError(_) => 7,
+            Self::SetConfigError(_) => 8,
+            Self::GetConfigError(_) => 9,
+            Self::CheckConfigError(_) => 10,
+            Self::OutputError(_) => 11,
+            Self::ProfileError(_) => 13,
+            Self::ManPageError(_) => 14,
+            Self::ParseFileError(_) => 16,
             Self::NotImplemented => 100,
         }
     }
+
+    /// Name of the variant. Unlike the message, this is stable and can be relied on by scripts
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NotImplemented => "NotImplemented",
+            Self::BadArgument(_) => "BadArgument",
+            Self::DatabaseError(_) => "DatabaseError",
+            Self::RetrievalError(_) => "RetrievalError",
+            Self::InitConfigError(_) => "InitConfigError",
+            Self::ReadConfigError(_) => "ReadConfigError",
+            Self::SetConfigError(_) => "SetConfigError",
+            Self::GetConfigError(_) => "GetConfigError",
+            Self::CheckConfigError(_) => "CheckConfigError",
+            Self::OutputError(_) => "OutputError",
+            Self::ProfileError(_) => "ProfileError",
+            Self::ManPageError(_) => "ManPageError",
+            Self::ParseFileError(_) => "ParseFileError",
+        }
+    }
+
+    /// Formats the error as a single line JSON object with the name of the variant, the exit code, and the message
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "error": self.name(),
+            "exit_code": self.exit_code(),
+            "message": self.to_string(),
+        })
+        .to_string()
+    }
 }
 
 impl From<DatabaseInitError> for ApplicationError {
@@ -335,3 +1385,273 @@ impl From<DatabaseInitError> for ApplicationError {
         Self::from(DatabaseError::InitError(value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn file_statuses() {
+        let dir = std::env::temp_dir().join(format!("lectio-diei-doctor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("present.toml");
+        std::fs::write(&file, "").unwrap();
+
+        assert_eq!("exists, writable", file_status(&file));
+        assert_eq!("does not exist", file_status(&dir.join("missing.toml")));
+        assert_eq!("exists, not a file", file_status(&dir));
+        assert_eq!(
+            format!("Log: {} (exists, writable)", file.to_string_lossy()),
+            location_line("Log", Ok(file.clone()))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn web_check_reports_each_step() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = std::fs::read_to_string("tests/resources/lent.html").unwrap();
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+        let client = WebClient::new(&reqwest::Url::parse(&server.uri()).unwrap());
+        let date_id = DateId::checked_from_str("022024").unwrap();
+        let lines = web_check_lines(&client, &PageSelectors::default(), date_id).await;
+        assert!(lines[0].starts_with("Site: retrieved ") && lines[0].ends_with("/bible/readings/022024.cfm"));
+        assert_eq!("Page: readings found for 'Tuesday of the First Week of Lent'", lines[1]);
+        assert_eq!("Readings parsed: Reading I, Responsorial Psalm, Alleluia, Gospel", lines[2]);
+        assert_eq!("Readings missing: none", lines[3]);
+    }
+
+    #[tokio::test]
+    async fn web_check_stops_at_missing_container() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html><body>Changed</body></html>"))
+            .mount(&server)
+            .await;
+        let client = WebClient::new(&reqwest::Url::parse(&server.uri()).unwrap());
+        let lines = web_check_lines(&client, &PageSelectors::default(), DateId::checked_from_str("022024").unwrap()).await;
+        assert_eq!(2, lines.len());
+        assert!(lines[1].starts_with("Page: readings not found"));
+    }
+
+    #[test]
+    fn only_y_confirms() {
+        assert!(read_confirmation(&mut "y\n".as_bytes()));
+        assert!(read_confirmation(&mut " Y \n".as_bytes()));
+        assert!(!read_confirmation(&mut "yes\n".as_bytes()));
+        assert!(!read_confirmation(&mut "n\n".as_bytes()));
+        assert!(!read_confirmation(&mut "\n".as_bytes()));
+        assert!(!read_confirmation(&mut "".as_bytes()));
+    }
+
+    #[test]
+    fn completions_cover_subcommands_and_readings() {
+        let mut out = Vec::new();
+        write_completions(Shell::Bash, &mut out);
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("lectio-diei"));
+        assert!(script.contains("completions"));
+        assert!(script.contains("refresh"));
+        assert!(script.contains("reading1"));
+    }
+
+    #[test]
+    fn rows_in_range_bounds() {
+        let rows = ["063024", "070124", "071524", "080124"]
+            .iter()
+            .map(|id| LectionaryRow {
+                id: DateId::checked_from_str(id).unwrap(),
+                name: String::new(),
+                fetched_at: None,
+            })
+            .collect::<Vec<_>>();
+        let since = DateId::checked_from_str("070124").unwrap();
+        let before = DateId::checked_from_str("080124").unwrap();
+
+        let ids = |rows: Vec<LectionaryRow>| rows.into_iter().map(|row| row.id.to_string()).collect::<Vec<_>>();
+        assert_eq!(vec!["070124", "071524"], ids(rows_in_range(rows, Some(&since), Some(&before))));
+    }
+
+    #[test]
+    fn sort_rows_by_each_order() {
+        let row = |id: &str, name: &str, fetched_at: Option<i64>| LectionaryRow {
+            id: DateId::checked_from_str(id).unwrap(),
+            name: String::from(name),
+            fetched_at,
+        };
+        let mut rows = vec![
+            row("071524", "Memorial of Saint Bonaventure", Some(300)),
+            row("071424", "Fifteenth Sunday in Ordinary Time", Some(100)),
+            row("071624", "Tuesday of the Fifteenth Week", Some(200)),
+            row("071324", "Saturday of the Fourteenth Week", None),
+        ];
+        let ids = |rows: &[LectionaryRow]| rows.iter().map(|row| row.id.to_string()).collect::<Vec<_>>();
+
+        sort_rows(&mut rows, ShowSort::Date);
+        assert_eq!(vec!["071324", "071424", "071524", "071624"], ids(&rows));
+        sort_rows(&mut rows, ShowSort::Name);
+        assert_eq!(vec!["071424", "071524", "071324", "071624"], ids(&rows));
+        sort_rows(&mut rows, ShowSort::Insertion);
+        assert_eq!(vec!["071324", "071424", "071624", "071524"], ids(&rows));
+    }
+
+    #[test]
+    fn rows_as_csv_and_json() {
+        let rows = vec![
+            LectionaryRow {
+                id: DateId::checked_from_str("071424").unwrap(),
+                name: String::from("Fifteenth Sunday in Ordinary Time"),
+                fetched_at: None,
+            },
+            LectionaryRow {
+                id: DateId::checked_from_str("071524").unwrap(),
+                name: String::from("Memorial of Saint Bonaventure, Bishop and Doctor of the Church"),
+                fetched_at: None,
+            },
+        ];
+
+        assert_eq!(
+            "date,name\n071424,Fifteenth Sunday in Ordinary Time\n071524,\"Memorial of Saint Bonaventure, Bishop and Doctor of the Church\"\n",
+            rows_csv(&rows, false)
+        );
+        assert!(rows_csv(&rows, true).starts_with("date,fetched_at,name\n071424,,Fifteenth"));
+        assert_eq!("\"Say \"\"hi\"\"\"", csv_field("Say \"hi\""));
+
+        let json: serde_json::Value = serde_json::from_str(&rows_json(&rows, true)).unwrap();
+        assert_eq!("071424", json[0]["date"]);
+        assert_eq!("Fifteenth Sunday in Ordinary Time", json[0]["name"]);
+        assert!(json[1]["fetched_at"].is_null());
+        let json: serde_json::Value = serde_json::from_str(&rows_json(&rows, false)).unwrap();
+        assert!(json[0].get("fetched_at").is_none());
+    }
+
+    #[test]
+    fn stats_include_range_and_fetch_times() {
+        let row = |id: &str, fetched_at: Option<i64>| LectionaryRow {
+            id: DateId::checked_from_str(id).unwrap(),
+            name: String::new(),
+            fetched_at,
+        };
+        let rows = vec![row("071524", Some(300)), row("071424", Some(100)), row("071324", None)];
+        let lines = stats_lines(&rows);
+        assert_eq!("Lectionaries: 3", lines[0]);
+        assert_eq!("Dates: 071324 to 071524", lines[1]);
+        assert!(lines[2].starts_with("Oldest fetch: ") && lines[2].ends_with("(071424)"));
+        assert!(lines[3].starts_with("Newest fetch: ") && lines[3].ends_with("(071524)"));
+
+        assert_eq!(vec!["Lectionaries: 0"], stats_lines(&[]));
+        assert_eq!("unknown", format_fetched_at(&rows[2]));
+    }
+
+    #[test]
+    fn reading_lengths() {
+        let reading = |text: &str| Some(crate::lectionary::Reading::new(String::new(), String::from(text), None));
+        let id = DateId::checked_from_str("071424").unwrap();
+        let lectionary = Lectionary::new(
+            id,
+            String::new(),
+            reading("one two three"),
+            None,
+            reading("one"),
+            reading("one two three four five"),
+            reading("one two three"),
+        );
+        assert_eq!(
+            vec![
+                "Average reading: 3 words, 13 characters",
+                "Longest reading: 5 words, 23 characters (Gospel, 071424)"
+            ],
+            {
+                let mut lengths = ReadingLengths::default();
+                lengths.add(&lectionary);
+                lengths.lines()
+            }
+        );
+        assert!(ReadingLengths::default().lines().is_empty());
+    }
+
+    #[test]
+    fn error_as_json() {
+        let error = ApplicationError::from(ArgumentError::RawRequiresOneReading);
+        let json: serde_json::Value = serde_json::from_str(&error.to_json()).unwrap();
+        assert_eq!("BadArgument", json["error"]);
+        assert_eq!(3, json["exit_code"]);
+        assert_eq!(error.to_string(), json["message"]);
+    }
+
+    #[test]
+    fn man_page_for_each_subcommand() {
+        let dir = std::env::temp_dir().join("lectio-diei-man-test");
+        write_man_pages(&dir).unwrap();
+        assert!(dir.join("lectio-diei.1").exists());
+        assert!(dir.join("lectio-diei-display.1").exists());
+        assert!(dir.join("lectio-diei-db-update.1").exists());
+        assert!(!dir.join("lectio-diei-man.1").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn clean_range_bounds_future_entries() {
+        let today = Local.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap();
+        let db_config = DbConfig {
+            past_entries: 5,
+            future_entries: 10,
+            ..DbConfig::default()
+        };
+
+        let (earliest, latest) = clean_range_from(&today, &db_config, false);
+        assert_eq!("011025", earliest.as_str());
+        assert_eq!("020425", latest.as_str());
+
+        let (earliest, latest) = clean_range_from(&today, &db_config, true);
+        assert_eq!("011025", earliest.as_str());
+        assert_eq!("012525", latest.as_str());
+    }
+
+    #[test]
+    fn failed_updates_round_trip() {
+        let path = std::env::temp_dir().join(format!("lectio-diei-failed-{}.txt", std::process::id()));
+        assert!(read_failed_updates(&path).unwrap().is_empty());
+
+        let ids = vec![
+            DateId::checked_from_str("071424").unwrap(),
+            DateId::checked_from_str("071524").unwrap(),
+        ];
+        write_failed_updates(&path, &ids).unwrap();
+        assert_eq!(ids, read_failed_updates(&path).unwrap());
+
+        write_failed_updates(&path, &[]).unwrap();
+        assert!(!path.exists());
+        assert!(read_failed_updates(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn progress_counts_completed() {
+        let mut progress = Progress::new(3, false);
+        assert_eq!("0/3", progress.line());
+        progress.increment();
+        progress.increment();
+        assert_eq!("2/3", progress.line());
+    }
+
+    #[test]
+    fn store_range_includes_both_ends() {
+        let range: Vec<String> = parse_store_range("123024", "010225")
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(vec!["123024", "123124", "010125", "010225"], range);
+        assert!(matches!(parse_store_range("010225", "123024"), Err(ArgumentError::ToBeforeFrom)));
+        assert!(matches!(parse_store_range("13xx24", "010225"), Err(ArgumentError::InvalidDate(_))));
+    }
+}
@@ -1,28 +1,46 @@
 use std::{
+    collections::BTreeMap,
     env,
     fmt::{self, Display},
-    fs::File,
+    fs::{self, File},
     io::{self, Read, Write},
-    path::PathBuf,
+    ops::Range,
+    path::{Path, PathBuf},
 };
 
 use clap::ValueEnum;
 use log::*;
+use reqwest::Url;
+use scraper::Selector;
 use serde::{Deserialize, Serialize};
 use toml::{de, ser::ValueSerializer};
-use toml_edit::{self, DocumentMut};
+use toml_edit::{self, DocumentMut, ImDocument, TomlError};
 
 use crate::{
-    args::ReadingArg,
+    args::{DisplayFormat, ReadingArg, ReadingLength, SeparatorStyle},
+    client,
+    date::DateFormat,
     path::{self, PathError},
 };
 
+/// Set to a non-empty value to not create a config file when there isn't one, like --no-create-config
+pub const NO_CREATE_CONFIG_ENV_VAR: &str = "LECTIO_DIEI_NO_CREATE_CONFIG";
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Config {
     #[serde(default)]
     pub display: DisplayConfig,
     #[serde(default)]
     pub database: DbConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub web: WebConfig,
+    #[serde(default)]
+    pub selectors: SelectorsConfig,
+    /// Named sets of display settings that override the display table when selected with --profile
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
 }
 
 impl Display for Config {
@@ -36,24 +54,64 @@ impl Display for Config {
     }
 }
 
+/// Comments written above each key in the generated config as (table, key, comment)
+///
+/// The comment for `display.reading_order` lists the readings, so it is generated in `Config::to_commented_doc` instead
+const KEY_COMMENTS: [(&str, &str, &str); 29] = [
+    ("display", "original_linebreaks", "Whether to use original linebreaks as displayed on USCCB site. If true, max_width is ignored. Note: Resp. Psalm and Alleluia always use original line breaks"),
+    ("display", "max_width", "Maximum width for formatting readings. Set to \"auto\" to use the width of the terminal. Ignored if original_linebreaks is true. Not used for Psalm/Alleluia. Set to 0 for no line breaks"),
+    ("display", "offline", "If true, only displays readings already stored in the database and never retrieves from the web"),
+    ("display", "reading_length", "Which form to display for readings that have an alternate (noted with 'OR:' on USCCB site). Possible values: \"long\", \"short\""),
+    ("display", "expand_refs", "If true, book abbreviations in reading locations are replaced with the full name of the book (e.g. \"Mt 9:1-8\" becomes \"Matthew 9:1-8\")"),
+    ("display", "separator_style", "Line written around the name of the day and the text of each reading. Possible values: \"dashes\", \"blank\", \"none\""),
+    ("display", "short_labels", "If true, headings use short labels for the readings: \"R1\", \"R2\", \"Ps\", \"Gsp\", \"All\""),
+    ("display", "ascii", "If true, curly quotes, dashes, and ellipses in the readings are replaced with ASCII characters. The stored readings are unchanged"),
+    ("display", "include_responses", "If false, liturgical responses (e.g. \"The word of the Lord.\") are removed from the end of each reading"),
+    ("display", "psalm_keep_verse_number", "If true, the verse number of the response (e.g. \"(8)\") is kept in the first line of the responsorial psalm"),
+    ("display", "date_format", "Format of dates given as arguments, as a chrono strftime format (e.g. \"%d.%m.%Y\"). Dates in the MMddYY format are always accepted too"),
+    ("display", "format", "How the readings are written. Possible values: \"pretty\", \"tts\", \"html\", \"json\"\n# Overridden by --format. Not used with --raw or --headings"),
+    ("database", "future_entries", "Number of days after today to try to keep in the database. Today is always kept (i.e. a value of 0 will only store today's readings)"),
+    ("database", "past_entries", "Number of days before today to try to keep in the database"),
+    ("database", "max_concurrent_requests", "Maximum number of simultaneous requests to the web when adding entries to the database. Values less than 1 are treated as 1"),
+    ("database", "path", "Location of the database file. Relative paths are resolved against the current directory\n# Leave empty to use the default location. Overridden by the LECTIO_DIEI_DB environment variable"),
+    ("database", "refresh_stale_days", "During 'db refresh', entries fetched more than this many days ago are retrieved again in case the text was corrected. Set to 0 to never retrieve present entries again"),
+    ("logging", "file_level", "Level for the log file. Possible values: off, error, warn, info, debug, trace\n# Made more or less detailed by the --verbose and --quiet arguments"),
+    ("logging", "max_file_size", "Size in megabytes at which the log file is rotated. Set to 0 to never rotate"),
+    ("logging", "max_backups", "Number of rotated log files to keep (e.g. lectio-diei.log.1, lectio-diei.log.2)"),
+    ("logging", "file_format", "Format of the log file. Possible values: \"text\", \"json\" (one object per line with timestamp, level, target, and message)"),
+    ("web", "base_url", "Site to retrieve readings from. Only change this to use a mirror of the USCCB site\n# Overridden by the LECTIO_DIEI_BASE_URL environment variable"),
+    ("web", "save_html", "If true, each page retrieved from the web is saved to the cache directory (e.g. ~/.cache/lectio-diei/html), for debugging parse issues\n# Saved pages are only used instead of the site with --use-cache"),
+    ("selectors", "container", "CSS selectors for finding the readings on the page. Only change these if the site changed and a new version is not out yet\n# Leave empty to use the default. Main container of the readings. Default: \"#block-usccb-readings-content div.page-container\""),
+    ("selectors", "day_name", "Within the container, the element with the name of the day. Default: \"div.b-lectionary div.innerblock :first-child\""),
+    ("selectors", "readings", "Within the container, each reading. Default: \"div.b-verse\""),
+    ("selectors", "reading_name", "Within a reading, its name (e.g. Reading I). Default: \".name\""),
+    ("selectors", "reading_content", "Within a reading, its text. Default: \"div.content-body\""),
+    ("selectors", "reading_location", "Within a reading, its location (e.g. Mt 9:1-8). Default: \"div.content-header div.address a\""),
+];
+
 impl Config {
-    pub fn from_file_or_default() -> Self {
-        match path::create_and_get_config_path() {
+    /// Reads the config file at `explicit_path` (e.g. from --config), or the default one if not given. Any failure is logged and
+    /// the default settings are used instead
+    pub fn from_file_or_default(explicit_path: Option<&Path>) -> Self {
+        match Self::get_path(explicit_path) {
             Ok(path) => match Self::from_file(&path) {
                 Ok(config) => {
                     info!("Successfully retrieved config from '{}'", path.to_string_lossy());
                     config
                 }
+                Err(ReadConfigError::NotFound(_)) if explicit_path.is_some() => {
+                    // Don't create a file the user may have mistyped
+                    error!(
+                        "Config file '{}' does not exist; Proceeding with default config settings",
+                        path.to_string_lossy()
+                    );
+                    Self::default()
+                }
                 Err(ReadConfigError::NotFound(_)) => {
-                    warn!(
-                        "No config file found at '{}'; Generating new config file with default settings",
+                    info!(
+                        "No config file found at '{}'; Proceeding with default config settings",
                         path.to_string_lossy()
                     );
-                    // Going to create a config with force=true because if someone created a file in the clock cycles between failing to
-                    //  retrieve and creating, they deserve to lose their config tbh
-                    if let Err(create_error) = Self::create_config(&path, true) {
-                        error!("Failed to create config file ({create_error})");
-                    }
                     Self::default()
                 }
                 Err(e) => {
@@ -68,17 +126,248 @@ impl Config {
         }
     }
 
-    pub fn initialize_default_config(force: bool) -> Result<(), InitConfigError> {
-        debug!("Creating a default config with force={}", force);
+    /// Replaces the display settings with those from the named profile. Settings not in the profile are left as they are
+    ///
+    /// # Errors
+    /// Returns a `ProfileError` if there is no profile with the name or the profile has invalid settings
+    pub fn with_profile(mut self, name: &str) -> Result<Self, ProfileError> {
+        let Some(profile) = self.profiles.get(name) else {
+            return Err(ProfileError::Unknown {
+                name: name.to_owned(),
+                available: self.profiles.keys().cloned().collect(),
+            });
+        };
+        let mut display = toml::Table::try_from(&self.display).expect("Display config should be serializable to TOML table");
+        for (key, value) in profile {
+            display.insert(key.clone(), value.clone());
+        }
+        self.display = display.try_into().map_err(|source| ProfileError::Invalid {
+            name: name.to_owned(),
+            source: Box::new(source),
+        })?;
+        info!("Using display settings from profile '{name}'");
+        Ok(self)
+    }
+
+    /// Reads the config file, failing if it can't be read or is invalid
+    ///
+    /// Used for explicit config commands so that mistakes aren't hidden behind defaults. A missing file is not an error unless the
+    /// path was given explicitly
+    pub fn from_file_strict(explicit_path: Option<&Path>) -> Result<Self, ReadConfigError> {
+        let path = Self::get_path(explicit_path)?;
+        match Self::from_file(&path) {
+            Err(ReadConfigError::NotFound(_)) if explicit_path.is_none() => {
+                warn!("No config file found at '{}'; Using default config", path.to_string_lossy());
+                Ok(Self::default())
+            }
+            result => result,
+        }
+    }
+
+    /// Checks the config file for unknown keys and invalid values
+    ///
+    /// Returns every problem found. Fails if the file can't be read
+    pub fn check_file(explicit_path: Option<&Path>) -> Result<Vec<ConfigProblem>, ReadConfigError> {
+        let path = Self::get_path(explicit_path)?;
+        let config_string = fs::read_to_string(&path)?;
+        Ok(Self::check_str(&config_string))
+    }
+
+    fn check_str(config_string: &str) -> Vec<ConfigProblem> {
+        let doc = match ImDocument::parse(config_string) {
+            Ok(doc) => doc,
+            Err(e) => {
+                return vec![ConfigProblem::new(
+                    config_string,
+                    e.span(),
+                    format!("Invalid TOML: {}", e.message().trim()),
+                )]
+            }
+        };
+
+        let valid_keys = Self::valid_keys();
+        let mut problems = Vec::new();
+        for (table_name, item) in doc.iter() {
+            let table_span = doc.as_table().key(table_name).and_then(toml_edit::Key::span);
+            let Some(table) = item.as_table() else {
+                problems.push(ConfigProblem::new(config_string, table_span, format!("Unknown key '{table_name}'")));
+                continue;
+            };
+            if table_name == "profiles" {
+                problems.extend(Self::check_profiles(config_string, table, &valid_keys));
+                continue;
+            }
+            if !valid_keys.iter().any(|key| key.starts_with(&format!("{table_name}."))) {
+                problems.push(ConfigProblem::new(
+                    config_string,
+                    table_span,
+                    format!("Unknown table '{table_name}'"),
+                ));
+                continue;
+            }
+            for (key_name, value) in table {
+                let full_key = format!("{table_name}.{key_name}");
+                let key_span = table.key(key_name).and_then(toml_edit::Key::span);
+                if valid_keys.contains(&full_key) {
+                    problems.extend(Self::check_value(config_string, &full_key, value, key_span));
+                } else {
+                    problems.push(ConfigProblem::new(config_string, key_span, format!("Unknown key '{full_key}'")));
+                }
+            }
+        }
+
+        // Catches anything not covered above (e.g. wrong types). Only reported alone to avoid reporting the same problem twice
+        if problems.is_empty() {
+            if let Err(e) = de::from_str::<Self>(config_string) {
+                problems.push(ConfigProblem::new(config_string, e.span(), e.message().trim().to_owned()));
+            }
+        }
+        problems
+    }
+
+    /// Checks that each profile only has valid display keys and values
+    fn check_profiles(config_string: &str, profiles: &toml_edit::Table, valid_keys: &[String]) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        for (profile_name, item) in profiles {
+            let profile_span = profiles.key(profile_name).and_then(toml_edit::Key::span);
+            let Some(profile) = item.as_table() else {
+                let message = format!("Profile '{profile_name}' must be a table of display settings");
+                problems.push(ConfigProblem::new(config_string, profile_span, message));
+                continue;
+            };
+            for (key_name, value) in profile {
+                let display_key = format!("display.{key_name}");
+                let key_span = profile.key(key_name).and_then(toml_edit::Key::span);
+                if valid_keys.contains(&display_key) {
+                    problems.extend(Self::check_value(config_string, &display_key, value, key_span));
+                } else {
+                    let message = format!("Unknown key '{key_name}' in profile '{profile_name}'. Profiles can only set display settings");
+                    problems.push(ConfigProblem::new(config_string, key_span, message));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Checks values that would deserialize but are invalid or out of range
+    fn check_value(config_string: &str, key: &str, item: &toml_edit::Item, key_span: Option<Range<usize>>) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        let span = item.span().or(key_span);
+        match item.as_value() {
+            Some(toml_edit::Value::Array(array)) if key == "display.reading_order" => {
+                let mut seen = Vec::new();
+                for entry in array {
+                    match entry.as_str().map(|name| ReadingArg::from_str(name, false)) {
+                        Some(Ok(reading)) if seen.contains(&reading) => {
+                            let message = format!(
+                                "Duplicate entry {} in '{key}'. Only the first is displayed",
+                                entry.to_string().trim()
+                            );
+                            problems.push(ConfigProblem::new(config_string, entry.span().or(span.clone()), message));
+                        }
+                        Some(Ok(reading)) => seen.push(reading),
+                        _ => {
+                            let message = format!(
+                                "Invalid entry {} in '{key}'. Possible values: {}",
+                                entry.to_string().trim(),
+                                ReadingArg::variant_string()
+                            );
+                            problems.push(ConfigProblem::new(config_string, entry.span().or(span.clone()), message));
+                        }
+                    }
+                }
+            }
+            Some(toml_edit::Value::String(url)) if key == "web.base_url" => {
+                if let Err(e) = Url::parse(url.value()) {
+                    problems.push(ConfigProblem::new(config_string, span, format!("Invalid URL for '{key}': {e}")));
+                }
+            }
+            Some(toml_edit::Value::String(selector)) if key.starts_with("selectors.") && !selector.value().is_empty() => {
+                if let Err(e) = Selector::parse(selector.value()) {
+                    problems.push(ConfigProblem::new(
+                        config_string,
+                        span,
+                        format!("Invalid selector for '{key}': {e}"),
+                    ));
+                }
+            }
+            Some(toml_edit::Value::String(format)) if key == "display.date_format" => {
+                if let Err(e) = format.value().parse::<DateFormat>() {
+                    problems.push(ConfigProblem::new(config_string, span, e.to_string()));
+                }
+            }
+            Some(toml_edit::Value::Integer(number)) => {
+                if let Some((min, max)) = Self::integer_range(key) {
+                    if !(min..=max).contains(number.value()) {
+                        let message = format!("'{key}' must be between {min} and {max}");
+                        problems.push(ConfigProblem::new(config_string, span, message));
+                    }
+                }
+            }
+            _ => {}
+        }
+        problems
+    }
+
+    /// The valid range for keys with integer values
+    fn integer_range(key: &str) -> Option<(i64, i64)> {
+        match key {
+            "display.max_width" => Some((0, i64::from(u16::MAX))),
+            "database.max_concurrent_requests" => Some((1, i64::from(u16::MAX))),
+            "database.future_entries"
+            | "database.past_entries"
+            | "database.refresh_stale_days"
+            | "logging.max_file_size"
+            | "logging.max_backups" => Some((0, i64::from(u32::MAX))),
+            _ => None,
+        }
+    }
+
+    /// Resolves a config path given by the user against the current directory (if relative). Nothing is created
+    ///
+    /// If `must_exist` is true, fails if there is no file at `path` since the user asked for that file specifically
+    pub fn use_config_path(path: &Path, must_exist: bool) -> Result<PathBuf, ReadConfigError> {
+        if must_exist && !path.is_file() {
+            let message = format!("'{}' does not exist", path.to_string_lossy());
+            return Err(ReadConfigError::NotFound(io::Error::new(io::ErrorKind::NotFound, message)));
+        }
+        Ok(env::current_dir().map_err(PathError::NoCurrentDir)?.join(path))
+    }
+
+    /// Creates the config file with the default settings if there isn't one
+    ///
+    /// Does nothing if the `LECTIO_DIEI_NO_CREATE_CONFIG` environment variable is set, or if `explicit_path` is given, since a
+    /// missing file there may be a mistyped path
+    pub fn create_if_missing(explicit_path: Option<&Path>) {
+        if env_var_set(env::var_os(NO_CREATE_CONFIG_ENV_VAR).as_deref()) || explicit_path.is_some() {
+            return;
+        }
         match path::create_and_get_config_path() {
+            Ok(path) if !path.exists() => {
+                info!(
+                    "No config file found at '{}'; Generating new config file with default settings",
+                    path.to_string_lossy()
+                );
+                if let Err(create_error) = Self::create_config(&path, false) {
+                    error!("Failed to create config file ({create_error})");
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("Failed to determine path for config file ({e}); Not creating one"),
+        }
+    }
+
+    pub fn initialize_default_config(explicit_path: Option<&Path>, force: bool) -> Result<(), InitConfigError> {
+        debug!("Creating a default config with force={}", force);
+        match Self::get_path(explicit_path) {
             Ok(path) => Self::create_config(&path, force).map_err(InitConfigError::from),
             Err(e) => Err(e.into()),
         }
     }
 
     //TODO This returns a ReadConfigError even when the error is a write error
-    pub fn upgrade_config() -> Result<(), ReadConfigError> {
-        let path = path::create_and_get_config_path()?;
+    pub fn upgrade_config(explicit_path: Option<&Path>) -> Result<(), ReadConfigError> {
+        let path = Self::get_path(explicit_path)?;
         //TODO handle case with no config file
         let config = match Self::from_file(&path) {
             Ok(config) => config,
@@ -105,6 +394,94 @@ impl Config {
         Ok(())
     }
 
+    /// Gets a single value from the config as a string
+    ///
+    /// Strings are returned without quotes, any other value is formatted as TOML
+    pub fn get_value(&self, key: &str) -> Result<String, UnknownKeyError> {
+        let (table_name, key_name) = Self::split_key(key)?;
+        let table = toml::Table::try_from(self).expect("Config should be serializable to TOML table");
+        let value = table
+            .get(table_name)
+            .and_then(|table| table.get(key_name))
+            .expect("Valid key should be present in serialized config");
+        match value {
+            toml::Value::String(string) => Ok(string.clone()),
+            other => Ok(other.to_string()),
+        }
+    }
+
+    /// Sets a single value in the config file, preserving comments and formatting of everything else
+    ///
+    /// The value is parsed as TOML if possible, otherwise it is treated as a string. The resulting config must deserialize
+    /// successfully or nothing is written. If there is no config file, the default config is used as the base
+    pub fn set_value(explicit_path: Option<&Path>, key: &str, value: &str) -> Result<(), SetConfigError> {
+        let (table_name, key_name) = Self::split_key(key)?;
+        let path = Self::get_path(explicit_path)?;
+        let mut doc = match fs::read_to_string(&path) {
+            Ok(config_string) => config_string.parse::<DocumentMut>()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                warn!("No config file found at '{}'; Using default config", path.to_string_lossy());
+                Self::default_document()
+            }
+            Err(e) => return Err(SetConfigError::IOError(e)),
+        };
+
+        let new_value = value.parse::<toml_edit::Value>().unwrap_or_else(|_| toml_edit::Value::from(value));
+        if !doc.contains_table(table_name) {
+            doc[table_name] = toml_edit::table();
+        }
+        let table = doc[table_name]
+            .as_table_mut()
+            .ok_or_else(|| SetConfigError::NotATable(table_name.to_owned()))?;
+        match table.get_mut(key_name) {
+            // Replacing the value of the existing item keeps the comment above the key
+            Some(item) => *item = toml_edit::Item::Value(new_value),
+            None => {
+                table.insert(key_name, toml_edit::Item::Value(new_value));
+            }
+        }
+
+        let doc_string = doc.to_string();
+        de::from_str::<Self>(&doc_string).map_err(|e| SetConfigError::InvalidValue {
+            key: key.to_owned(),
+            source: e,
+        })?;
+        fs::write(&path, doc_string).map_err(SetConfigError::IOError)?;
+        debug!("Set '{key}' to '{value}' in config at '{}'", path.to_string_lossy());
+        Ok(())
+    }
+
+    /// Splits a dotted key (e.g. `display.max_width`) in to the table and key, after checking that it is valid
+    fn split_key(key: &str) -> Result<(&str, &str), UnknownKeyError> {
+        let valid_keys = Self::valid_keys();
+        match key.split_once('.') {
+            Some(split) if valid_keys.iter().any(|valid| valid == key) => Ok(split),
+            _ => Err(UnknownKeyError {
+                key: key.to_owned(),
+                valid_keys: valid_keys.join(", "),
+            }),
+        }
+    }
+
+    /// Every key in the config, in the form 'table.key'
+    fn valid_keys() -> Vec<String> {
+        let default_table = toml::Table::try_from(Self::default()).expect("Default config should be serializable to TOML table");
+        let mut keys = Vec::new();
+        for (table_name, table) in default_table {
+            if let toml::Value::Table(table) = table {
+                for key in table.keys() {
+                    keys.push(format!("{table_name}.{key}"));
+                }
+            }
+        }
+        keys
+    }
+
+    /// Returns `explicit_path` if given, otherwise the default location of the config file
+    pub(crate) fn get_path(explicit_path: Option<&Path>) -> Result<PathBuf, PathError> {
+        explicit_path.map_or_else(path::create_and_get_config_path, |path| Ok(path.to_owned()))
+    }
+
     fn from_file(path: &PathBuf) -> Result<Self, ReadConfigError> {
         debug!("Reading config from path: {}", path.to_string_lossy());
         let mut config_string = String::new();
@@ -143,36 +520,14 @@ impl Config {
             "display",
             "reading_order",
             &format!(
-                "Defines which readings and what order. Possible values: {}\n# Use empty array to only display day",
+                "Defines which readings and what order. Possible values: {}\n# Use empty array to only display day. A reading listed more than once is only displayed the first time",
                 ReadingArg::variant_string()
             ),
         );
 
-        Self::set_key_comment(
-            &mut doc,
-            "display",
-            "original_linebreaks",
-            "Whether to use original linebreaks as displayed on USCCB site. If true, max_width is ignored. Note: Resp. Psalm and Alleluia always use original line breaks",
-        );
-
-        Self::set_key_comment(
-            &mut doc,
-            "display" ,
-            "max_width" ,
-            "Maximum width for formatting readings. Ignored if original_linebreaks is true. Not used for Psalm/Alleluia. Set to 0 for no line breaks" );
-
-        Self::set_key_comment(
-            &mut doc,
-            "database",
-            "future_entries",
-            "Number of days in to the future to try to keep in the database. Includes today (i.e. a value of 1 will only store today's readings)",
-        );
-        Self::set_key_comment(
-            &mut doc,
-            "database",
-            "past_entries",
-            "Number of days in to the past to try to keep in the database",
-        );
+        for (table, key, comment) in KEY_COMMENTS {
+            Self::set_key_comment(&mut doc, table, key, comment);
+        }
 
         doc
     }
@@ -193,18 +548,70 @@ impl Config {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebConfig {
+    #[serde(default = "WebConfig::default_base_url")]
+    pub base_url: String,
+    #[serde(default)]
+    pub save_html: bool,
+}
+
+impl WebConfig {
+    fn default_base_url() -> String {
+        client::DEFAULT_BASE_URL.to_owned()
+    }
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            base_url: Self::default_base_url(),
+            save_html: false,
+        }
+    }
+}
+
+/// CSS selectors for finding the readings on the page, for when the site changes its markup before a new release
+///
+/// An empty selector uses the default
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SelectorsConfig {
+    #[serde(default)]
+    pub container: String,
+    #[serde(default)]
+    pub day_name: String,
+    #[serde(default)]
+    pub readings: String,
+    #[serde(default)]
+    pub reading_name: String,
+    #[serde(default)]
+    pub reading_content: String,
+    #[serde(default)]
+    pub reading_location: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DbConfig {
     #[serde(default = "DbConfig::default_future_entries")]
     pub future_entries: u32,
     #[serde(default)]
     pub past_entries: u32,
+    #[serde(default = "DbConfig::default_max_concurrent_requests")]
+    pub max_concurrent_requests: u16,
+    #[serde(default)]
+    pub path: PathBuf,
+    #[serde(default)]
+    pub refresh_stale_days: u32,
 }
 
 impl DbConfig {
     fn default_future_entries() -> u32 {
         30
     }
+
+    fn default_max_concurrent_requests() -> u16 {
+        4
+    }
 }
 
 impl Default for DbConfig {
@@ -212,18 +619,107 @@ impl Default for DbConfig {
         Self {
             future_entries: Self::default_future_entries(),
             past_entries: u32::default(),
+            max_concurrent_requests: Self::default_max_concurrent_requests(),
+            path: PathBuf::default(),
+            refresh_stale_days: u32::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct LoggingConfig {
+    #[serde(default = "LoggingConfig::default_file_level", with = "level_filter_lowercase")]
+    pub file_level: LevelFilter,
+    #[serde(default = "LoggingConfig::default_max_file_size")]
+    pub max_file_size: u32,
+    #[serde(default = "LoggingConfig::default_max_backups")]
+    pub max_backups: u32,
+    #[serde(default)]
+    pub file_format: LogFormat,
+}
+
+/// Format of the lines in the log file
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for log aggregators
+    Json,
+}
+
+impl LoggingConfig {
+    fn default_file_level() -> LevelFilter {
+        LevelFilter::Debug
+    }
+
+    fn default_max_file_size() -> u32 {
+        10
+    }
+
+    fn default_max_backups() -> u32 {
+        3
+    }
+}
+
+/// (De)serializes a `LevelFilter` as a lowercase string (e.g. "debug") to match the other config values
+mod level_filter_lowercase {
+    use log::LevelFilter;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    // Reason: signature required by serde's `with` attribute
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(level: &LevelFilter, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&level.as_str().to_lowercase())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<LevelFilter, D::Error> {
+        let level_string = String::deserialize(deserializer)?;
+        level_string.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            file_level: Self::default_file_level(),
+            max_file_size: Self::default_max_file_size(),
+            max_backups: Self::default_max_backups(),
+            file_format: LogFormat::default(),
         }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct DisplayConfig {
     #[serde(default = "DisplayConfig::default_reading_order")]
     pub reading_order: Vec<ReadingArg>,
     #[serde(default)]
     pub original_linebreaks: bool,
-    #[serde(default = "DisplayConfig::default_width")]
-    pub max_width: u16,
+    #[serde(default)]
+    pub max_width: MaxWidth,
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(default)]
+    pub reading_length: ReadingLength,
+    #[serde(default)]
+    pub expand_refs: bool,
+    #[serde(default)]
+    pub separator_style: SeparatorStyle,
+    #[serde(default)]
+    pub short_labels: bool,
+    #[serde(default)]
+    pub ascii: bool,
+    #[serde(default = "DisplayConfig::default_include_responses")]
+    pub include_responses: bool,
+    #[serde(default)]
+    pub psalm_keep_verse_number: bool,
+    #[serde(default)]
+    pub date_format: DateFormat,
+    #[serde(default)]
+    pub format: DisplayFormat,
 }
 
 impl DisplayConfig {
@@ -231,17 +727,70 @@ impl DisplayConfig {
         vec![ReadingArg::Reading1, ReadingArg::Reading2, ReadingArg::Gospel]
     }
 
-    fn default_width() -> u16 {
+    fn default_include_responses() -> bool {
+        true
+    }
+
+    /// Width used when the max width is "auto" but the terminal width can't be determined
+    pub fn default_width() -> u16 {
         140
     }
 }
 
+/// Maximum width for formatting readings
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MaxWidth {
+    /// Use the width of the terminal. Written as "auto" in the config
+    #[default]
+    Auto,
+    /// A fixed number of columns. 0 means no line breaks
+    Columns(u16),
+}
+
+impl Serialize for MaxWidth {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Auto => serializer.serialize_str("auto"),
+            Self::Columns(columns) => serializer.serialize_u16(*columns),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MaxWidth {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        const EXPECTED: &str = "expected a number of columns or \"auto\"";
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawMaxWidth {
+            Columns(u16),
+            Keyword(String),
+        }
+
+        match RawMaxWidth::deserialize(deserializer).map_err(|_| serde::de::Error::custom(EXPECTED))? {
+            RawMaxWidth::Columns(columns) => Ok(Self::Columns(columns)),
+            RawMaxWidth::Keyword(keyword) if keyword == "auto" => Ok(Self::Auto),
+            RawMaxWidth::Keyword(_) => Err(serde::de::Error::custom(EXPECTED)),
+        }
+    }
+}
+
 impl Default for DisplayConfig {
     fn default() -> Self {
         Self {
             reading_order: Self::default_reading_order(),
             original_linebreaks: bool::default(),
-            max_width: Self::default_width(),
+            max_width: MaxWidth::default(),
+            offline: bool::default(),
+            reading_length: ReadingLength::default(),
+            expand_refs: bool::default(),
+            separator_style: SeparatorStyle::default(),
+            short_labels: bool::default(),
+            ascii: bool::default(),
+            include_responses: Self::default_include_responses(),
+            psalm_keep_verse_number: bool::default(),
+            date_format: DateFormat::default(),
+            format: DisplayFormat::default(),
         }
     }
 }
@@ -296,6 +845,73 @@ pub enum ReadConfigError {
     DeserializationError(#[from] de::Error),
 }
 
+/// Represents a failure to use the display settings from a profile
+#[derive(thiserror::Error, Debug)]
+pub enum ProfileError {
+    #[error("No profile named '{name}'. Available profiles: {}", if available.is_empty() { "(none)".to_owned() } else { available.join(", ") })]
+    Unknown { name: String, available: Vec<String> },
+    #[error("Invalid display settings in profile '{name}': ({source})")]
+    Invalid { name: String, source: Box<de::Error> },
+}
+
+/// A single problem found while checking the config file
+#[derive(Debug)]
+pub struct ConfigProblem {
+    line: Option<usize>,
+    message: String,
+}
+
+impl ConfigProblem {
+    /// Creates a problem, converting the span (byte offsets in to the config string) to a line number
+    fn new(config_string: &str, span: Option<Range<usize>>, message: String) -> Self {
+        let line = span.map(|span| config_string[..span.start.min(config_string.len())].matches('\n').count() + 1);
+        Self { line, message }
+    }
+}
+
+impl Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Represents a config file that could not be checked or that has problems
+#[derive(thiserror::Error, Debug)]
+pub enum CheckConfigError {
+    #[error("{0}")]
+    ReadError(#[from] ReadConfigError),
+    #[error("Found {0} problem(s) in config file")]
+    ProblemsFound(usize),
+}
+
+/// Represents a key that does not exist in the config
+#[derive(thiserror::Error, Debug)]
+#[error("Unknown config key '{key}'. Valid keys are: {valid_keys}")]
+pub struct UnknownKeyError {
+    key: String,
+    valid_keys: String,
+}
+
+/// Represents a failure to set a single value in the config file
+#[derive(thiserror::Error, Debug)]
+pub enum SetConfigError {
+    #[error("{0}")]
+    UnknownKey(#[from] UnknownKeyError),
+    #[error("Cannot get path to config file: ({0})")]
+    CannotGetPath(#[from] PathError),
+    #[error("Failed to parse existing config file: ({0})")]
+    ParseError(#[from] TomlError),
+    #[error("'{0}' in config file is not a table")]
+    NotATable(String),
+    #[error("Invalid value for '{key}': ({source})")]
+    InvalidValue { key: String, source: de::Error },
+    #[error("I/O Error encountered while setting config value: ({0})")]
+    IOError(#[source] io::Error),
+}
+
 /// `io::Error` can be `NotFound` or a generic `IOError`
 impl From<io::Error> for ReadConfigError {
     fn from(value: io::Error) -> Self {
@@ -306,9 +922,15 @@ impl From<io::Error> for ReadConfigError {
     }
 }
 
+/// An environment variable that is set to an empty value counts as not set
+fn env_var_set(value: Option<&std::ffi::OsStr>) -> bool {
+    value.is_some_and(|value| !value.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::html;
 
     /// Ensures that the default config serializes to a valid document
     #[test]
@@ -316,4 +938,156 @@ mod tests {
         // Just make sure it doesn't panic
         let _ = Config::default_document();
     }
+
+    #[test]
+    fn split_valid_key() {
+        assert_eq!(("display", "max_width"), Config::split_key("display.max_width").unwrap());
+    }
+
+    #[test]
+    fn get_value_from_default() {
+        let config = Config::default();
+        assert_eq!("auto", config.get_value("display.max_width").unwrap());
+        assert_eq!("long", config.get_value("display.reading_length").unwrap());
+        assert_eq!(
+            r#"["reading1", "reading2", "gospel"]"#,
+            config.get_value("display.reading_order").unwrap()
+        );
+        assert!(config.get_value("display.not_a_key").is_err());
+    }
+
+    #[test]
+    fn default_document_has_no_problems() {
+        assert!(Config::check_str(&Config::default_document().to_string()).is_empty());
+    }
+
+    #[test]
+    fn check_finds_problems_with_lines() {
+        let config_string = "[display]\nmax_width = -5\nreading_order = [\"gospel\", \"gosple\"]\nnot_a_key = 1\n\n[extra]\n";
+        let problems: Vec<String> = Config::check_str(config_string).iter().map(ToString::to_string).collect();
+        assert_eq!(4, problems.len());
+        assert!(problems[0].starts_with("line 2: 'display.max_width'"));
+        assert!(problems[1].starts_with("line 3: Invalid entry \"gosple\""));
+        assert!(problems[2].starts_with("line 4: Unknown key 'display.not_a_key'"));
+        assert!(problems[3].starts_with("line 6: Unknown table 'extra'"));
+    }
+
+    #[test]
+    fn selector_comments_list_defaults() {
+        let defaults = [
+            ("container", html::DEFAULT_CONTAINER_SELECTOR),
+            ("day_name", html::DEFAULT_DAY_NAME_SELECTOR),
+            ("readings", html::DEFAULT_READINGS_SELECTOR),
+            ("reading_name", html::DEFAULT_READING_NAME_SELECTOR),
+            ("reading_content", html::DEFAULT_READING_CONTENT_SELECTOR),
+            ("reading_location", html::DEFAULT_READING_LOCATION_SELECTOR),
+        ];
+        for (key, default) in defaults {
+            let (_, _, comment) = KEY_COMMENTS
+                .iter()
+                .find(|(table, k, _)| *table == "selectors" && *k == key)
+                .unwrap();
+            assert!(comment.ends_with(&format!("Default: \"{default}\"")), "{key}");
+        }
+    }
+
+    #[test]
+    fn check_finds_invalid_selector() {
+        let problems = Config::check_str("[selectors]\nreadings = \"div..verse\"\ncontainer = \"\"\n");
+        assert_eq!(1, problems.len());
+        assert!(problems[0]
+            .to_string()
+            .starts_with("line 2: Invalid selector for 'selectors.readings'"));
+        assert!(Config::check_str("[selectors]\nreadings = \"section.reading\"\n").is_empty());
+    }
+
+    #[test]
+    fn check_finds_duplicate_readings() {
+        let problems = Config::check_str("[display]\nreading_order = [\"gospel\", \"psalm\", \"gospel\"]\n");
+        assert_eq!(1, problems.len());
+        assert!(problems[0].to_string().starts_with("line 2: Duplicate entry \"gospel\""));
+    }
+
+    #[test]
+    fn check_finds_wrong_type() {
+        let problems = Config::check_str("[display]\noffline = \"yes\"\n");
+        assert_eq!(1, problems.len());
+        assert_eq!(Some(2), problems[0].line);
+    }
+
+    #[test]
+    fn display_format_from_config() {
+        let config: Config = de::from_str("[display]\nformat = \"json\"\n").unwrap();
+        assert_eq!(DisplayFormat::Json, config.display.format);
+        assert_eq!(DisplayFormat::Pretty, Config::default().display.format);
+        assert_eq!(1, Config::check_str("[display]\nformat = \"markdown\"\n").len());
+    }
+
+    #[test]
+    fn max_width_auto_or_columns() {
+        let auto: Config = de::from_str("[display]\nmax_width = \"auto\"\n").unwrap();
+        assert_eq!(MaxWidth::Auto, auto.display.max_width);
+        let columns: Config = de::from_str("[display]\nmax_width = 80\n").unwrap();
+        assert_eq!(MaxWidth::Columns(80), columns.display.max_width);
+        assert_eq!(1, Config::check_str("[display]\nmax_width = \"wide\"\n").len());
+    }
+
+    #[test]
+    fn check_finds_invalid_base_url() {
+        let problems = Config::check_str("[web]\nbase_url = \"not a url\"\n");
+        assert_eq!(1, problems.len());
+        assert_eq!(Some(2), problems[0].line);
+        assert!(Config::check_str("[web]\nbase_url = \"http://127.0.0.1:8080\"\n").is_empty());
+    }
+
+    #[test]
+    fn invalid_date_format_fails_to_load() {
+        assert!(de::from_str::<Config>("[display]\ndate_format = \"%m-%d\"\n").is_err());
+        let config: Config = de::from_str("[display]\ndate_format = \"%d.%m.%Y\"\n").unwrap();
+        assert_eq!("%d.%m.%Y", config.display.date_format.as_str());
+
+        let problems = Config::check_str("[display]\ndate_format = \"%Q\"\n");
+        assert_eq!(1, problems.len());
+        assert_eq!(Some(2), problems[0].line);
+    }
+
+    #[test]
+    fn profile_overrides_display() {
+        let config: Config = de::from_str("[display]\noffline = true\nmax_width = 80\n[profiles.export]\nmax_width = 0\n").unwrap();
+        let config = config.with_profile("export").unwrap();
+        assert_eq!(MaxWidth::Columns(0), config.display.max_width);
+        assert!(config.display.offline);
+    }
+
+    #[test]
+    fn unknown_or_invalid_profile_fails() {
+        let config: Config = de::from_str("[profiles.export]\nmax_width = \"wide\"\n").unwrap();
+        assert!(matches!(
+            Config::default().with_profile("export"),
+            Err(ProfileError::Unknown { .. })
+        ));
+        assert!(matches!(config.with_profile("export"), Err(ProfileError::Invalid { .. })));
+    }
+
+    #[test]
+    fn check_finds_invalid_profile_keys() {
+        let problems = Config::check_str("[profiles.export]\nmax_width = 0\nfuture_entries = 5\n");
+        assert_eq!(1, problems.len());
+        assert_eq!(Some(3), problems[0].line);
+        assert!(Config::check_str("[profiles.export]\nmax_width = 0\noffline = true\n").is_empty());
+    }
+
+    #[test]
+    fn split_unknown_key() {
+        assert!(Config::split_key("display.not_a_key").is_err());
+        assert!(Config::split_key("display").is_err());
+        assert!(Config::split_key("").is_err());
+    }
+
+    #[test]
+    fn empty_env_var_not_set() {
+        assert!(!env_var_set(None));
+        assert!(!env_var_set(Some(std::ffi::OsStr::new(""))));
+        assert!(env_var_set(Some(std::ffi::OsStr::new("1"))));
+    }
 }
@@ -1,8 +1,10 @@
 use std::cmp::Ordering;
-use std::fmt::{self, Display};
+use std::fmt::{self, Display, Write};
+use std::str::FromStr;
 
-use chrono::format::ParseError;
-use chrono::{DateTime, Local, NaiveDate, TimeDelta};
+use chrono::format::{Item, ParseError, StrftimeItems};
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeDelta, Weekday};
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use sqlx::{
     sqlite::{Sqlite, SqliteValueRef},
@@ -14,7 +16,10 @@ use sqlx::{
 const DATE_ID_FORMAT: &str = "%m%d%y";
 
 /// Type-checked `String` used for url retrieval and database ids
-#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+///
+/// Serialized as the id string (e.g. "040124"), which is validated when deserialized
+#[derive(Debug, Clone, PartialEq, Eq, FromRow, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 pub struct DateId {
     id: String,
 }
@@ -22,11 +27,13 @@ pub struct DateId {
 impl DateId {
     /// Reference to inner value
     /// Use this for binding to `sqlx` queries because implementing the `Encode` trait is more work than it's worth
+    #[must_use]
     pub fn as_str(&self) -> &str {
         &self.id
     }
 
     /// Gets the `DateId` for today, local time
+    #[must_use]
     pub fn today() -> Self {
         Self::from_local_datetime(&Local::now())
     }
@@ -34,14 +41,38 @@ impl DateId {
     /// Checks that a given `str` is a valid `DateId` before returning it
     ///
     /// First converts to a `NaiveDate`, then  back to a `String` for storage within `DateId` struct
+    /// # Errors
+    /// Returns a `ParseError` if the string is not a valid date in the `MMddYY` format
     pub fn checked_from_str(date_string: &str) -> Result<Self, ParseError> {
         let date = NaiveDate::parse_from_str(date_string, DATE_ID_FORMAT)?;
         Ok(Self::from_date(date))
     }
 
+    /// Parses a date given as an argument, trying the given format first and then the `MMddYY` format
+    ///
+    /// # Errors
+    /// Returns a `ParseError` if the string is in neither format
+    pub fn parse_with_format(date_string: &str, format: &DateFormat) -> Result<Self, ParseError> {
+        NaiveDate::parse_from_str(date_string, format.as_str())
+            .map(Self::from_date)
+            .or_else(|_| Self::checked_from_str(date_string))
+    }
+
+    /// Creates a `DateId` from the year, month, and day
+    ///
+    /// # Errors
+    /// Returns an `InvalidDateError` if the values are not a valid date (e.g. February 30th)
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Result<Self, InvalidDateError> {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .map(Self::from_date)
+            .ok_or(InvalidDateError { year, month, day })
+    }
+
     /// Gets a list of `DateId`s for a range
     ///
     /// Note that `future_days` must include today (i.e. if future days is 0, today will not be included)
+    #[deprecated(note = "use `DateId::window`, where `future_days` does not count today")]
+    #[must_use]
     pub fn get_list(past_days: u32, future_days: u32) -> Vec<DateId> {
         let length = past_days + future_days;
         let mut list = Vec::with_capacity(length as usize);
@@ -53,7 +84,69 @@ impl DateId {
         list
     }
 
+    /// Gets the `DateId`s from `past_days` before today to `future_days` after today. Today is always included
+    ///
+    /// e.g. `window(0, 0)` is only today, and `window(1, 2)` is yesterday, today, and the two days after
+    #[must_use]
+    pub fn window(past_days: u32, future_days: u32) -> Vec<DateId> {
+        Self::window_around(&Self::today(), past_days, future_days)
+    }
+
+    /// Like `window`, but relative to `center` instead of today
+    fn window_around(center: &DateId, past_days: u32, future_days: u32) -> Vec<DateId> {
+        let date = center.to_naive_date();
+        let first = Self::from_date(date - TimeDelta::days(i64::from(past_days)));
+        let last = Self::from_date(date + TimeDelta::days(i64::from(future_days)));
+        Self::get_range(&first, &last)
+    }
+
+    /// Gets the `DateId` for the day before this one
+    #[must_use]
+    pub fn previous_day(&self) -> Self {
+        Self::from_date(self.to_naive_date() - TimeDelta::days(1))
+    }
+
+    /// Gets the `DateId` for the day after this one
+    #[must_use]
+    pub fn next_day(&self) -> Self {
+        Self::from_date(self.to_naive_date() + TimeDelta::days(1))
+    }
+
+    /// Gets the `DateId`s from `first` to `last`, including both. Empty if `last` is before `first`
+    #[must_use]
+    pub fn get_range(first: &DateId, last: &DateId) -> Vec<DateId> {
+        let mut range = Vec::new();
+        let mut current = first.clone();
+        while &current <= last {
+            let next = current.next_day();
+            range.push(current);
+            current = next;
+        }
+        range
+    }
+
+    /// Converts back to the date this id represents
+    /// # Panics
+    /// Will not panic since a `DateId` always holds a valid date
+    #[must_use]
+    pub fn to_naive_date(&self) -> NaiveDate {
+        NaiveDate::parse_from_str(&self.id, DATE_ID_FORMAT).expect("DateId is always a valid date")
+    }
+
+    /// Gets the day of the week for this date
+    #[must_use]
+    pub fn weekday(&self) -> Weekday {
+        self.to_naive_date().weekday()
+    }
+
+    /// True if this date is a Sunday
+    #[must_use]
+    pub fn is_sunday(&self) -> bool {
+        self.weekday() == Weekday::Sun
+    }
+
     /// Returns a `DateId` for given local `DateTime`
+    #[must_use]
     pub fn from_local_datetime(date: &DateTime<Local>) -> Self {
         let id = date.format(DATE_ID_FORMAT).to_string();
         Self { id }
@@ -72,6 +165,101 @@ impl Display for DateId {
     }
 }
 
+impl TryFrom<String> for DateId {
+    type Error = InvalidDateIdError;
+
+    /// Checks that the id is 6 digits and a valid date in the `MMddYY` format
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.len() != 6 || !value.chars().all(|c| c.is_ascii_digit()) {
+            return Err(InvalidDateIdError { id: value });
+        }
+        Self::checked_from_str(&value).map_err(|_| InvalidDateIdError { id: value })
+    }
+}
+
+impl From<DateId> for String {
+    fn from(date_id: DateId) -> Self {
+        date_id.id
+    }
+}
+
+/// Error for `TryFrom<String>` on `DateId`
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid date id '{id}'. Must be a date in the MMddYY format (e.g. 040124)")]
+pub struct InvalidDateIdError {
+    id: String,
+}
+
+/// A chrono strftime format for dates given as arguments (e.g. "%d.%m.%Y"). Dates in the `MMddYY` format are always accepted too
+///
+/// Only formats that contain a full date are valid, so that every parsed date is unambiguous
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DateFormat(String);
+
+impl DateFormat {
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        Self(String::from(DATE_ID_FORMAT))
+    }
+}
+
+impl FromStr for DateFormat {
+    type Err = InvalidDateFormatError;
+
+    /// Checks that the format is valid and can be used to parse back a date written with it
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        let error = || InvalidDateFormatError { format: format.to_owned() };
+        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(error());
+        }
+        let sample = NaiveDate::from_ymd_opt(2024, 7, 14).expect("valid date");
+        // Formatting fails (instead of panicking as with to_string) if the format has fields that a date doesn't have
+        let mut formatted = String::new();
+        write!(formatted, "{}", sample.format(format)).map_err(|_| error())?;
+        match NaiveDate::parse_from_str(&formatted, format) {
+            Ok(parsed) if parsed == sample => Ok(Self(format.to_owned())),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl TryFrom<String> for DateFormat {
+    type Error = InvalidDateFormatError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<DateFormat> for String {
+    fn from(format: DateFormat) -> Self {
+        format.0
+    }
+}
+
+/// Error for `FromStr` on `DateFormat`
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid date format '{format}'. Must be a strftime format with a year, month, and day (e.g. \"%d.%m.%Y\")")]
+pub struct InvalidDateFormatError {
+    format: String,
+}
+
+/// The year, month, and day given to `DateId::from_ymd` are not a valid date
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("Invalid date: {year}-{month:02}-{day:02}")]
+pub struct InvalidDateError {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
 impl<'r> Decode<'r, Sqlite> for DateId {
     fn decode(value_ref: SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
         let id = <&str as Decode<Sqlite>>::decode(value_ref)?.to_owned();
@@ -95,12 +283,9 @@ impl PartialOrd for DateId {
 }
 
 impl Ord for DateId {
+    /// Compares the full dates so that the order is correct across centuries (e.g. "123199" is before "010100")
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // First compares years, then month-day
-        match self.id[4..6].cmp(&other.id[4..6]) {
-            Ordering::Equal => self.id[0..4].cmp(&other.id[0..4]),
-            ord => ord,
-        }
+        self.to_naive_date().cmp(&other.to_naive_date())
     }
 }
 
@@ -132,8 +317,135 @@ mod tests {
     }
 
     #[test]
+    fn parse_with_configured_format() {
+        let format = DateFormat::from_str("%d.%m.%Y").unwrap();
+        assert_eq!("071424", DateId::parse_with_format("14.07.2024", &format).unwrap().as_str());
+        // MMddYY is always accepted
+        assert_eq!("071424", DateId::parse_with_format("071424", &format).unwrap().as_str());
+        assert!(DateId::parse_with_format("2024-07-14", &format).is_err());
+
+        let default = DateFormat::default();
+        assert_eq!("071424", DateId::parse_with_format("071424", &default).unwrap().as_str());
+    }
+
+    #[test]
+    fn invalid_date_formats() {
+        assert!(DateFormat::from_str("%Y-%m-%d").is_ok());
+        assert!(DateFormat::from_str("%Q").is_err());
+        assert!(DateFormat::from_str("%m-%d").is_err());
+        assert!(DateFormat::from_str("%H:%M").is_err());
+        assert!(DateFormat::from_str("").is_err());
+    }
+
+    #[test]
+    fn serde_json_round_trip() {
+        let date_id = DateId::checked_from_str("071424").unwrap();
+        let json = serde_json::to_string(&date_id).unwrap();
+        assert_eq!("\"071424\"", json);
+        assert_eq!(date_id, serde_json::from_str::<DateId>(&json).unwrap());
+
+        assert!(serde_json::from_str::<DateId>("\"abc\"").is_err());
+        assert!(serde_json::from_str::<DateId>("\"71424\"").is_err());
+        assert!(serde_json::from_str::<DateId>("\"133124\"").is_err());
+    }
+
+    #[test]
+    fn from_ymd_valid() {
+        assert_eq!("071424", DateId::from_ymd(2024, 7, 14).unwrap().as_str());
+        assert_eq!("022924", DateId::from_ymd(2024, 2, 29).unwrap().as_str());
+    }
+
+    #[test]
+    fn from_ymd_invalid() {
+        assert_eq!(
+            InvalidDateError {
+                year: 2024,
+                month: 2,
+                day: 30
+            },
+            DateId::from_ymd(2024, 2, 30).unwrap_err()
+        );
+        assert!(DateId::from_ymd(2025, 2, 29).is_err());
+        assert!(DateId::from_ymd(2024, 13, 1).is_err());
+        assert!(DateId::from_ymd(2024, 0, 1).is_err());
+    }
+
+    #[test]
+    fn previous_day_crosses_year() {
+        let date_id = DateId::checked_from_str("010125").unwrap();
+        assert_eq!("123124", date_id.previous_day().as_str());
+    }
+
+    #[test]
+    fn range_includes_both_ends() {
+        let first = DateId::checked_from_str("123024").unwrap();
+        let last = DateId::checked_from_str("010225").unwrap();
+        let range: Vec<String> = DateId::get_range(&first, &last).iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["123024", "123124", "010125", "010225"], range);
+        assert_eq!(vec![first.clone()], DateId::get_range(&first, &first));
+        assert!(DateId::get_range(&last, &first).is_empty());
+    }
+
+    #[test]
+    fn known_sunday_is_sunday() {
+        let date_id = DateId::checked_from_str("071424").unwrap();
+        assert_eq!(Weekday::Sun, date_id.weekday());
+        assert!(date_id.is_sunday());
+        assert!(!date_id.previous_day().is_sunday());
+    }
+
+    #[test]
+    fn ordered_across_century() {
+        let end_of_1999 = DateId::from_ymd(1999, 12, 31).unwrap();
+        let start_of_2000 = DateId::from_ymd(2000, 1, 1).unwrap();
+        assert_eq!("123199", end_of_1999.as_str());
+        assert_eq!("010100", start_of_2000.as_str());
+        assert!(end_of_1999 < start_of_2000);
+        assert_eq!(end_of_1999, start_of_2000.previous_day());
+
+        let mut ids = [
+            DateId::checked_from_str("010200").unwrap(),
+            DateId::checked_from_str("123199").unwrap(),
+            DateId::checked_from_str("010100").unwrap(),
+            DateId::checked_from_str("123099").unwrap(),
+        ];
+        ids.sort();
+        let sorted: Vec<&str> = ids.iter().map(DateId::as_str).collect();
+        assert_eq!(vec!["123099", "123199", "010100", "010200"], sorted);
+    }
+
+    #[test]
+    fn ordered_within_year() {
+        let earlier = DateId::checked_from_str("021524").unwrap();
+        let later = DateId::checked_from_str("110324").unwrap();
+        assert!(earlier < later);
+        assert!(DateId::checked_from_str("123123").unwrap() < earlier);
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn get_list_correct_length() {
         let list = DateId::get_list(5, 3);
         assert_eq!(8, list.len());
     }
+
+    #[test]
+    fn window_always_includes_today() {
+        let today = DateId::today();
+        assert_eq!(vec![today.clone()], DateId::window(0, 0));
+        let window = DateId::window(5, 3);
+        assert_eq!(9, window.len());
+        assert_eq!(Some(&today), window.get(5));
+    }
+
+    #[test]
+    fn window_boundaries() {
+        let center = DateId::checked_from_str("123124").unwrap();
+        let window: Vec<String> = DateId::window_around(&center, 2, 1).iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["122924", "123024", "123124", "010125"], window);
+        let window: Vec<String> = DateId::window_around(&center, 0, 1).iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["123124", "010125"], window);
+        let window: Vec<String> = DateId::window_around(&center, 1, 0).iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["123024", "123124"], window);
+    }
 }
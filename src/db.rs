@@ -1,15 +1,30 @@
-﻿use std::fmt::{self, Display};
-
+use std::env;
+use std::fmt::{self, Display};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, Utc};
+use futures_util::{stream, Stream, StreamExt};
 use log::*;
 use sqlx::{
     migrate::{MigrateDatabase, MigrateError},
-    sqlite::{Sqlite, SqlitePool},
+    sqlite::{Sqlite, SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions},
     Executor, FromRow, Row, Transaction,
 };
 
-use crate::{date::DateId, path::PathError};
+use crate::config::Config;
 use crate::lectionary::{Lectionary, Reading};
 use crate::path::{self};
+use crate::rank::Rank;
+use crate::{date::DateId, path::PathError};
+
+/// Environment variable that overrides the location of the database file
+const DB_PATH_ENV_VAR: &str = "LECTIO_DIEI_DB";
+/// How long a connection waits for another process to release its lock on the database before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// A wrapper over a Sqlite pool that defines functions for working with the database
 #[derive(Clone)]
@@ -18,30 +33,79 @@ pub struct DatabaseHandle {
 }
 
 impl DatabaseHandle {
-    pub async fn new() -> Result<Self, DatabaseInitError> {
-        let db_url = Self::get_db_url()?;
+    /// Opens the database, creating it and running any migrations if needed
+    ///
+    /// The location is read from the config file at `config_path`, or the default config file if not given
+    ///
+    /// # Errors
+    /// Returns a `DatabaseInitError` if the database could not be located, created, or connected to
+    pub async fn new(config_path: Option<&Path>) -> Result<Self, DatabaseInitError> {
+        let db_url = Self::get_db_url(config_path)?;
         let pool = Self::init_db(&db_url).await?;
 
         Ok(Self { connection: pool })
     }
 
+    /// Opens a new, empty database that only exists in memory and runs the migrations on it
+    ///
+    /// Nothing is written to the filesystem. The database is discarded when the last clone of the handle is dropped
+    ///
+    /// # Errors
+    /// Returns a `DatabaseInitError` if the database could not be opened or the migrations failed
+    pub async fn new_in_memory() -> Result<Self, DatabaseInitError> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:").map_err(DatabaseInitError::PoolCreationFailed)?;
+        // Every connection to an in-memory database gets a separate database, so the pool keeps exactly one connection open
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .map_err(DatabaseInitError::PoolCreationFailed)?;
+        Self::prepare_pool(&pool).await?;
+
+        Ok(Self { connection: pool })
+    }
+
     /// Inserts a lectionary data into the lectionary and readings tables
+    ///
+    /// If the lectionary is already stored, it is updated in place. Stored readings it doesn't have are removed
+    ///
+    /// # Errors
+    /// Returns an error if any insert fails, in which case nothing is stored
     pub async fn insert_lectionary(&self, lectionary: &Lectionary) -> Result<(), sqlx::Error> {
         let mut transaction = self.connection.begin().await?;
 
         let id = lectionary.get_id();
 
-        let insert_lect = sqlx::query("INSERT OR REPLACE INTO lectionary (id, name) VALUES ($1, $2)")
-            .bind(id.as_str())
-            .bind(lectionary.get_day_name());
+        // Not INSERT OR REPLACE, which would delete the row and cascade the delete to its readings
+        let insert_lect = sqlx::query(
+            "INSERT INTO lectionary (id, name, rank, fetched_at) VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (id) DO UPDATE SET name=excluded.name, rank=excluded.rank, fetched_at=excluded.fetched_at",
+        )
+        .bind(id.as_str())
+        .bind(lectionary.get_day_name())
+        .bind(lectionary.rank().map(Rank::as_str))
+        .bind(Utc::now().timestamp());
         transaction.execute(insert_lect).await?;
 
-        Self::insert_reading(&mut transaction, lectionary.get_reading_1(), id, DbReadingType::FirstReading).await?;
-        Self::insert_reading(&mut transaction, lectionary.get_resp_psalm(), id, DbReadingType::Psalm).await?;
-        Self::insert_reading(&mut transaction, lectionary.get_gospel(), id, DbReadingType::Gospel).await?;
-        Self::insert_reading(&mut transaction, lectionary.get_alleluia(), id, DbReadingType::Alleluia).await?;
-        if let Some(reading_2) = lectionary.get_reading_2() {
-            Self::insert_reading(&mut transaction, reading_2, id, DbReadingType::SecondReading).await?;
+        let readings = [
+            (lectionary.get_reading_1(), DbReadingType::FirstReading),
+            (lectionary.get_resp_psalm(), DbReadingType::Psalm),
+            (lectionary.get_gospel(), DbReadingType::Gospel),
+            (lectionary.get_alleluia(), DbReadingType::Alleluia),
+            (lectionary.get_reading_2(), DbReadingType::SecondReading),
+        ];
+        for (reading, reading_type) in readings {
+            if let Some(reading) = reading {
+                Self::insert_reading(&mut transaction, reading, id, reading_type).await?;
+            } else {
+                let delete_reading = sqlx::query("DELETE FROM reading WHERE lectionary_id=$1 AND reading_type=$2")
+                    .bind(id.as_str())
+                    .bind(reading_type.as_str());
+                transaction.execute(delete_reading).await?;
+            }
         }
 
         transaction.commit().await
@@ -50,32 +114,39 @@ impl DatabaseHandle {
     /// Gets a lectionary from the database
     ///
     /// Requires reading from both the lectionary table and then the readings table
+    ///
+    /// # Errors
+    /// Returns `DatabaseGetError::NotPresent` if there is no lectionary with the id, or a `QueryError` if a query fails
     pub async fn get_lectionary(&self, id: &DateId) -> Result<Lectionary, DatabaseGetError> {
-        let lect_row = sqlx::query_as::<_, LectionaryRow>("SELECT id, name FROM lectionary WHERE id = $1 LIMIT 1")
+        let lect_row = sqlx::query_as::<_, LectionaryRow>("SELECT id, name, fetched_at FROM lectionary WHERE id = $1 LIMIT 1")
             .bind(id.as_str())
             .fetch_optional(&self.connection)
             .await?
             .ok_or(DatabaseGetError::NotPresent)?;
 
-        let first_reading_row = self.get_reading_row(id, DbReadingType::FirstReading).await?;
-        let psalm_row = self.get_reading_row(id, DbReadingType::Psalm).await?;
-        let gospel_row = self.get_reading_row(id, DbReadingType::Gospel).await?;
-        let second_reading_row = self.get_reading_row(id, DbReadingType::SecondReading).await.ok();
-        let alleluia_row = self.get_reading_row(id, DbReadingType::Alleluia).await?;
-
-        let entity = LectionaryDbEntity {
-            lect_row,
-            first_reading_row,
-            psalm_row,
-            gospel_row,
-            second_reading_row,
-            alleluia_row,
-        };
-
+        let entity = self.get_lectionary_entity(lect_row).await?;
         Ok(Lectionary::from(entity))
     }
 
+    /// Streams every lectionary in the database, sorted by date. Each one is only read from the database once the stream reaches it
+    ///
+    /// Meant for going through the whole database (e.g. exporting it) without holding every lectionary in memory at once. Only
+    /// the ids are read up front
+    ///
+    /// # Errors
+    /// Returns a `DatabaseGetError` if the ids can't be read
+    pub async fn stream_lectionaries(&self) -> Result<impl Stream<Item = Result<Lectionary, DatabaseGetError>> + '_, DatabaseGetError> {
+        let mut ids = self.get_all_ids().await?;
+        // The MMddYY ids don't sort by date as text, so they are sorted with the century handling of DateId
+        ids.sort_unstable();
+        Ok(stream::iter(ids).then(move |id| async move { self.get_lectionary(&id).await }))
+    }
+
     /// Removes a single lectionary by its `DateId`
+    ///
+    /// Returns true if the lectionary was present and removed
+    /// # Errors
+    /// Returns an error if the delete query fails
     pub async fn remove_lectionary(&self, id: &DateId) -> Result<bool, sqlx::Error> {
         let result = sqlx::query("DELETE FROM lectionary WHERE id=$1")
             .bind(id.as_str())
@@ -96,37 +167,55 @@ impl DatabaseHandle {
     /// Deletes the entire lectionary table
     ///
     /// To be used with the 'db purge' command
+    /// # Errors
+    /// Returns an error if the delete query fails
     pub async fn remove_all(&self) -> Result<u64, sqlx::Error> {
         let result = sqlx::query("DELETE FROM lectionary").execute(&self.connection).await?;
 
         Ok(result.rows_affected())
     }
 
+    /// Gets the ids of the entries outside a given range, sorted by date
+    ///
+    /// If latest is None, only gets old entries
+    /// # Errors
+    /// Returns an error if the query fails
+    pub async fn ids_outside_range(&self, earliest: &DateId, maybe_latest: Option<&DateId>) -> Result<Vec<DateId>, sqlx::Error> {
+        let mut ids_outside_range: Vec<DateId> = self
+            .get_all_ids()
+            .await?
+            .into_iter()
+            .filter(|id| id < earliest || maybe_latest.is_some_and(|latest| id > latest))
+            .collect();
+        ids_outside_range.sort_unstable();
+        Ok(ids_outside_range)
+    }
+
     /// Deletes entries outside a given range
     ///
     /// If latest is None, it only deletes old entries
-    /// Returns the number of Succesfully removed rows
+    /// Returns the ids of the Succesfully removed rows
+    /// # Errors
     /// Only fails if cannot GET ids. Failure to remove will write errors to log but return Ok
-    pub async fn remove_outside_range(&self, earliest: DateId, maybe_latest: Option<DateId>) -> Result<u64, sqlx::Error> {
-        let all_ids = sqlx::query_as::<_, DateId>("SELECT id FROM lectionary")
-            .fetch_all(&self.connection)
-            .await?;
-        let ids_outside_range = all_ids
-            .into_iter()
-            .filter(|id| id < &earliest || maybe_latest.as_ref().is_some_and(|latest| *id > *latest));
-        let mut count_removed = 0;
-        for id in ids_outside_range {
+    pub async fn remove_outside_range(&self, earliest: &DateId, maybe_latest: Option<&DateId>) -> Result<Vec<DateId>, sqlx::Error> {
+        let mut removed = Vec::new();
+        for id in self.ids_outside_range(earliest, maybe_latest).await? {
             if let Err(e) = self.remove_lectionary(&id).await {
                 error!("Failed to remove lectionary '{}' ({})", id, e);
             } else {
                 info!("Succesfully removed lectionary '{}' during clean operation", id);
-                count_removed += 1;
+                removed.push(id);
             }
         }
-        Ok(count_removed)
+        Ok(removed)
     }
 
     /// Returns a count of the number of rows in the lectionary table
+    ///
+    /// # Errors
+    /// Returns an error if the count query fails
+    /// # Panics
+    /// Panics if the count is negative, which should never happen
     pub async fn get_lectionary_count(&self) -> Result<u64, sqlx::Error> {
         let result = sqlx::query("SELECT COUNT(*) FROM lectionary").fetch_one(&self.connection).await?;
 
@@ -139,6 +228,8 @@ impl DatabaseHandle {
     /// Determines if a lectionary with a given id is present
     ///
     /// More efficient than `get_lectionary` because it doesn't try to decode the whole reading
+    /// # Errors
+    /// Returns an error if the query fails
     pub async fn lectionary_present(&self, id: &DateId) -> Result<bool, sqlx::Error> {
         sqlx::query("SELECT id FROM lectionary WHERE id=$1")
             .bind(id.as_str())
@@ -147,36 +238,143 @@ impl DatabaseHandle {
             .map(|success| success.is_some())
     }
 
+    /// Gets when the lectionary was fetched, in seconds since the Unix epoch
+    ///
+    /// Returns None if the lectionary is not present or was stored before fetch times were recorded
+    /// # Errors
+    /// Returns an error if the query fails
+    pub async fn get_fetched_at(&self, id: &DateId) -> Result<Option<i64>, sqlx::Error> {
+        let fetched_at: Option<Option<i64>> = sqlx::query_scalar("SELECT fetched_at FROM lectionary WHERE id=$1")
+            .bind(id.as_str())
+            .fetch_optional(&self.connection)
+            .await?;
+        Ok(fetched_at.flatten())
+    }
+
+    /// Gets the ids of all stored lectionaries, in no particular order
+    ///
+    /// Does not touch the reading table
+    /// # Errors
+    /// Returns an error if the query fails
+    pub async fn get_all_ids(&self) -> Result<Vec<DateId>, sqlx::Error> {
+        sqlx::query_as::<_, DateId>("SELECT id FROM lectionary")
+            .fetch_all(&self.connection)
+            .await
+    }
+
     /// Gets all of the rows from the lectionary table
     ///
     /// Does not touch the reading table
+    /// # Errors
+    /// Returns an error if the query fails
     pub async fn get_lectionary_rows(&self) -> Result<Vec<LectionaryRow>, sqlx::Error> {
-        sqlx::query_as::<_, LectionaryRow>("SELECT id, name FROM lectionary")
+        sqlx::query_as::<_, LectionaryRow>("SELECT id, name, fetched_at FROM lectionary")
             .fetch_all(&self.connection)
             .await
     }
 
-    /// Gets a reading row for a specified lectionary with a given type
-    async fn get_reading_row(&self, lect_id: &DateId, reading_type: DbReadingType) -> Result<ReadingRow, sqlx::Error> {
-        sqlx::query_as::<_, ReadingRow>("SELECT location, content FROM reading WHERE lectionary_id=$1 AND reading_type=$2 LIMIT 1")
-            .bind(lect_id.as_str())
-            .bind(reading_type.as_str())
-            .fetch_one(&self.connection)
+    /// Gets the rows from the lectionary table with the given rank
+    ///
+    /// Lectionaries stored before ranks were recorded have no rank and are never returned
+    /// # Errors
+    /// Returns an error if the query fails
+    pub async fn get_lectionary_rows_by_rank(&self, rank: Rank) -> Result<Vec<LectionaryRow>, sqlx::Error> {
+        sqlx::query_as::<_, LectionaryRow>("SELECT id, name, fetched_at FROM lectionary WHERE rank = $1")
+            .bind(rank.as_str())
+            .fetch_all(&self.connection)
             .await
     }
 
+    /// Gets the rows of the lectionaries with a name containing `text`, ignoring case. Sorted by date
+    ///
+    /// # Errors
+    /// Returns an error if the query fails
+    pub async fn find_by_name(&self, text: &str) -> Result<Vec<LectionaryRow>, sqlx::Error> {
+        // Escape the wildcards of LIKE so that they match literally
+        let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let mut rows =
+            sqlx::query_as::<_, LectionaryRow>("SELECT id, name, fetched_at FROM lectionary WHERE name LIKE '%' || $1 || '%' ESCAPE '\\'")
+                .bind(escaped)
+                .fetch_all(&self.connection)
+                .await?;
+        rows.sort_unstable();
+        Ok(rows)
+    }
+
+    /// Gets all of the reading rows for a lectionary in a single query and sorts them by type in to a `LectionaryDbEntity`
+    ///
+    /// Fails with `RowNotFound` if any reading other than the second reading is missing. If there is more than one reading of a
+    /// type, the first one inserted is used
+    async fn get_lectionary_entity(&self, lect_row: LectionaryRow) -> Result<LectionaryDbEntity, sqlx::Error> {
+        let rows = sqlx::query_as::<_, TypedReadingRow>(
+            "SELECT reading_type, location, content, alternate, footnotes FROM reading WHERE lectionary_id=$1 ORDER BY id",
+        )
+        .bind(lect_row.id.as_str())
+        .fetch_all(&self.connection)
+        .await?;
+
+        let mut first_reading_row = None;
+        let mut psalm_row = None;
+        let mut gospel_row = None;
+        let mut second_reading_row = None;
+        let mut alleluia_row = None;
+        for row in rows {
+            let slot = match DbReadingType::from_str(&row.reading_type) {
+                Some(DbReadingType::FirstReading) => &mut first_reading_row,
+                Some(DbReadingType::SecondReading) => &mut second_reading_row,
+                Some(DbReadingType::Psalm) => &mut psalm_row,
+                Some(DbReadingType::Gospel) => &mut gospel_row,
+                Some(DbReadingType::Alleluia) => &mut alleluia_row,
+                None => {
+                    warn!("Unknown reading type '{}' for lectionary '{}'", row.reading_type, lect_row.id);
+                    continue;
+                }
+            };
+            if slot.is_none() {
+                *slot = Some(row.reading);
+            }
+        }
+
+        Ok(LectionaryDbEntity {
+            first_reading_row: first_reading_row.ok_or(sqlx::Error::RowNotFound)?,
+            psalm_row: psalm_row.ok_or(sqlx::Error::RowNotFound)?,
+            gospel_row: gospel_row.ok_or(sqlx::Error::RowNotFound)?,
+            second_reading_row,
+            alleluia_row: alleluia_row.ok_or(sqlx::Error::RowNotFound)?,
+            lect_row,
+        })
+    }
+
     /// Inserts a single reading into the reading table
+    ///
+    /// If the lectionary already has a reading of the same type, it is replaced and a warning is logged
     async fn insert_reading(
         transaction: &mut Transaction<'_, Sqlite>,
         reading: &Reading,
         lectionary_id: &DateId,
         reading_type: DbReadingType,
     ) -> Result<(), sqlx::Error> {
-        let insert_reading = sqlx::query("INSERT INTO reading (lectionary_id, reading_type, location, content) VALUES ($1, $2, $3, $4)")
+        let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM reading WHERE lectionary_id=$1 AND reading_type=$2")
             .bind(lectionary_id.as_str())
             .bind(reading_type.as_str())
-            .bind(reading.get_location())
-            .bind(reading.get_text());
+            .fetch_optional(&mut **transaction)
+            .await?;
+        if existing.is_some() {
+            warn!("Lectionary '{lectionary_id}' already has a {reading_type} reading; Replacing it");
+        }
+
+        let insert_reading = sqlx::query(
+            "INSERT INTO reading (lectionary_id, reading_type, location, content, alternate, footnotes) VALUES ($1, $2, $3, $4, $5, $6) \
+             ON CONFLICT (lectionary_id, reading_type) DO UPDATE \
+             SET location=excluded.location, content=excluded.content, alternate=excluded.alternate, footnotes=excluded.footnotes",
+        )
+        .bind(lectionary_id.as_str())
+        .bind(reading_type.as_str())
+        .bind(reading.get_location())
+        .bind(reading.get_text())
+        .bind(reading.get_alternate())
+        // Stored one per line, or NULL if there are none
+        .bind(Some(reading.get_footnotes().join("\n")).filter(|footnotes| !footnotes.is_empty()));
         transaction.execute(insert_reading).await?;
         Ok(())
     }
@@ -185,33 +383,112 @@ impl DatabaseHandle {
     async fn init_db(db_url: &str) -> Result<SqlitePool, DatabaseInitError> {
         if !Sqlite::database_exists(db_url).await.unwrap_or(false) {
             warn!("Creating new database at '{}' (You should only see this once)", &db_url);
-            Sqlite::create_database(db_url)
-                .await
-                .map_err(DatabaseInitError::CreateDatabaseError)?;
+            if let Err(e) = Sqlite::create_database(db_url).await {
+                Self::check_writable(db_url)?;
+                return Err(DatabaseInitError::CreateDatabaseError(e));
+            }
         }
-        let pool = SqlitePool::connect(db_url).await.map_err(DatabaseInitError::PoolCreationFailed)?;
+        // WAL lets other processes read while one is writing (e.g. display during db update). Set on every connection in the pool
+        let options = SqliteConnectOptions::from_str(db_url)
+            .map_err(DatabaseInitError::PoolCreationFailed)?
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(BUSY_TIMEOUT);
+        let pool = match SqlitePool::connect_with(options).await {
+            Ok(pool) => pool,
+            Err(e) => {
+                Self::check_writable(db_url)?;
+                return Err(DatabaseInitError::PoolCreationFailed(e));
+            }
+        };
+        Self::prepare_pool(&pool).await?;
+
+        Ok(pool)
+    }
+
+    /// Called after failing to create or open the database, since sqlx does not say when that is caused by permissions
+    ///
+    /// Returns `NotWritable` if a file can't be created in the directory of the database. Otherwise the original error stands
+    fn check_writable(db_url: &str) -> Result<(), DatabaseInitError> {
+        let db_path = Path::new(db_url.strip_prefix("sqlite://").unwrap_or(db_url));
+        let Some(dir) = db_path.parent() else {
+            return Ok(());
+        };
+        let probe_path = dir.join(concat!(".", env!("CARGO_PKG_NAME"), "-write-test"));
+        match File::create(&probe_path) {
+            Ok(_) => {
+                let _ = fs::remove_file(&probe_path);
+                Ok(())
+            }
+            Err(source) if path::is_permission_denied(&source) => Err(DatabaseInitError::NotWritable {
+                path: dir.to_owned(),
+                source,
+            }),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Enables foreign keys and runs any migrations that have not been applied yet
+    async fn prepare_pool(pool: &SqlitePool) -> Result<(), DatabaseInitError> {
         // Without this PRAGMA statement, foreign key constraints are not enforced. This would mean we could end up with orphan readings
         pool.execute("PRAGMA foreign_keys = ON;")
             .await
             .map_err(DatabaseInitError::PragmaForeignKeysFailure)?;
-        sqlx::migrate!("./migrations")
-            .run(&pool)
-            .await
-            .map_err(DatabaseInitError::MigrationError)?;
+        Self::run_migrations(pool).await.map_err(DatabaseInitError::MigrationError)
+    }
 
-        Ok(pool)
+    async fn run_migrations(pool: &SqlitePool) -> Result<(), MigrateError> {
+        sqlx::migrate!("./migrations").run(pool).await
+    }
+
+    /// Runs any migrations that have not been applied yet
+    ///
+    /// Migrations are already run when the handle is created, so this only does something if another process changed the database
+    ///
+    /// # Errors
+    /// Returns a `MigrateError` if a migration fails, or if an applied migration does not match the one in this version
+    pub async fn migrate(&self) -> Result<(), MigrateError> {
+        Self::run_migrations(&self.connection).await
+    }
+
+    /// Gets the version of the latest migration applied to the database, or None if none have been applied
+    ///
+    /// # Errors
+    /// Returns an error if the query fails
+    pub async fn get_schema_version(&self) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+            .fetch_one(&self.connection)
+            .await
     }
 
     /// Creates the path to the Sqlite database
-    fn get_db_url() -> Result<String, DatabaseInitError> {
+    fn get_db_url(config_path: Option<&Path>) -> Result<String, DatabaseInitError> {
         let mut db_url = String::from("sqlite://");
-        let file_path = path::create_and_get_db_path().map_err(DatabaseInitError::CannotGetUrl)?;
+        let file_path = Self::get_db_path(config_path).map_err(|e| match e {
+            PathError::PermissionDenied { path, source } => DatabaseInitError::NotWritable { path, source },
+            e => DatabaseInitError::CannotGetUrl(e),
+        })?;
 
         //TODO may need to look in to this expect statement when implementing Windows support
         db_url.push_str(file_path.to_str().expect("file path should be valid string"));
 
         Ok(db_url)
     }
+
+    /// Gets the path of the database file
+    ///
+    /// The environment variable takes precedence over the config. If neither is set, uses the default location
+    pub(crate) fn get_db_path(config_path: Option<&Path>) -> Result<PathBuf, PathError> {
+        let configured_path = match env::var_os(DB_PATH_ENV_VAR) {
+            Some(env_path) if !env_path.is_empty() => PathBuf::from(env_path),
+            _ => Config::from_file_or_default(config_path).database.path,
+        };
+        if configured_path.as_os_str().is_empty() {
+            path::create_and_get_db_path()
+        } else {
+            debug!("Using configured database path '{}'", configured_path.to_string_lossy());
+            path::create_and_resolve_path(&configured_path)
+        }
+    }
 }
 
 /// Intermediate struct used for creating a ```Lectionary``` struct
@@ -228,6 +505,20 @@ pub struct LectionaryDbEntity {
 pub struct LectionaryRow {
     pub id: DateId,
     pub name: String,
+    /// When the lectionary was fetched from the web and stored, in seconds since the Unix epoch
+    ///
+    /// None for lectionaries stored before this was recorded
+    pub fetched_at: Option<i64>,
+}
+
+impl LectionaryRow {
+    /// Gets the time the lectionary was fetched in the local time zone, if it is known
+    #[must_use]
+    pub fn fetched_at_local(&self) -> Option<DateTime<Local>> {
+        self.fetched_at
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .map(|time| time.with_timezone(&Local))
+    }
 }
 
 impl PartialOrd for LectionaryRow {
@@ -246,6 +537,17 @@ impl Ord for LectionaryRow {
 pub struct ReadingRow {
     pub location: String,
     pub content: String,
+    pub alternate: Option<String>,
+    /// Footnote markers separated by newlines
+    pub footnotes: Option<String>,
+}
+
+/// A reading row along with its type. Used to get all of the readings for a lectionary at once
+#[derive(Debug, FromRow)]
+struct TypedReadingRow {
+    reading_type: String,
+    #[sqlx(flatten)]
+    reading: ReadingRow,
 }
 
 //TODO I have three enums that more or less serve the same function. Should maybe fix that
@@ -267,6 +569,17 @@ impl DbReadingType {
             Self::Alleluia => "alleluia",
         }
     }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "first_reading" => Some(Self::FirstReading),
+            "second_reading" => Some(Self::SecondReading),
+            "psalm" => Some(Self::Psalm),
+            "gospel" => Some(Self::Gospel),
+            "alleluia" => Some(Self::Alleluia),
+            _ => None,
+        }
+    }
 }
 
 impl Display for DbReadingType {
@@ -279,6 +592,13 @@ impl Display for DbReadingType {
 pub enum DatabaseInitError {
     #[error("Cannot construct database URL: ({0})")]
     CannotGetUrl(#[source] PathError),
+    /// The directory of the database can't be created or written to
+    #[error(
+        "Not allowed to write the database in '{}' ({source}). Check the permissions of the directory, or set 'database.path' in \
+        the config (or the {DB_PATH_ENV_VAR} environment variable) to a writable location",
+        path.to_string_lossy()
+    )]
+    NotWritable { path: PathBuf, source: io::Error },
     #[error("Cannot create database: ({0})")]
     CreateDatabaseError(#[source] sqlx::Error),
     #[error("Failed to create a connection pool for the database: ({0})")]
@@ -293,18 +613,403 @@ pub enum DatabaseInitError {
 pub enum DatabaseGetError {
     #[error("Query returned no results")]
     NotPresent,
+    #[error("The database is locked by another process. Try again once it finishes")]
+    Locked(#[source] sqlx::Error),
     #[error("Select Query failed: ({0})")]
-    QueryError(#[from] sqlx::Error),
+    QueryError(#[source] sqlx::Error),
+}
+
+impl From<sqlx::Error> for DatabaseGetError {
+    fn from(value: sqlx::Error) -> Self {
+        if is_locked_error(&value) {
+            Self::Locked(value)
+        } else {
+            Self::QueryError(value)
+        }
+    }
 }
 
+/// True if the error was caused by another connection holding a lock on the database for longer than the busy timeout
+pub(crate) fn is_locked_error(error: &sqlx::Error) -> bool {
+    // SQLITE_BUSY (5) and SQLITE_LOCKED (6). Extended result codes keep the primary code in the lowest byte
+    let sqlx::Error::Database(db_error) = error else {
+        return false;
+    };
+    db_error
+        .code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| matches!(code & 0xff, 5 | 6))
+}
 
 #[cfg(test)]
 mod tests {
+    use futures_util::TryStreamExt;
+    use scraper::Html;
+
     use super::*;
+    use crate::html::PageSelectors;
 
     #[sqlx::test]
     fn test_db_init() {
         assert!(DatabaseHandle::init_db("sqlite://:memory:").await.is_ok());
     }
 
+    #[tokio::test]
+    async fn in_memory_insert_get_remove() {
+        let html = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        let id = DateId::checked_from_str("071424").unwrap();
+        let lectionary = Lectionary::create_from_html(id.clone(), &Html::parse_document(&html), &PageSelectors::default()).unwrap();
+        let db = DatabaseHandle::new_in_memory().await.unwrap();
+        assert_eq!(0, db.get_lectionary_count().await.unwrap());
+
+        db.insert_lectionary(&lectionary).await.unwrap();
+        assert_eq!(1, db.get_lectionary_count().await.unwrap());
+        let stored = db.get_lectionary(&id).await.unwrap();
+        assert_eq!(lectionary.get_day_name(), stored.get_day_name());
+
+        assert!(db.remove_lectionary(&id).await.unwrap());
+        assert!(!db.remove_lectionary(&id).await.unwrap());
+        assert!(matches!(db.get_lectionary(&id).await, Err(DatabaseGetError::NotPresent)));
+
+        // Each in-memory handle is a separate database
+        let other = DatabaseHandle::new_in_memory().await.unwrap();
+        db.insert_lectionary(&lectionary).await.unwrap();
+        assert_eq!(0, other.get_lectionary_count().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn find_by_name_ignores_case() {
+        let db = DatabaseHandle::new_in_memory().await.unwrap();
+        for (id, name) in [
+            ("081524", "Solemnity of the Assumption of the Blessed Virgin Mary"),
+            ("081424", "Vigil of the Assumption"),
+            ("071424", "Fifteenth Sunday in Ordinary Time"),
+        ] {
+            let reading = || Some(Reading::new(String::new(), String::from("text"), None));
+            let id = DateId::checked_from_str(id).unwrap();
+            let lectionary = Lectionary::new(id, name.to_owned(), reading(), None, reading(), reading(), reading());
+            db.insert_lectionary(&lectionary).await.unwrap();
+        }
+
+        let ids = |rows: Vec<LectionaryRow>| rows.into_iter().map(|row| row.id.to_string()).collect::<Vec<_>>();
+        assert_eq!(vec!["081424", "081524"], ids(db.find_by_name("assumption").await.unwrap()));
+        assert_eq!(vec!["071424"], ids(db.find_by_name("Ordinary Time").await.unwrap()));
+        assert!(db.find_by_name("Pentecost").await.unwrap().is_empty());
+        assert!(db.find_by_name("%").await.unwrap().is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn unwritable_directory_is_reported() {
+        // Nothing can be created in /sys, even by root
+        let error = DatabaseHandle::init_db("sqlite:///sys/lectio-diei-test.db").await.unwrap_err();
+        assert!(matches!(error, DatabaseInitError::NotWritable { .. }));
+        let message = error.to_string();
+        assert!(message.contains("'/sys'"));
+        assert!(message.contains("database.path"));
+    }
+
+    #[tokio::test]
+    async fn overlapping_access_from_two_handles() {
+        let path = std::env::temp_dir().join(format!("lectio-diei-concurrent-{}.db", std::process::id()));
+        let db_url = format!("sqlite://{}", path.to_str().unwrap());
+        let writer = DatabaseHandle {
+            connection: DatabaseHandle::init_db(&db_url).await.unwrap(),
+        };
+        let reader = DatabaseHandle {
+            connection: DatabaseHandle::init_db(&db_url).await.unwrap(),
+        };
+
+        // Reads are not blocked by a write in progress
+        let mut transaction = writer.connection.begin().await.unwrap();
+        sqlx::query("INSERT INTO lectionary (id, name) VALUES ('010125', 'Test')")
+            .execute(&mut *transaction)
+            .await
+            .unwrap();
+        assert_eq!(0, reader.get_lectionary_count().await.unwrap());
+        transaction.commit().await.unwrap();
+        assert_eq!(1, reader.get_lectionary_count().await.unwrap());
+
+        // Simultaneous writes wait for each other instead of failing
+        let html = Html::parse_document(&std::fs::read_to_string("tests/resources/sunday_or.html").unwrap());
+        let first = Lectionary::create_from_html(DateId::checked_from_str("071424").unwrap(), &html, &PageSelectors::default()).unwrap();
+        let second = Lectionary::create_from_html(DateId::checked_from_str("072124").unwrap(), &html, &PageSelectors::default()).unwrap();
+        let (first_result, second_result) = tokio::join!(writer.insert_lectionary(&first), reader.insert_lectionary(&second));
+        first_result.unwrap();
+        second_result.unwrap();
+        assert_eq!(3, writer.get_lectionary_count().await.unwrap());
+
+        writer.connection.close().await;
+        reader.connection.close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.to_str().unwrap()));
+        }
+    }
+
+    #[sqlx::test]
+    async fn stream_lectionaries_sorted_by_date(pool: SqlitePool) {
+        let html = Html::parse_document(&std::fs::read_to_string("tests/resources/sunday_or.html").unwrap());
+        let db = DatabaseHandle { connection: pool };
+        for id in ["071424", "123123", "010124", "123199"] {
+            let lectionary = Lectionary::create_from_html(DateId::checked_from_str(id).unwrap(), &html, &PageSelectors::default()).unwrap();
+            db.insert_lectionary(&lectionary).await.unwrap();
+        }
+
+        let lectionaries: Vec<Lectionary> = db.stream_lectionaries().await.unwrap().try_collect().await.unwrap();
+        let ids: Vec<&str> = lectionaries.iter().map(|lectionary| lectionary.get_id().as_str()).collect();
+        assert_eq!(vec!["123199", "123123", "010124", "071424"], ids);
+        assert!(lectionaries.iter().all(Lectionary::is_complete));
+    }
+
+    #[tokio::test]
+    async fn stream_lectionaries_in_memory() {
+        let html = Html::parse_document(&std::fs::read_to_string("tests/resources/sunday_or.html").unwrap());
+        let db = DatabaseHandle::new_in_memory().await.unwrap();
+        let lectionary =
+            Lectionary::create_from_html(DateId::checked_from_str("071424").unwrap(), &html, &PageSelectors::default()).unwrap();
+        db.insert_lectionary(&lectionary).await.unwrap();
+
+        let lectionaries: Vec<Lectionary> = db.stream_lectionaries().await.unwrap().try_collect().await.unwrap();
+        assert_eq!(1, lectionaries.len());
+    }
+
+    #[sqlx::test]
+    async fn schema_version_is_latest_migration(pool: SqlitePool) {
+        let db = DatabaseHandle { connection: pool };
+        db.migrate().await.unwrap();
+        let latest = sqlx::migrate!("./migrations").iter().map(|migration| migration.version).max();
+        assert!(latest.is_some());
+        assert_eq!(latest, db.get_schema_version().await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn lectionary_round_trip(pool: SqlitePool) {
+        let html = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        let id = DateId::checked_from_str("071424").unwrap();
+        let lectionary = Lectionary::create_from_html(id.clone(), &Html::parse_document(&html), &PageSelectors::default()).unwrap();
+        let db = DatabaseHandle { connection: pool };
+        db.insert_lectionary(&lectionary).await.unwrap();
+
+        let stored = db.get_lectionary(&id).await.unwrap();
+        assert_eq!(lectionary.get_day_name(), stored.get_day_name());
+        assert_eq!(
+            lectionary.get_reading_1().unwrap().get_text(),
+            stored.get_reading_1().unwrap().get_text()
+        );
+        assert_eq!(
+            lectionary.get_resp_psalm().unwrap().get_text(),
+            stored.get_resp_psalm().unwrap().get_text()
+        );
+        assert_eq!(
+            lectionary.get_gospel().unwrap().get_location(),
+            stored.get_gospel().unwrap().get_location()
+        );
+        assert_eq!(
+            lectionary.get_alleluia().unwrap().get_text(),
+            stored.get_alleluia().unwrap().get_text()
+        );
+        let reading_2 = stored.get_reading_2().unwrap();
+        assert_eq!(lectionary.get_reading_2().unwrap().get_alternate(), reading_2.get_alternate());
+    }
+
+    #[sqlx::test]
+    async fn footnotes_round_trip(pool: SqlitePool) {
+        let html = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        let id = DateId::checked_from_str("071424").unwrap();
+        let lectionary = Lectionary::create_from_html(id.clone(), &Html::parse_document(&html), &PageSelectors::default()).unwrap();
+        let db = DatabaseHandle { connection: pool };
+        db.insert_lectionary(&lectionary).await.unwrap();
+
+        let gospel = Reading::new(String::from("Jn 1:1-5"), String::from("In the beginning"), None)
+            .with_footnotes(vec![String::from("a"), String::from("*")]);
+        let mut transaction = db.connection.begin().await.unwrap();
+        DatabaseHandle::insert_reading(&mut transaction, &gospel, &id, DbReadingType::Gospel)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        let stored = db.get_lectionary(&id).await.unwrap();
+        assert_eq!(vec!["a", "*"], stored.get_gospel().unwrap().get_footnotes());
+        assert!(stored.get_reading_1().unwrap().get_footnotes().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn rows_by_rank(pool: SqlitePool) {
+        let html = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        let id = DateId::checked_from_str("071424").unwrap();
+        let lectionary = Lectionary::create_from_html(id.clone(), &Html::parse_document(&html), &PageSelectors::default()).unwrap();
+        let db = DatabaseHandle { connection: pool };
+        db.insert_lectionary(&lectionary).await.unwrap();
+
+        let sundays = db.get_lectionary_rows_by_rank(Rank::Sunday).await.unwrap();
+        assert_eq!(1, sundays.len());
+        assert_eq!(id, sundays[0].id);
+        assert!(sundays[0].fetched_at.is_some());
+        assert!(db.get_lectionary_rows_by_rank(Rank::Memorial).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn insert_same_lectionary_twice(pool: SqlitePool) {
+        let html = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        let id = DateId::checked_from_str("071424").unwrap();
+        let lectionary = Lectionary::create_from_html(id.clone(), &Html::parse_document(&html), &PageSelectors::default()).unwrap();
+        let db = DatabaseHandle { connection: pool };
+        db.insert_lectionary(&lectionary).await.unwrap();
+        db.insert_lectionary(&lectionary).await.unwrap();
+
+        let counts: Vec<(String, i64)> =
+            sqlx::query_as("SELECT reading_type, COUNT(*) FROM reading WHERE lectionary_id=$1 GROUP BY reading_type")
+                .bind(id.as_str())
+                .fetch_all(&db.connection)
+                .await
+                .unwrap();
+        assert_eq!(5, counts.len());
+        assert!(counts.iter().all(|(_, count)| *count == 1));
+        assert_eq!(
+            lectionary.get_gospel().unwrap().get_text(),
+            db.get_lectionary(&id).await.unwrap().get_gospel().unwrap().get_text()
+        );
+    }
+
+    #[sqlx::test]
+    async fn reinserting_removes_missing_readings(pool: SqlitePool) {
+        let id = DateId::checked_from_str("071424").unwrap();
+        let reading = |text: &str| Reading::new(String::from("Mt 1:1"), text.to_owned(), None);
+        let with_second = Lectionary::new(
+            id.clone(),
+            String::from("Test"),
+            Some(reading("First")),
+            Some(reading("Second")),
+            Some(reading("Psalm")),
+            Some(reading("Gospel")),
+            Some(reading("Alleluia")),
+        );
+        let without_second = Lectionary::new(
+            id.clone(),
+            String::from("Renamed"),
+            Some(reading("First")),
+            None,
+            Some(reading("Psalm")),
+            Some(reading("New gospel")),
+            Some(reading("Alleluia")),
+        );
+        let db = DatabaseHandle { connection: pool };
+        db.insert_lectionary(&with_second).await.unwrap();
+        db.insert_lectionary(&without_second).await.unwrap();
+
+        let stored = db.get_lectionary(&id).await.unwrap();
+        assert_eq!("Renamed", stored.get_day_name());
+        assert!(stored.get_reading_2().is_none());
+        assert_eq!("New gospel", stored.get_gospel().unwrap().get_text());
+    }
+
+    #[sqlx::test]
+    async fn duplicate_reading_type_replaced(pool: SqlitePool) {
+        let html = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        let id = DateId::checked_from_str("071424").unwrap();
+        let lectionary = Lectionary::create_from_html(id.clone(), &Html::parse_document(&html), &PageSelectors::default()).unwrap();
+        let db = DatabaseHandle { connection: pool };
+        db.insert_lectionary(&lectionary).await.unwrap();
+
+        // Insert a reading of an existing type without replacing the lectionary
+        let mut transaction = db.connection.begin().await.unwrap();
+        DatabaseHandle::insert_reading(&mut transaction, lectionary.get_reading_1().unwrap(), &id, DbReadingType::Gospel)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        let gospel_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM reading WHERE lectionary_id=$1 AND reading_type='gospel'")
+            .bind(id.as_str())
+            .fetch_one(&db.connection)
+            .await
+            .unwrap();
+        assert_eq!(1, gospel_count);
+        let stored = db.get_lectionary(&id).await.unwrap();
+        assert_eq!(
+            lectionary.get_reading_1().unwrap().get_text(),
+            stored.get_gospel().unwrap().get_text()
+        );
+    }
+
+    #[sqlx::test]
+    async fn missing_required_reading_fails(pool: SqlitePool) {
+        sqlx::query("INSERT INTO lectionary (id, name) VALUES ('071424', 'Test')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let db = DatabaseHandle { connection: pool };
+        let id = DateId::checked_from_str("071424").unwrap();
+        assert!(matches!(db.get_lectionary(&id).await, Err(DatabaseGetError::QueryError(_))));
+    }
+
+    #[sqlx::test]
+    async fn remove_outside_range_spans_past_and_future(pool: SqlitePool) {
+        for id in ["122924", "010525", "011025", "011525", "020425", "020525", "030125"] {
+            sqlx::query("INSERT INTO lectionary (id, name) VALUES ($1, 'Test')")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let db = DatabaseHandle { connection: pool };
+        let earliest = DateId::checked_from_str("011025").unwrap();
+        let latest = DateId::checked_from_str("020425").unwrap();
+
+        let removed: Vec<String> = db
+            .remove_outside_range(&earliest, Some(&latest))
+            .await
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(vec!["122924", "010525", "020525", "030125"], removed);
+        assert_eq!(3, db.get_lectionary_count().await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn get_all_ids_returns_every_id(pool: SqlitePool) {
+        for id in ["010225", "123124", "010125"] {
+            sqlx::query("INSERT INTO lectionary (id, name) VALUES ($1, 'Test')")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let db = DatabaseHandle { connection: pool };
+        let mut ids = db.get_all_ids().await.unwrap();
+        ids.sort_unstable();
+        let ids: Vec<&str> = ids.iter().map(DateId::as_str).collect();
+        assert_eq!(vec!["123124", "010125", "010225"], ids);
+    }
+
+    #[sqlx::test]
+    async fn ids_outside_range_filtered_and_sorted(pool: SqlitePool) {
+        for id in ["010225", "123124", "010125", "011025"] {
+            sqlx::query("INSERT INTO lectionary (id, name) VALUES ($1, 'Test')")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let db = DatabaseHandle { connection: pool };
+        let earliest = DateId::checked_from_str("010125").unwrap();
+        let latest = DateId::checked_from_str("010525").unwrap();
+
+        let old: Vec<String> = db
+            .ids_outside_range(&earliest, None)
+            .await
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(vec!["123124"], old);
+        let outside: Vec<String> = db
+            .ids_outside_range(&earliest, Some(&latest))
+            .await
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(vec!["123124", "011025"], outside);
+    }
 }
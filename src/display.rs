@@ -1,15 +1,27 @@
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Write};
+use std::sync::OnceLock;
+
 use log::*;
 use regex::Regex;
+use terminal_size::{terminal_size, Width};
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    args::{CommonArguments, DisplayReadingsArgs, FormattingArgs, ReadingArg},
-    config::Config,
+    args::{
+        ColorWhen, CommonArguments, DisplayFormat, DisplayReadingsArgs, FormattingArgs, OutputArgs, ReadingArg, ReadingLength,
+        ReadingLengthArgs, RetrievalArgs, SeparatorStyle,
+    },
+    config::{Config, DisplayConfig, MaxWidth},
+    export,
     lectionary::{Lectionary, Reading, ReadingName},
+    liturgy::Color,
+    scripture,
 };
 
 /// Used for reading1, reading2, gospel. Not psalm
 #[derive(Clone, Copy)]
-enum LineBreaks {
+pub(crate) enum LineBreaks {
     /// Removes all lines breaks
     None,
     /// Keeps original line breaks
@@ -19,7 +31,13 @@ enum LineBreaks {
 }
 
 impl LineBreaks {
-    fn from_config_and_args(config_original_linebreaks: bool, config_max_width: u16, args: FormattingArgs) -> Self {
+    /// `terminal_width` is only used if the max width is not set explicitly in either the args or config
+    fn from_config_and_args(
+        config_original_linebreaks: bool,
+        config_max_width: MaxWidth,
+        args: FormattingArgs,
+        terminal_width: Option<u16>,
+    ) -> Self {
         // First look at args, since args overwrite config
         if args.original_linebreaks {
             return Self::Original;
@@ -34,14 +52,20 @@ impl LineBreaks {
         if config_original_linebreaks {
             return Self::Original;
         }
-        if config_max_width == 0 {
+        let max_width = match config_max_width {
+            MaxWidth::Columns(columns) => columns,
+            MaxWidth::Auto => terminal_width.unwrap_or_else(DisplayConfig::default_width),
+        };
+        if max_width == 0 {
             return Self::None;
         }
-        Self::Width(config_max_width)
+        Self::Width(max_width)
     }
 }
 
 /// Says what readings to print
+///
+/// A specified list never has a reading more than once
 pub enum ReadingsOptions {
     All,
     DayOnly,
@@ -49,6 +73,7 @@ pub enum ReadingsOptions {
 }
 
 impl ReadingsOptions {
+    /// Repeated readings in the arguments or the config are only displayed the first time they are listed
     fn from_config_and_args(config_reading_order: Vec<ReadingArg>, args: DisplayReadingsArgs) -> Self {
         // First look at args, since args overwrite configs
         if args.day_only {
@@ -57,16 +82,101 @@ impl ReadingsOptions {
         if args.all {
             return Self::All;
         }
+        let flagged = args.flagged_readings();
+        if !flagged.is_empty() {
+            return Self::Specified(flagged);
+        }
         // Prefer to use commandline arguments over config
-        Self::Specified(args.readings.unwrap_or(config_reading_order))
+        let (list, source) = match args.readings {
+            Some(list) => (list, "--readings"),
+            None => (config_reading_order, "display.reading_order"),
+        };
+        let deduplicated = dedup_readings(&list);
+        if deduplicated.len() < list.len() {
+            warn!("Readings are listed more than once in {source}; Each is only displayed the first time it is listed");
+        }
+        Self::Specified(deduplicated)
+    }
+}
+
+/// Removes repeated readings, keeping the first of each so that the order is otherwise unchanged
+pub(crate) fn dedup_readings(readings: &[ReadingArg]) -> Vec<ReadingArg> {
+    let mut deduplicated = Vec::with_capacity(readings.len());
+    for reading in readings {
+        if !deduplicated.contains(reading) {
+            deduplicated.push(*reading);
+        }
+    }
+    deduplicated
+}
+
+/// How the lectionary is written
+#[derive(Clone, Copy)]
+enum Rendering {
+    /// Day name, headings, and separators around each reading
+    Pretty,
+    /// Only the text of the readings
+    Raw,
+    /// Day name and the heading of each reading, without the text
+    Headings,
+    /// Only the text to be spoken
+    Tts,
+    /// A self-contained HTML document
+    Html,
+    /// JSON, indented unless `compact` is true
+    Json { compact: bool },
+}
+
+impl Rendering {
+    /// --raw and --headings take precedence over the format, which is taken from the arguments before the config
+    fn from_config_and_args(config_format: DisplayFormat, args: &OutputArgs) -> Self {
+        if args.raw {
+            return Self::Raw;
+        }
+        if args.headings {
+            return Self::Headings;
+        }
+        match args.format.unwrap_or(config_format) {
+            DisplayFormat::Pretty => Self::Pretty,
+            DisplayFormat::Tts => Self::Tts,
+            DisplayFormat::Html => Self::Html,
+            DisplayFormat::Json => Self::Json { compact: args.compact },
+        }
     }
 }
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct DisplaySettings {
     pub readings_to_display: ReadingsOptions,
-    //TODO handle color and no_color
-    pub _no_color: bool,
-    line_breaks: LineBreaks,
+    /// If true, the day name and headings are bold and the liturgical color is written in that color, using ANSI escape codes
+    color: bool,
+    /// If true, only the database is used to retrieve the lectionary
+    pub offline: bool,
+    /// If false, a lectionary retrieved from the web is not stored in the database
+    pub store: bool,
+    /// Maximum number of previous days to try if there are no readings for the date
+    pub nearest: u16,
+    /// Name of the Mass to display on days with more than one
+    pub mass: Option<String>,
+    pub(crate) line_breaks: LineBreaks,
+    pub(crate) reading_length: ReadingLength,
+    rendering: Rendering,
+    /// Line written around the day name and the text of each reading in the pretty rendering
+    separator_style: SeparatorStyle,
+    /// If true, headings use short labels for the readings (e.g. "Ps" instead of "Responsorial Psalm")
+    short_labels: bool,
+    /// If true, book abbreviations in reading locations are replaced with full names
+    pub(crate) expand_refs: bool,
+    /// If true, the footnote markers removed from each reading are listed after it
+    pub(crate) footnotes: bool,
+    /// If true, typographic punctuation (e.g. curly quotes) is replaced with ASCII
+    pub(crate) ascii: bool,
+    /// If false, liturgical responses (e.g. "The word of the Lord.") are removed from the end of each reading
+    include_responses: bool,
+    /// If true, the verse number (e.g. "(8)") is kept in the first line of the psalm
+    pub(crate) psalm_keep_verse_number: bool,
+    /// If true, each line of the text of the readings is prefixed with its number
+    number_lines: bool,
 }
 
 impl DisplaySettings {
@@ -74,53 +184,276 @@ impl DisplaySettings {
         config: Config,
         reading_args: DisplayReadingsArgs,
         formatting_args: FormattingArgs,
-        args: CommonArguments,
+        retrieval_args: RetrievalArgs,
+        length_args: ReadingLengthArgs,
+        output_args: &OutputArgs,
+        args: &CommonArguments,
     ) -> Self {
+        let reading_length = if length_args.long {
+            ReadingLength::Long
+        } else if length_args.short {
+            ReadingLength::Short
+        } else {
+            config.display.reading_length
+        };
+        // Colors are off by default when writing to a file or a pipe
+        let color = match output_args.color {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => !args.no_color && output_args.output.is_none() && io::stdout().is_terminal(),
+        };
+        // The terminal width is only relevant when writing to the terminal
+        let terminal_width = if output_args.output.is_none() {
+            terminal_size().map(|(Width(width), _)| width)
+        } else {
+            None
+        };
         Self {
-            _no_color: args.no_color,
+            color,
+            offline: retrieval_args.offline || config.display.offline,
+            store: !retrieval_args.no_store,
+            nearest: retrieval_args.nearest.unwrap_or(0),
+            mass: retrieval_args.mass,
             readings_to_display: ReadingsOptions::from_config_and_args(config.display.reading_order, reading_args),
-            line_breaks: LineBreaks::from_config_and_args(config.display.original_linebreaks, config.display.max_width, formatting_args),
+            line_breaks: LineBreaks::from_config_and_args(
+                config.display.original_linebreaks,
+                config.display.max_width,
+                formatting_args,
+                terminal_width,
+            ),
+            reading_length,
+            rendering: Rendering::from_config_and_args(config.display.format, output_args),
+            separator_style: output_args.separator.unwrap_or(config.display.separator_style),
+            short_labels: config.display.short_labels,
+            expand_refs: output_args.expand_refs || config.display.expand_refs,
+            footnotes: output_args.footnotes,
+            ascii: output_args.ascii || config.display.ascii,
+            include_responses: config.display.include_responses && !output_args.no_responses,
+            psalm_keep_verse_number: config.display.psalm_keep_verse_number,
+            number_lines: output_args.number_lines,
         }
     }
-}
 
-const ALL_READINGS: [ReadingArg; 4] = [ReadingArg::Reading1, ReadingArg::Reading2, ReadingArg::Psalm, ReadingArg::Gospel];
+    /// Gets the label used in the heading of the reading
+    fn label(&self, name: &ReadingName) -> &'static str {
+        if self.short_labels {
+            name.short_str()
+        } else {
+            name.as_str()
+        }
+    }
 
-impl Lectionary {
-    /// Displays the lectionary with the given `DisplaySettings`
-    pub fn pretty_print(&self, settings: &DisplaySettings) {
-        let list = match &settings.readings_to_display {
+    /// Gets the readings to print, in order
+    pub(crate) fn reading_list(&self) -> &[ReadingArg] {
+        match &self.readings_to_display {
             ReadingsOptions::All => ALL_READINGS.as_slice(),
             ReadingsOptions::DayOnly => &[],
             ReadingsOptions::Specified(list) => list.as_slice(),
+        }
+    }
+}
+
+/// The readings displayed with --all. The acclamation comes right before the gospel, as it does at Mass
+const ALL_READINGS: [ReadingArg; 5] = [
+    ReadingArg::Reading1,
+    ReadingArg::Reading2,
+    ReadingArg::Psalm,
+    ReadingArg::Alleluia,
+    ReadingArg::Gospel,
+];
+
+/// Writes each lectionary to `out` in order with the given `DisplaySettings`
+///
+/// With the html format, the lectionaries are written as a single document with a table of contents
+///
+/// # Errors
+/// Returns an `io::Error` if writing to `out` fails
+pub fn print_lectionaries(lectionaries: &[Lectionary], settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+    with_ascii_normalization(settings, out, |out: &mut dyn Write| {
+        match settings.rendering {
+            Rendering::Html => return export::write_html_document(lectionaries, settings, out),
+            Rendering::Json { compact } => return export::write_json(lectionaries, settings, compact, out),
+            _ => {}
+        }
+        for (index, lectionary) in lectionaries.iter().enumerate() {
+            if index > 0 {
+                writeln!(out)?;
+            }
+            lectionary.print_unnormalized(settings, out)?;
+        }
+        Ok(())
+    })
+}
+
+/// Runs `print` on `out`. If ASCII output is enabled in the settings, typographic punctuation in what it writes is replaced first
+///
+/// Done here rather than when storing so that the original text is kept in the database
+fn with_ascii_normalization(
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+    print: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    // Replacing curly quotes in JSON would end its strings early, so JSON replaces them in each value instead
+    if !settings.ascii || matches!(settings.rendering, Rendering::Json { .. }) {
+        return print(out);
+    }
+    let mut buffer = Vec::new();
+    print(&mut buffer)?;
+    out.write_all(to_ascii(&String::from_utf8_lossy(&buffer)).as_bytes())
+}
+
+/// Runs `print` on `out`. If line numbers are enabled in the settings, each line it writes is prefixed with its number
+///
+/// Numbers are right-aligned to the widest number. Blank lines (between paragraphs) are kept but not numbered
+fn with_line_numbers(
+    settings: &DisplaySettings,
+    out: &mut dyn Write,
+    print: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    if !settings.number_lines {
+        return print(out);
+    }
+    let mut buffer = Vec::new();
+    print(&mut buffer)?;
+    out.write_all(number_lines(&String::from_utf8_lossy(&buffer)).as_bytes())
+}
+
+fn number_lines(text: &str) -> String {
+    let count = text.lines().filter(|line| !line.trim().is_empty()).count();
+    let width = count.to_string().len();
+    let mut numbered = String::with_capacity(text.len() + count * (width + 1));
+    let mut number = 0;
+    for line in text.lines() {
+        if !line.trim().is_empty() {
+            number += 1;
+            write!(numbered, "{number:>width$} {line}").expect("Writing to a String does not fail");
+        }
+        numbered.push('\n');
+    }
+    numbered
+}
+
+/// Replaces curly quotes, en and em dashes, and ellipses with their closest ASCII equivalents
+pub(crate) fn to_ascii(text: &str) -> String {
+    let mut ascii = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\u{201C}' | '\u{201D}' => ascii.push('"'),
+            '\u{2018}' | '\u{2019}' => ascii.push('\''),
+            '\u{2013}' => ascii.push('-'),
+            '\u{2014}' => ascii.push_str("--"),
+            '\u{2026}' => ascii.push_str("..."),
+            _ => ascii.push(c),
+        }
+    }
+    ascii
+}
+
+impl Lectionary {
+    /// Writes the lectionary to `out` with the given `DisplaySettings`
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if writing to `out` fails
+    pub fn pretty_print(&self, settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+        with_ascii_normalization(settings, out, |out: &mut dyn Write| self.print_unnormalized(settings, out))
+    }
+
+    /// Renders the lectionary to a `String`, exactly as `pretty_print` would write it
+    ///
+    /// # Panics
+    /// Will not panic since writing to a `Vec` does not fail
+    #[must_use]
+    pub fn render(&self, settings: &DisplaySettings) -> String {
+        let mut out = Vec::new();
+        self.pretty_print(settings, &mut out).expect("Writing to a Vec does not fail");
+        String::from_utf8(out).expect("Rendered text is built from strings")
+    }
+
+    fn print_unnormalized(&self, settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+        let list = settings.reading_list();
+        match settings.rendering {
+            Rendering::Raw => return self.print_raw(list, settings, out),
+            Rendering::Tts => return self.print_tts(list, settings, out),
+            Rendering::Html => return export::write_html_document(std::slice::from_ref(self), settings, out),
+            Rendering::Json { compact } => return export::write_json(std::slice::from_ref(self), settings, compact, out),
+            Rendering::Pretty | Rendering::Headings => {}
+        }
+        let seperator = self.get_seperator(settings.separator_style);
+        let seperator = seperator.as_deref();
+        let header_width = match settings.line_breaks {
+            LineBreaks::Width(width) => Some(width),
+            LineBreaks::None | LineBreaks::Original => None,
         };
-        let dashes = self.get_dash_seperator();
-        self.print_day_name(&dashes);
-        for reading in list {
-            match reading {
-                ReadingArg::Reading1 => {
-                    self.get_reading_1()
-                        .pretty_print_as_reading(ReadingName::Reading1.as_str(), &dashes, settings.line_breaks);
-                }
-                ReadingArg::Reading2 => {
-                    let _ = self.get_reading_2().inspect(|reading_2| {
-                        reading_2.pretty_print_as_reading(ReadingName::Reading2.as_str(), &dashes, settings.line_breaks);
-                    });
-                }
-                ReadingArg::Psalm => self.get_resp_psalm().pretty_print_as_psalm(ReadingName::Psalm.as_str(), &dashes),
-                ReadingArg::Gospel => {
-                    self.get_gospel()
-                        .pretty_print_as_reading(ReadingName::Gospel.as_str(), &dashes, settings.line_breaks);
-                }
-                ReadingArg::Alleluia => self
-                    .get_alleluia()
-                    .pretty_print_as_alleliua(ReadingName::Alleluia.as_str(), &dashes),
+        self.print_day_name(seperator, header_width, settings.color, out)?;
+        if let Rendering::Headings = settings.rendering {
+            return self.print_headings(list, settings, out);
+        }
+        for (name, reading) in self.present_readings(list) {
+            match name {
+                ReadingName::Psalm => reading.pretty_print_as_psalm(settings.label(&name), seperator, settings, out)?,
+                ReadingName::Alleluia => reading.pretty_print_as_alleliua(settings.label(&name), seperator, settings, out)?,
+                _ => reading.pretty_print_as_reading(settings.label(&name), seperator, settings, out)?,
             }
         }
+        Ok(())
+    }
+
+    /// Gets the readings in `list` in order, skipping readings that are not present
+    pub(crate) fn present_readings<'a>(&'a self, list: &'a [ReadingArg]) -> impl Iterator<Item = (ReadingName, &'a Reading)> + 'a {
+        list.iter().filter_map(|reading| match reading {
+            ReadingArg::Reading1 => self.get_reading_1().map(|reading| (ReadingName::Reading1, reading)),
+            ReadingArg::Reading2 => self.get_reading_2().map(|reading| (ReadingName::Reading2, reading)),
+            ReadingArg::Psalm => self.get_resp_psalm().map(|reading| (ReadingName::Psalm, reading)),
+            ReadingArg::Gospel => self.get_gospel().map(|reading| (ReadingName::Gospel, reading)),
+            ReadingArg::Alleluia => self.get_alleluia().map(|reading| (ReadingName::Alleluia, reading)),
+        })
+    }
+
+    /// Writes the name and location of each reading, skipping readings that are not present
+    fn print_headings(&self, list: &[ReadingArg], settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+        for (name, reading) in self.present_readings(list) {
+            reading.print_heading(settings.label(&name), settings, out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes only the text of each reading, without the day name, headings, or separators
+    fn print_raw(&self, list: &[ReadingArg], settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+        for (name, reading) in self.present_readings(list) {
+            with_line_numbers(settings, out, |out: &mut dyn Write| match name {
+                ReadingName::Psalm => reading.print_psalm_text(settings, out),
+                ReadingName::Alleluia => writeln!(out, "{}", reading.get_text()),
+                _ => reading.print_reading_text(settings, out),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Writes only the text to be spoken: the day name as a sentence, then each reading with a spoken lead-in
+    fn print_tts(&self, list: &[ReadingArg], settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", as_sentence(self.get_day_name()))?;
+        for (name, reading) in self.present_readings(list) {
+            writeln!(out)?;
+            writeln!(out, "{}", spoken_lead_in(&name))?;
+            match name {
+                ReadingName::Psalm | ReadingName::Alleluia => reading.print_spoken_verses(out)?,
+                _ => Reading::print_spoken_text(reading.get_display_text(settings), out)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the line written around the day name and the text of each reading. None if no line should be written
+    fn get_seperator(&self, style: SeparatorStyle) -> Option<String> {
+        match style {
+            SeparatorStyle::Dashes => Some(self.get_dash_seperator()),
+            SeparatorStyle::Blank => Some(String::new()),
+            SeparatorStyle::None => None,
+        }
     }
 
     fn get_dash_seperator(&self) -> String {
-        let dash_length = self.get_day_name().len() + 4;
+        let dash_length = self.get_day_name().width() + 4;
         let mut dashes = String::with_capacity(dash_length);
         for _ in 0..dash_length {
             dashes.push('-');
@@ -128,91 +461,952 @@ impl Lectionary {
         dashes
     }
 
-    fn print_day_name(&self, dashes: &str) {
-        println!("{dashes}");
-        println!("  {}  ", self.get_day_name());
-        println!("{dashes}");
+    /// Writes the day name and color between separators. If `width` is given, each line is centered within it
+    ///
+    /// If `color` is true, the day name is bold and the liturgical color is written in that color
+    fn print_day_name(&self, seperator: Option<&str>, width: Option<u16>, color: bool, out: &mut dyn Write) -> io::Result<()> {
+        let seperator = seperator.map(|line| center_line(line, width, false));
+        let seperator = seperator.as_deref();
+        print_separator(seperator, out)?;
+        let day_name = self.get_day_name();
+        let (left, right) = center_padding(day_name, width, true);
+        writeln!(out, "{}{}{}", " ".repeat(left), styled(day_name, BOLD, color), " ".repeat(right))?;
+        match self.liturgical_color() {
+            Color::Unknown => debug!("Could not determine liturgical color for '{}'", self.get_day_name()),
+            liturgical_color => {
+                let name = liturgical_color.to_string();
+                let (left, right) = center_padding(&format!("Color: {name}"), width, true);
+                let name = styled(&name, ansi_code(liturgical_color), color);
+                writeln!(out, "{}Color: {name}{}", " ".repeat(left), " ".repeat(right))?;
+            }
+        }
+        print_separator(seperator, out)
+    }
+}
+
+/// Pads `text` to center it within `width`, on both sides if `pad_right` is true, otherwise only on the left
+///
+/// If `width` is None or too narrow, text padded on the right is padded with two spaces on each side, and other text is left
+/// as is. The day name and its separator are padded the same way since the separator is four columns longer. An empty line is
+/// left empty
+fn center_line(text: &str, width: Option<u16>, pad_right: bool) -> String {
+    let (left, right) = center_padding(text, width, pad_right);
+    format!("{}{text}{}", " ".repeat(left), " ".repeat(right))
+}
+
+/// Number of spaces before and after `text` for `center_line`
+fn center_padding(text: &str, width: Option<u16>, pad_right: bool) -> (usize, usize) {
+    let length = text.width();
+    let minimal = if pad_right { (2, 2) } else { (0, 0) };
+    let Some(width) = width.map(usize::from) else {
+        return minimal;
+    };
+    let minimal_length = if pad_right { length + 4 } else { length };
+    if length == 0 || minimal_length > width {
+        return minimal;
+    }
+    let left = (width - length) / 2;
+    let right = if pad_right { width - length - left } else { 0 };
+    (left, right)
+}
+
+/// Writes the separator line, if there is one
+fn print_separator(seperator: Option<&str>, out: &mut dyn Write) -> io::Result<()> {
+    match seperator {
+        Some(line) => writeln!(out, "{line}"),
+        None => Ok(()),
+    }
+}
+
+/// ANSI escape code for bold text
+const BOLD: &str = "\x1b[1m";
+/// ANSI escape code that ends any style
+const RESET: &str = "\x1b[0m";
+
+/// ANSI escape code for writing the name of a liturgical color in that color. Violet and rose use the closest terminal colors
+fn ansi_code(color: Color) -> &'static str {
+    match color {
+        Color::Green => "\x1b[32m",
+        Color::Violet => "\x1b[35m",
+        Color::White => "\x1b[97m",
+        Color::Red => "\x1b[31m",
+        Color::Rose => "\x1b[95m",
+        Color::Unknown => "",
+    }
+}
+
+/// Wraps `text` in the ANSI escape code `code` if `color` is true
+fn styled(text: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("{code}{text}{RESET}")
+    } else {
+        text.to_owned()
+    }
+}
+
+/// Liturgical responses that may end a reading, in lowercase. Longer phrases come first
+const RESPONSE_PHRASES: [&str; 4] = [
+    "praise to you, lord jesus christ",
+    "the gospel of the lord",
+    "the word of the lord",
+    "thanks be to god",
+];
+
+/// Removes the liturgical responses (e.g. "The word of the Lord.") from the end of the text, ignoring case
+///
+/// A phrase is only removed if it is on its own line or follows the end of a sentence, so "made by the word of the Lord." is kept
+fn strip_trailing_responses(text: &str) -> &str {
+    let mut remaining = text.trim_end();
+    'strip: loop {
+        let without_punctuation = remaining.trim_end_matches(['.', '!']);
+        for phrase in RESPONSE_PHRASES {
+            let Some(start) = without_punctuation.len().checked_sub(phrase.len()) else {
+                continue;
+            };
+            if !without_punctuation.is_char_boundary(start) || !without_punctuation[start..].eq_ignore_ascii_case(phrase) {
+                continue;
+            }
+            let before = &without_punctuation[..start];
+            let at_boundary =
+                before.ends_with('\n') || before.trim_end().is_empty() || before.trim_end().ends_with(['.', '!', '?', '"', '\u{201D}']);
+            if at_boundary {
+                remaining = before.trim_end();
+                continue 'strip;
+            }
+        }
+        return remaining;
     }
 }
 
 impl Reading {
+    /// Gets the text of the reading with the length from the settings, without the trailing responses unless they are included
+    pub(crate) fn get_display_text(&self, settings: &DisplaySettings) -> &str {
+        let text = self.get_text_of_length(settings.reading_length);
+        if settings.include_responses {
+            text
+        } else {
+            strip_trailing_responses(text)
+        }
+    }
+
     /// prints the reading
     ///
     /// seperator is the line seperating the heading from the text
-    fn pretty_print_as_reading(&self, heading: &str, seperator: &str, line_breaks: LineBreaks) {
-        self.print_heading(heading);
-        println!("{seperator}");
-        match line_breaks {
-            LineBreaks::Original => println!("{}", self.get_text()),
-            LineBreaks::None => println!("{}", self.get_text().replace('\n', " ")),
-            LineBreaks::Width(width) => Self::print_word_wrapped_text(self.get_text(), width),
-        };
-        println!("{seperator}");
+    fn pretty_print_as_reading(
+        &self,
+        heading: &str,
+        seperator: Option<&str>,
+        settings: &DisplaySettings,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.print_heading(heading, settings, out)?;
+        print_separator(seperator, out)?;
+        with_line_numbers(settings, out, |out: &mut dyn Write| self.print_reading_text(settings, out))?;
+        self.print_footnotes(settings, out)?;
+        print_separator(seperator, out)
+    }
+
+    /// Writes the footnote markers of the reading on one line if enabled in the settings and the reading has any
+    fn print_footnotes(&self, settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+        let footnotes = self.get_footnotes();
+        if settings.footnotes && !footnotes.is_empty() {
+            writeln!(out, "Footnotes: {}", footnotes.join(", "))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the text of the reading using the length and line breaks from the settings
+    fn print_reading_text(&self, settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+        let text = self.get_display_text(settings);
+        match settings.line_breaks {
+            LineBreaks::Original => writeln!(out, "{text}"),
+            LineBreaks::None => writeln!(out, "{}", text.replace('\n', " ")),
+            LineBreaks::Width(width) => Self::print_word_wrapped_text(text, width, out),
+        }
     }
 
     /// Should only be used for Psalms
-    fn pretty_print_as_psalm(&self, heading: &str, seperator: &str) {
-        self.print_heading(heading);
-        println!("{seperator}");
+    fn pretty_print_as_psalm(
+        &self,
+        heading: &str,
+        seperator: Option<&str>,
+        settings: &DisplaySettings,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.print_heading(heading, settings, out)?;
+        print_separator(seperator, out)?;
+        with_line_numbers(settings, out, |out: &mut dyn Write| self.print_psalm_text(settings, out))?;
+        self.print_footnotes(settings, out)?;
+        print_separator(seperator, out)
+    }
+
+    /// Writes the text of the psalm, with the verse number removed from the first line unless the settings keep it
+    fn print_psalm_text(&self, settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
         let mut lines = self.get_text().lines();
         if let Some(first_line) = lines.next() {
-            println!("{}", Self::format_psalm_first_line(first_line));
+            if settings.psalm_keep_verse_number {
+                writeln!(out, "{first_line}")?;
+            } else {
+                writeln!(out, "{}", Self::format_psalm_first_line(first_line))?;
+            }
             for line in lines {
-                println!("{line}");
+                writeln!(out, "{line}")?;
             }
         } else {
             error!("Can't format the psalm: it has no content");
         }
-        println!("{seperator}");
+        Ok(())
     }
 
     /// Similar to psalm but without modifications to the first line
-    fn pretty_print_as_alleliua(&self, heading: &str, seperator: &str) {
-        self.print_heading(heading);
-        println!("{seperator}");
-        println!("{}", self.get_text());
-        println!("{seperator}");
+    fn pretty_print_as_alleliua(
+        &self,
+        heading: &str,
+        seperator: Option<&str>,
+        settings: &DisplaySettings,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.print_heading(heading, settings, out)?;
+        print_separator(seperator, out)?;
+        with_line_numbers(settings, out, |out: &mut dyn Write| writeln!(out, "{}", self.get_text()))?;
+        self.print_footnotes(settings, out)?;
+        print_separator(seperator, out)
     }
 
-    fn print_heading(&self, heading: &str) {
-        if self.get_location().is_empty() {
-            println!("{heading}");
+    fn print_heading(&self, heading: &str, settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+        let heading = styled(heading, BOLD, settings.color);
+        let location = self.get_location();
+        if location.is_empty() {
+            writeln!(out, "{heading}")
+        } else if settings.expand_refs {
+            writeln!(out, "{heading} ({})", scripture::expand_location(location))
         } else {
-            println!("{heading} ({})", self.get_location());
+            writeln!(out, "{heading} ({location})")
+        }
+    }
+
+    /// Writes each paragraph of the text on a single line, keeping a blank line between paragraphs
+    fn print_spoken_text(text: &str, out: &mut dyn Write) -> io::Result<()> {
+        let paragraphs = text.split("\n\n").filter(|paragraph| !paragraph.trim().is_empty());
+        for (index, paragraph) in paragraphs.enumerate() {
+            if index > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}", paragraph.split_whitespace().collect::<Vec<_>>().join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Writes each line of a psalm or alleluia, spelling out the response marker
+    fn print_spoken_verses(&self, out: &mut dyn Write) -> io::Result<()> {
+        let mut lines = self.get_text().lines();
+        if let Some(first_line) = lines.next() {
+            writeln!(out, "{}", Self::spoken_response(&Self::format_psalm_first_line(first_line)))?;
+            for line in lines.filter(|line| !line.trim().is_empty()) {
+                writeln!(out, "{}", Self::spoken_response(line))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the "R." marker at the start of a line with "Response:"
+    fn spoken_response(line: &str) -> String {
+        let line = line.trim();
+        match line.strip_prefix("R.") {
+            Some(response) => format!("Response: {}", response.trim_start()),
+            None => line.to_owned(),
         }
     }
 
-    /// Removes the verse number from the first line of the psalm
-    fn format_psalm_first_line(first_line: &str) -> String {
-        let pattern = Regex::new(r"\(.+\)\s+").expect("Should be valid regex");
-        let mut out = String::new();
-        for part in pattern.splitn(first_line, 2) {
-            out += part;
+    /// Removes the verse number from the first line of the psalm (e.g. "R. (8) Lord, ..." becomes "R. Lord, ...")
+    ///
+    /// Only a parenthetical containing a number at the start of the line (after "R.") is removed. Lines without one are
+    /// returned unchanged
+    pub(crate) fn format_psalm_first_line(first_line: &str) -> String {
+        psalm_verse_number_pattern().replace(first_line, "$response").into_owned()
+    }
+
+    /// Reflows the text within each paragraph, keeping a blank line between paragraphs
+    fn print_word_wrapped_text(text: &str, max_width: u16, out: &mut dyn Write) -> io::Result<()> {
+        let paragraphs = text.split("\n\n").filter(|paragraph| !paragraph.trim().is_empty());
+        for (index, paragraph) in paragraphs.enumerate() {
+            if index > 0 {
+                writeln!(out)?;
+            }
+            Self::print_word_wrapped_paragraph(paragraph, max_width, out)?;
         }
-        out
+        Ok(())
     }
 
-    fn print_word_wrapped_text(text: &str, max_width: u16) {
-        let words = text.split_whitespace();
+    /// Widths are measured in terminal columns rather than bytes so that non-ASCII characters wrap at the right column
+    fn print_word_wrapped_paragraph(paragraph: &str, max_width: u16, out: &mut dyn Write) -> io::Result<()> {
+        let words = paragraph.split_whitespace();
         let mut current_line = String::new();
+        let mut current_width = 0;
         for word in words {
-            if (current_line.len() + word.len()) > max_width.into() {
-                println!("{current_line}");
+            let word_width = word.width();
+            if (current_width + word_width) > max_width.into() {
+                writeln!(out, "{current_line}")?;
                 current_line.clear();
+                current_width = 0;
             }
             current_line.push_str(word);
             current_line.push(' ');
+            current_width += word_width + 1;
         }
-        println!("{current_line}");
+        writeln!(out, "{current_line}")
+    }
+}
+
+/// Matches the verse number at the start of the first line of a psalm, keeping the "R." before it as `response`
+fn psalm_verse_number_pattern() -> &'static Regex {
+    static PSALM_VERSE_NUMBER_PATTERN: OnceLock<Regex> = OnceLock::new();
+    PSALM_VERSE_NUMBER_PATTERN.get_or_init(|| Regex::new(r"^(?<response>\s*R\.\s*)?\([^()]*\d[^()]*\)\s*").unwrap())
+}
+
+/// Lead-in spoken before each reading in place of the heading and location
+fn spoken_lead_in(name: &ReadingName) -> &'static str {
+    match name {
+        ReadingName::Reading1 => "The first reading.",
+        ReadingName::Reading2 => "The second reading.",
+        ReadingName::Psalm => "The responsorial psalm.",
+        ReadingName::Gospel => "A reading from the holy Gospel.",
+        ReadingName::Alleluia => "Alleluia.",
+    }
+}
+
+/// Ends the text with a period unless it already ends with punctuation
+fn as_sentence(text: &str) -> String {
+    let text = text.trim();
+    if text.ends_with(['.', '!', '?']) {
+        text.to_owned()
+    } else {
+        format!("{text}.")
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use scraper::Html;
+
     use super::*;
+    use crate::date::DateId;
+    use crate::html::PageSelectors;
+
+    fn sunday_lectionary() -> Lectionary {
+        let html = std::fs::read_to_string("tests/resources/sunday_or.html").unwrap();
+        Lectionary::create_from_html(
+            DateId::checked_from_str("071424").unwrap(),
+            &Html::parse_document(&html),
+            &PageSelectors::default(),
+        )
+        .unwrap()
+    }
+
+    fn raw_settings(readings: Vec<ReadingArg>) -> DisplaySettings {
+        DisplaySettings {
+            readings_to_display: ReadingsOptions::Specified(readings),
+            color: false,
+            offline: true,
+            store: true,
+            nearest: 0,
+            mass: None,
+            line_breaks: LineBreaks::Original,
+            reading_length: ReadingLength::Long,
+            rendering: Rendering::Raw,
+            separator_style: SeparatorStyle::Dashes,
+            short_labels: false,
+            expand_refs: false,
+            footnotes: false,
+            ascii: false,
+            include_responses: true,
+            psalm_keep_verse_number: false,
+            number_lines: false,
+        }
+    }
+
+    #[test]
+    fn raw_writes_only_text() {
+        let lectionary = sunday_lectionary();
+        let mut out = Vec::new();
+        lectionary.pretty_print(&raw_settings(vec![ReadingArg::Gospel]), &mut out).unwrap();
+        let gospel = lectionary.get_gospel().unwrap();
+        assert_eq!(
+            format!("{}\n", gospel.get_text_of_length(ReadingLength::Long)),
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn styled_only_with_color() {
+        assert_eq!("\x1b[1mName\x1b[0m", styled("Name", BOLD, true));
+        assert_eq!("Name", styled("Name", BOLD, false));
+    }
+
+    #[test]
+    fn render_matches_pretty_print() {
+        let html = std::fs::read_to_string("tests/resources/lent.html").unwrap();
+        let id = DateId::checked_from_str("022024").unwrap();
+        let lectionary = Lectionary::create_from_html(id, &Html::parse_document(&html), &PageSelectors::default()).unwrap();
+        let settings = DisplaySettings {
+            rendering: Rendering::Pretty,
+            ..raw_settings(vec![ReadingArg::Reading1, ReadingArg::Gospel])
+        };
+
+        let rendered = lectionary.render(&settings);
+        let expected = [
+            "-------------------------------------",
+            "  Tuesday of the First Week of Lent  ",
+            "  Color: Violet  ",
+            "-------------------------------------",
+            "Reading I (Is 55:10-11)",
+            "-------------------------------------",
+            "Thus says the LORD:",
+            "Just as from the heavens",
+            "the rain and snow come down",
+            "And do not return there",
+            "till they have watered the earth.",
+            "-------------------------------------",
+            "Gospel (Mt 6:7-15)",
+            "-------------------------------------",
+            "Jesus said to his disciples:",
+            "\"In praying, do not babble like the pagans,",
+            "who think that they will be heard because of their many words.\"",
+            "-------------------------------------",
+        ];
+        assert_eq!(format!("{}\n", expected.join("\n")), rendered);
+        let mut out = Vec::new();
+        lectionary.pretty_print(&settings, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), rendered);
+    }
+
+    #[test]
+    fn format_from_args_before_config() {
+        let no_format = OutputArgs::default();
+        assert!(matches!(
+            Rendering::from_config_and_args(DisplayFormat::Tts, &no_format),
+            Rendering::Tts
+        ));
+        let html = OutputArgs {
+            format: Some(DisplayFormat::Html),
+            ..OutputArgs::default()
+        };
+        assert!(matches!(
+            Rendering::from_config_and_args(DisplayFormat::Tts, &html),
+            Rendering::Html
+        ));
+        let headings = OutputArgs {
+            headings: true,
+            ..OutputArgs::default()
+        };
+        assert!(matches!(
+            Rendering::from_config_and_args(DisplayFormat::Json, &headings),
+            Rendering::Headings
+        ));
+    }
+
+    #[test]
+    fn repeated_readings_displayed_once() {
+        let args = DisplayReadingsArgs {
+            readings: Some(vec![ReadingArg::Gospel, ReadingArg::Psalm, ReadingArg::Gospel]),
+            ..DisplayReadingsArgs::default()
+        };
+        let ReadingsOptions::Specified(list) = ReadingsOptions::from_config_and_args(Vec::new(), args) else {
+            panic!("Readings were specified");
+        };
+        assert_eq!(vec![ReadingArg::Gospel, ReadingArg::Psalm], list);
+
+        let config_order = vec![ReadingArg::Reading1, ReadingArg::Reading1, ReadingArg::Gospel];
+        let ReadingsOptions::Specified(list) = ReadingsOptions::from_config_and_args(config_order, DisplayReadingsArgs::default()) else {
+            panic!("Config order is used");
+        };
+        assert_eq!(vec![ReadingArg::Reading1, ReadingArg::Gospel], list);
+    }
+
+    fn pretty_settings(readings_to_display: ReadingsOptions, line_breaks: LineBreaks) -> DisplaySettings {
+        DisplaySettings {
+            readings_to_display,
+            line_breaks,
+            rendering: Rendering::Pretty,
+            ..raw_settings(Vec::new())
+        }
+    }
+
+    /// Has every reading, with line breaks only where the page has them
+    fn solemnity_lectionary() -> Lectionary {
+        let html = std::fs::read_to_string("tests/resources/solemnity.html").unwrap();
+        Lectionary::create_from_html(
+            DateId::checked_from_str("052624").unwrap(),
+            &Html::parse_document(&html),
+            &PageSelectors::default(),
+        )
+        .unwrap()
+    }
+
+    /// Compares the rendered text to the snapshot in `tests/resources/snapshots`
+    ///
+    /// Run the tests with `UPDATE_SNAPSHOTS=1` to write the snapshots after an intended change to the formatting
+    fn assert_snapshot(name: &str, rendered: &str) {
+        let path = format!("tests/resources/snapshots/{name}.txt");
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path, rendered).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("Could not read snapshot '{path}': {e}"));
+        assert!(expected == rendered, "Rendered text does not match snapshot '{path}':\n{rendered}");
+    }
+
+    #[test]
+    fn snapshot_all_readings() {
+        let settings = pretty_settings(ReadingsOptions::All, LineBreaks::Original);
+        assert_snapshot("all_readings", &solemnity_lectionary().render(&settings));
+    }
+
+    #[test]
+    fn all_includes_alleluia() {
+        let settings = pretty_settings(ReadingsOptions::All, LineBreaks::Original);
+        let rendered = solemnity_lectionary().render(&settings);
+        let alleluia = rendered.find("Alleluia").expect("Alleluia is displayed with --all");
+        assert!(rendered.find("Responsorial Psalm").unwrap() < alleluia);
+        assert!(alleluia < rendered.find("Gospel").unwrap());
+    }
+
+    #[test]
+    fn snapshot_day_only() {
+        let settings = pretty_settings(ReadingsOptions::DayOnly, LineBreaks::Original);
+        assert_snapshot("day_only", &solemnity_lectionary().render(&settings));
+    }
+
+    #[test]
+    fn snapshot_width_40() {
+        let settings = pretty_settings(ReadingsOptions::All, LineBreaks::Width(40));
+        assert_snapshot("width_40", &solemnity_lectionary().render(&settings));
+    }
+
+    #[test]
+    fn snapshot_original_linebreaks() {
+        let readings = vec![ReadingArg::Reading2, ReadingArg::Alleluia, ReadingArg::Gospel];
+        let settings = pretty_settings(ReadingsOptions::Specified(readings), LineBreaks::Original);
+        assert_snapshot("original_linebreaks", &solemnity_lectionary().render(&settings));
+    }
+
+    #[test]
+    fn snapshot_no_linebreaks() {
+        let settings = pretty_settings(ReadingsOptions::Specified(vec![ReadingArg::Gospel]), LineBreaks::None);
+        assert_snapshot("no_linebreaks", &solemnity_lectionary().render(&settings));
+    }
+
+    #[test]
+    fn header_centered_within_width() {
+        assert_eq!("   Name   ", center_line("Name", Some(10), true));
+        assert_eq!("   Name    ", center_line("Name", Some(11), true));
+        assert_eq!("   ----", center_line("----", Some(10), false));
+        assert_eq!("  Name  ", center_line("Name", Some(7), true));
+        assert_eq!("  Name  ", center_line("Name", None, true));
+        assert_eq!("--------", center_line("--------", Some(7), false));
+        assert_eq!("", center_line("", Some(10), false));
+    }
+
+    #[test]
+    fn header_centered_by_display_width() {
+        // 主 and 日 each take two columns, while ê takes one column but two bytes
+        assert_eq!("  主日  ", center_line("主日", Some(8), true));
+        assert_eq!("   Fête   ", center_line("Fête", Some(10), true));
+    }
+
+    #[test]
+    fn auto_width_uses_terminal() {
+        let args = FormattingArgs {
+            max_width: None,
+            original_linebreaks: false,
+        };
+        assert!(matches!(
+            LineBreaks::from_config_and_args(false, MaxWidth::Auto, args, Some(80)),
+            LineBreaks::Width(80)
+        ));
+        assert!(matches!(
+            LineBreaks::from_config_and_args(false, MaxWidth::Auto, args, None),
+            LineBreaks::Width(140)
+        ));
+        assert!(matches!(
+            LineBreaks::from_config_and_args(false, MaxWidth::Columns(100), args, Some(80)),
+            LineBreaks::Width(100)
+        ));
+        let no_wrap_args = FormattingArgs {
+            max_width: Some(0),
+            original_linebreaks: false,
+        };
+        assert!(matches!(
+            LineBreaks::from_config_and_args(false, MaxWidth::Auto, no_wrap_args, Some(80)),
+            LineBreaks::None
+        ));
+    }
+
+    #[test]
+    fn heading_expands_refs() {
+        let lectionary = sunday_lectionary();
+        let settings = DisplaySettings {
+            rendering: Rendering::Pretty,
+            expand_refs: true,
+            ..raw_settings(vec![ReadingArg::Reading1])
+        };
+        let mut out = Vec::new();
+        lectionary.pretty_print(&settings, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("Reading I (Amos 7:12-15)"));
+    }
+
+    #[test]
+    fn footnotes_listed_when_enabled() {
+        let gospel = Reading::new(String::from("Jn 1:1-5"), String::from("In the beginning was the Word"), None)
+            .with_footnotes(vec![String::from("a"), String::from("*")]);
+        let lectionary = Lectionary::new(DateId::today(), String::from("Christmas"), None, None, None, Some(gospel), None);
+        let settings = DisplaySettings {
+            rendering: Rendering::Pretty,
+            ..raw_settings(vec![ReadingArg::Gospel])
+        };
+
+        let mut out = Vec::new();
+        lectionary.pretty_print(&settings, &mut out).unwrap();
+        assert!(!String::from_utf8(out).unwrap().contains("Footnotes"));
+
+        let mut out = Vec::new();
+        lectionary
+            .pretty_print(
+                &DisplaySettings {
+                    footnotes: true,
+                    ..settings
+                },
+                &mut out,
+            )
+            .unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("In the beginning was the Word\nFootnotes: a, *\n"));
+    }
+
+    #[test]
+    fn separator_styles() {
+        let alleluia = Reading::new(String::from("Jn 1:14a, 12a"), String::from("R. Alleluia, alleluia."), None);
+        let lectionary = Lectionary::new(DateId::today(), String::from("Christmas"), None, None, None, None, Some(alleluia));
+        let print_with = |separator_style| {
+            let settings = DisplaySettings {
+                rendering: Rendering::Pretty,
+                separator_style,
+                ..raw_settings(vec![ReadingArg::Alleluia])
+            };
+            let mut out = Vec::new();
+            lectionary.pretty_print(&settings, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(
+            "-------------\n  Christmas  \n  Color: White  \n-------------\nAlleluia (Jn 1:14a, 12a)\n-------------\nR. Alleluia, alleluia.\n-------------\n",
+            print_with(SeparatorStyle::Dashes)
+        );
+        assert_eq!(
+            "\n  Christmas  \n  Color: White  \n\nAlleluia (Jn 1:14a, 12a)\n\nR. Alleluia, alleluia.\n\n",
+            print_with(SeparatorStyle::Blank)
+        );
+        assert_eq!(
+            "  Christmas  \n  Color: White  \nAlleluia (Jn 1:14a, 12a)\nR. Alleluia, alleluia.\n",
+            print_with(SeparatorStyle::None)
+        );
+    }
+
+    #[test]
+    fn headings_with_short_labels() {
+        let lectionary = sunday_lectionary();
+        let settings = DisplaySettings {
+            rendering: Rendering::Headings,
+            short_labels: true,
+            ..raw_settings(ALL_READINGS.to_vec())
+        };
+        let mut out = Vec::new();
+        lectionary.pretty_print(&settings, &mut out).unwrap();
+        let headings = String::from_utf8(out).unwrap();
+        assert!(headings.contains("R1 (Am 7:12-15)\n"));
+        assert!(headings.contains("Ps (Ps 85:9-10, 11-12, 13-14)\n"));
+        assert!(!headings.contains("Reading I"));
+    }
+
+    #[test]
+    fn ascii_replaces_typographic_punctuation() {
+        let text = "Jesus said, \u{201C}Peace be with you.\u{201D} \u{2018}Lord\u{2019}s\u{2019} \u{2013} and \u{2014} then\u{2026}";
+        let gospel = Reading::new(String::from("Jn 20:19\u{2013}23"), String::from(text), None);
+        let lectionary = Lectionary::new(DateId::today(), String::from("Pentecost"), None, None, None, Some(gospel), None);
+        let settings = DisplaySettings {
+            rendering: Rendering::Pretty,
+            ..raw_settings(vec![ReadingArg::Gospel])
+        };
+
+        let mut out = Vec::new();
+        lectionary.pretty_print(&settings, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains(text));
+
+        let mut out = Vec::new();
+        lectionary
+            .pretty_print(&DisplaySettings { ascii: true, ..settings }, &mut out)
+            .unwrap();
+        let ascii = String::from_utf8(out).unwrap();
+        assert!(ascii.contains("Gospel (Jn 20:19-23)"));
+        assert!(ascii.contains("Jesus said, \"Peace be with you.\" 'Lord's' - and -- then..."));
+        assert!(ascii.is_ascii());
+    }
+
+    #[test]
+    fn trailing_responses_stripped() {
+        assert_eq!(
+            "Jesus said to his disciples: Peace be with you.",
+            strip_trailing_responses("Jesus said to his disciples: Peace be with you.\nThe Gospel of the Lord.\n")
+        );
+        assert_eq!(
+            "Brothers and sisters: Rejoice in the Lord always.",
+            strip_trailing_responses("Brothers and sisters: Rejoice in the Lord always. THE WORD OF THE LORD. Thanks be to God.")
+        );
+        assert_eq!(
+            "By the word of the LORD the heavens were made.",
+            strip_trailing_responses("By the word of the LORD the heavens were made.")
+        );
+        assert_eq!(
+            "The heavens were made by the word of the Lord.",
+            strip_trailing_responses("The heavens were made by the word of the Lord.")
+        );
+        assert_eq!(
+            "The word of the Lord came to Jonah.",
+            strip_trailing_responses("The word of the Lord came to Jonah.")
+        );
+    }
+
+    #[test]
+    fn responses_only_removed_when_excluded() {
+        let gospel = Reading::new(
+            String::from("Jn 1:1-5"),
+            String::from("In the beginning was the Word.\n\nThe Gospel of the Lord."),
+            None,
+        );
+        let lectionary = Lectionary::new(DateId::today(), String::from("Christmas"), None, None, None, Some(gospel), None);
+        let settings = raw_settings(vec![ReadingArg::Gospel]);
+
+        let mut out = Vec::new();
+        lectionary.pretty_print(&settings, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("The Gospel of the Lord."));
+
+        let mut out = Vec::new();
+        lectionary
+            .pretty_print(
+                &DisplaySettings {
+                    include_responses: false,
+                    ..settings
+                },
+                &mut out,
+            )
+            .unwrap();
+        assert_eq!("In the beginning was the Word.\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn headings_without_text() {
+        let lectionary = sunday_lectionary();
+        let settings = DisplaySettings {
+            rendering: Rendering::Headings,
+            ..raw_settings(ALL_READINGS.to_vec())
+        };
+        let mut out = Vec::new();
+        lectionary.pretty_print(&settings, &mut out).unwrap();
+        let headings = String::from_utf8(out).unwrap();
+        assert!(headings.contains("Fifteenth Sunday in Ordinary Time"));
+        assert!(headings.contains("Reading I (Am 7:12-15)\n"));
+        assert!(headings.contains("Responsorial Psalm (Ps 85:9-10, 11-12, 13-14)\n"));
+        assert!(headings.contains("Reading II ("));
+        assert!(headings.contains("Gospel ("));
+        assert!(!headings.contains("Amaziah"));
+    }
+
+    #[test]
+    fn html_document_has_contents_and_each_day() {
+        let gospel = Reading::new(String::from("Jn 1:1-5"), String::from("In the beginning & the end"), None);
+        let christmas = Lectionary::new(
+            DateId::checked_from_str("122524").unwrap(),
+            String::from("The Nativity of the Lord"),
+            None,
+            None,
+            None,
+            Some(gospel),
+            None,
+        );
+        let settings = DisplaySettings {
+            rendering: Rendering::Html,
+            ..raw_settings(ALL_READINGS.to_vec())
+        };
+        let mut out = Vec::new();
+        print_lectionaries(&[sunday_lectionary(), christmas], &settings, &mut out).unwrap();
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Readings for Sunday, July 14, 2024 to Wednesday, December 25, 2024</title>"));
+        assert!(html.contains("<a href=\"#day-071424\">Sunday, July 14, 2024: Fifteenth Sunday in Ordinary Time</a>"));
+        assert!(html.contains("<article id=\"day-122524\">"));
+        assert!(html.contains("<h3>Reading I <cite>Am 7:12-15</cite></h3>"));
+        assert!(html.contains("<p>In the beginning &amp; the end</p>"));
+        assert_eq!(1, html.matches("<html").count());
+    }
+
+    #[test]
+    fn json_pretty_and_compact() {
+        let settings = DisplaySettings {
+            rendering: Rendering::Json { compact: false },
+            ..raw_settings(vec![ReadingArg::Reading1, ReadingArg::Gospel])
+        };
+        let mut out = Vec::new();
+        print_lectionaries(&[sunday_lectionary()], &settings, &mut out).unwrap();
+        let pretty = String::from_utf8(out).unwrap();
+        assert!(pretty.lines().count() > 1);
+        let json: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!("071424", json["date"]);
+        assert_eq!("Fifteenth Sunday in Ordinary Time", json["day_name"]);
+        assert_eq!("Reading I", json["readings"][0]["name"]);
+        assert_eq!("Am 7:12-15", json["readings"][0]["location"]);
+        assert_eq!("Gospel", json["readings"][1]["name"]);
+        assert_eq!(2, json["readings"].as_array().unwrap().len());
+
+        let settings = DisplaySettings {
+            rendering: Rendering::Json { compact: true },
+            ..settings
+        };
+        let mut out = Vec::new();
+        print_lectionaries(&[sunday_lectionary(), sunday_lectionary()], &settings, &mut out).unwrap();
+        let compact = String::from_utf8(out).unwrap();
+        assert_eq!(1, compact.lines().count());
+        let json: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        assert_eq!(2, json.as_array().unwrap().len());
+    }
+
+    #[test]
+    fn json_array_written_one_element_at_a_time() {
+        let settings = raw_settings(vec![ReadingArg::Gospel]);
+        let mut out = Vec::new();
+        export::write_json_array_element(&sunday_lectionary(), &settings, true, &mut out).unwrap();
+        export::write_json_array_element(&sunday_lectionary(), &settings, false, &mut out).unwrap();
+        export::close_json_array(false, &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(2, json.as_array().unwrap().len());
+        assert_eq!("071424", json[1]["date"]);
+
+        let mut out = Vec::new();
+        export::close_json_array(true, &mut out).unwrap();
+        assert_eq!("[]\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn tts_has_no_headings_or_locations() {
+        let lectionary = sunday_lectionary();
+        let settings = DisplaySettings {
+            rendering: Rendering::Tts,
+            ..raw_settings(vec![ReadingArg::Reading1, ReadingArg::Psalm])
+        };
+        let mut out = Vec::new();
+        lectionary.pretty_print(&settings, &mut out).unwrap();
+        let spoken = String::from_utf8(out).unwrap();
+        assert!(spoken.starts_with("Fifteenth Sunday in Ordinary Time.\n"));
+        assert!(spoken.contains("The first reading.\nAmaziah, priest of Bethel"));
+        assert!(!spoken.contains("Am 7:12-15"));
+        assert!(!spoken.contains("---"));
+        assert!(!spoken.contains("(8)"));
+    }
+
+    #[test]
+    fn psalm_response_spelled_out() {
+        assert_eq!("Response: Lord, let us see", Reading::spoken_response("R. Lord, let us see"));
+        assert_eq!("Response: Lord", Reading::spoken_response("  R.  Lord"));
+        assert_eq!("Rejoice in the Lord", Reading::spoken_response("Rejoice in the Lord"));
+    }
 
     #[test]
     fn psalm_heading_formatted() {
         assert_eq!("R. Test Line", Reading::format_psalm_first_line("R. (8)   Test Line"));
+        assert_eq!("R. Test Line", Reading::format_psalm_first_line("R. (cf. 12b) Test Line"));
+        assert_eq!("Test Line", Reading::format_psalm_first_line("(8) Test Line"));
+    }
+
+    #[test]
+    fn lines_numbered_and_aligned() {
+        let text = (1..=10).map(|line| format!("line {line}")).collect::<Vec<_>>().join("\n");
+        let numbered = number_lines(&text);
+        let lines: Vec<&str> = numbered.lines().collect();
+        assert_eq!(" 1 line 1", lines[0]);
+        assert_eq!(" 9 line 9", lines[8]);
+        assert_eq!("10 line 10", lines[9]);
+
+        assert_eq!("1 first\n\n2 second\n", number_lines("first\n\nsecond\n"));
+    }
+
+    #[test]
+    fn numbers_only_reading_text() {
+        let settings = DisplaySettings {
+            number_lines: true,
+            ..pretty_settings(ReadingsOptions::Specified(vec![ReadingArg::Gospel]), LineBreaks::Width(40))
+        };
+        let expected = [
+            "       -------------------------",
+            "         The Most Holy Trinity          ",
+            "              Color: White              ",
+            "       -------------------------",
+            "Gospel (Mt 28:16-20)",
+            "-------------------------",
+            "1 The eleven disciples went to Galilee, to ",
+            "2 the mountain to which Jesus had ordered ",
+            "3 them. ",
+            "-------------------------",
+        ];
+        assert_eq!(format!("{}\n", expected.join("\n")), solemnity_lectionary().render(&settings));
+    }
+
+    #[test]
+    fn psalm_heading_without_verse_number_untouched() {
+        assert_eq!("R. Test Line", Reading::format_psalm_first_line("R. Test Line"));
+        assert_eq!(
+            "R. Lord (our God) is good",
+            Reading::format_psalm_first_line("R. Lord (our God) is good")
+        );
+        assert_eq!("", Reading::format_psalm_first_line(""));
+    }
+
+    #[test]
+    fn psalm_verse_number_kept_when_configured() {
+        let lectionary = solemnity_lectionary();
+        let psalm_first_line = |keep: bool| {
+            let settings = DisplaySettings {
+                rendering: Rendering::Pretty,
+                psalm_keep_verse_number: keep,
+                ..raw_settings(vec![ReadingArg::Psalm])
+            };
+            let rendered = lectionary.render(&settings);
+            rendered.lines().nth(6).unwrap().to_owned()
+        };
+        assert_eq!(
+            "R. (12b) Blessed the people the Lord has chosen to be his own.",
+            psalm_first_line(true)
+        );
+        assert_eq!("R. Blessed the people the Lord has chosen to be his own.", psalm_first_line(false));
+    }
+
+    #[test]
+    fn word_wrapped_text_written() {
+        let mut out = Vec::new();
+        Reading::print_word_wrapped_text("one two three four", 10, &mut out).unwrap();
+        assert_eq!("one two \nthree four \n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn word_wrap_counts_columns_not_bytes() {
+        let mut out = Vec::new();
+        Reading::print_word_wrapped_text("Ézéchiel said — listen now", 17, &mut out).unwrap();
+        assert_eq!("Ézéchiel said — \nlisten now \n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn word_wrap_keeps_paragraphs() {
+        let mut out = Vec::new();
+        let text = "first paragraph\nof text\n\nsecond paragraph";
+        Reading::print_word_wrapped_text(text, 20, &mut out).unwrap();
+        assert_eq!("first paragraph of \ntext \n\nsecond paragraph \n", String::from_utf8(out).unwrap());
     }
 }
@@ -0,0 +1,284 @@
+//! Renders lectionaries as a single self-contained HTML document, e.g. for reading offline on an e-reader, or as JSON for other
+//! programs
+
+use std::io::{self, Write};
+
+use serde_json::{json, Value};
+
+use crate::{
+    display::{self, DisplaySettings, LineBreaks},
+    lectionary::{Lectionary, Reading, ReadingName},
+    liturgy::Color,
+    scripture,
+};
+
+/// Kept inline so that the document does not depend on any other file
+const STYLE: &str = "body { font-family: serif; line-height: 1.5; max-width: 40em; margin: 0 auto; padding: 1em; }
+h2 { margin-bottom: 0; }
+h3 cite { font-weight: normal; font-style: italic; }
+.date, .color, .footnotes { color: #555; }
+article { page-break-before: always; }";
+
+/// Writes the lectionaries as one HTML document, with a table of contents linking to each day
+///
+/// # Errors
+/// Returns an `io::Error` if writing to `out` fails
+pub(crate) fn write_html_document(lectionaries: &[Lectionary], settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\">")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(&document_title(lectionaries)))?;
+    writeln!(out, "<style>\n{STYLE}\n</style>")?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    write_table_of_contents(lectionaries, out)?;
+    for lectionary in lectionaries {
+        write_article(lectionary, settings, out)?;
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+/// The name of the day if there is only one, otherwise the range of dates
+fn document_title(lectionaries: &[Lectionary]) -> String {
+    match lectionaries {
+        [] => String::from("Readings"),
+        [only] => only.get_day_name().to_owned(),
+        [first, .., last] => format!("Readings for {} to {}", long_date(first), long_date(last)),
+    }
+}
+
+fn write_table_of_contents(lectionaries: &[Lectionary], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "<nav>")?;
+    writeln!(out, "<h1>Contents</h1>")?;
+    writeln!(out, "<ol>")?;
+    for lectionary in lectionaries {
+        writeln!(
+            out,
+            "<li><a href=\"#{}\">{}: {}</a></li>",
+            anchor(lectionary),
+            long_date(lectionary),
+            escape(lectionary.get_day_name())
+        )?;
+    }
+    writeln!(out, "</ol>")?;
+    writeln!(out, "</nav>")
+}
+
+fn write_article(lectionary: &Lectionary, settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "<article id=\"{}\">", anchor(lectionary))?;
+    writeln!(out, "<header>")?;
+    writeln!(out, "<h2>{}</h2>", escape(lectionary.get_day_name()))?;
+    writeln!(out, "<p class=\"date\">{}</p>", long_date(lectionary))?;
+    match lectionary.liturgical_color() {
+        Color::Unknown => {}
+        color => writeln!(out, "<p class=\"color\">Color: {color}</p>")?,
+    }
+    writeln!(out, "</header>")?;
+    for (name, reading) in lectionary.present_readings(settings.reading_list()) {
+        write_reading(&name, reading, settings, out)?;
+    }
+    writeln!(out, "</article>")
+}
+
+fn write_reading(name: &ReadingName, reading: &Reading, settings: &DisplaySettings, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "<section class=\"reading\">")?;
+    let location = reading.get_location();
+    if location.is_empty() {
+        writeln!(out, "<h3>{name}</h3>")?;
+    } else if settings.expand_refs {
+        writeln!(
+            out,
+            "<h3>{name} <cite>{}</cite></h3>",
+            escape(&scripture::expand_location(location))
+        )?;
+    } else {
+        writeln!(out, "<h3>{name} <cite>{}</cite></h3>", escape(location))?;
+    }
+    match name {
+        ReadingName::Psalm => {
+            let mut lines = reading.get_text().lines();
+            let first_line = match lines.next() {
+                Some(line) if settings.psalm_keep_verse_number => line.to_owned(),
+                Some(line) => Reading::format_psalm_first_line(line),
+                None => String::new(),
+            };
+            let text = std::iter::once(first_line.as_str()).chain(lines).collect::<Vec<_>>().join("\n");
+            write_paragraphs(&text, true, out)?;
+        }
+        ReadingName::Alleluia => write_paragraphs(reading.get_text(), true, out)?,
+        _ => {
+            let keep_line_breaks = matches!(settings.line_breaks, LineBreaks::Original);
+            write_paragraphs(reading.get_display_text(settings), keep_line_breaks, out)?;
+        }
+    }
+    let footnotes = reading.get_footnotes();
+    if settings.footnotes && !footnotes.is_empty() {
+        writeln!(out, "<p class=\"footnotes\">Footnotes: {}</p>", escape(&footnotes.join(", ")))?;
+    }
+    writeln!(out, "</section>")
+}
+
+/// Writes each paragraph (separated by a blank line) as a `<p>`
+///
+/// Line breaks within a paragraph are kept as `<br>` if `keep_line_breaks` is true, otherwise the lines are joined
+fn write_paragraphs(text: &str, keep_line_breaks: bool, out: &mut dyn Write) -> io::Result<()> {
+    let separator = if keep_line_breaks { "<br>\n" } else { " " };
+    for paragraph in text.split("\n\n").filter(|paragraph| !paragraph.trim().is_empty()) {
+        let lines: Vec<String> = paragraph
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(escape)
+            .collect();
+        writeln!(out, "<p>{}</p>", lines.join(separator))?;
+    }
+    Ok(())
+}
+
+/// Writes the lectionaries as JSON: an object for a single lectionary, otherwise an array of objects. Ends with a newline
+///
+/// Indented for reading unless `compact` is true, in which case everything is on one line
+///
+/// # Errors
+/// Returns an `io::Error` if writing to `out` fails
+pub(crate) fn write_json(lectionaries: &[Lectionary], settings: &DisplaySettings, compact: bool, out: &mut dyn Write) -> io::Result<()> {
+    let value = match lectionaries {
+        [only] => lectionary_json(only, settings),
+        _ => lectionaries
+            .iter()
+            .map(|lectionary| lectionary_json(lectionary, settings))
+            .collect(),
+    };
+    if compact {
+        serde_json::to_writer(&mut *out, &value)?;
+    } else {
+        serde_json::to_writer_pretty(&mut *out, &value)?;
+    }
+    writeln!(out)
+}
+
+/// Writes one lectionary of a JSON array that is written one element at a time, e.g. while streaming from the database
+///
+/// Each element is on its own line. `first` must be true for the first element, which opens the array. The array is closed with
+/// `close_json_array`
+///
+/// # Errors
+/// Returns an `io::Error` if writing to `out` fails
+pub(crate) fn write_json_array_element(
+    lectionary: &Lectionary,
+    settings: &DisplaySettings,
+    first: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{}", if first { "[" } else { "," })?;
+    serde_json::to_writer(&mut *out, &lectionary_json(lectionary, settings))?;
+    Ok(())
+}
+
+/// Closes a JSON array written with `write_json_array_element`. `empty` must be true if no elements were written
+///
+/// # Errors
+/// Returns an `io::Error` if writing to `out` fails
+pub(crate) fn close_json_array(empty: bool, out: &mut dyn Write) -> io::Result<()> {
+    if empty {
+        writeln!(out, "[]")
+    } else {
+        writeln!(out, "\n]")
+    }
+}
+
+/// The date, name, and color of the day, along with the readings selected in the settings
+fn lectionary_json(lectionary: &Lectionary, settings: &DisplaySettings) -> Value {
+    let color = match lectionary.liturgical_color() {
+        Color::Unknown => Value::Null,
+        color => Value::from(color.to_string()),
+    };
+    let readings: Vec<Value> = lectionary
+        .present_readings(settings.reading_list())
+        .map(|(name, reading)| {
+            let location = if settings.expand_refs {
+                scripture::expand_location(reading.get_location())
+            } else {
+                reading.get_location().to_owned()
+            };
+            json!({
+                "name": name.as_str(),
+                "location": location,
+                "text": json_text(reading.get_display_text(settings), settings),
+                "footnotes": reading.get_footnotes(),
+            })
+        })
+        .collect();
+    json!({
+        "date": lectionary.get_id(),
+        "day_name": json_text(lectionary.get_day_name(), settings),
+        "color": color,
+        "readings": readings,
+    })
+}
+
+/// Replaces typographic punctuation in a value if ASCII output is enabled, since it can't be done after serializing
+fn json_text(text: &str, settings: &DisplaySettings) -> String {
+    if settings.ascii {
+        display::to_ascii(text)
+    } else {
+        text.to_owned()
+    }
+}
+
+/// Id of the article for the lectionary, used as the target of the table of contents links
+fn anchor(lectionary: &Lectionary) -> String {
+    format!("day-{}", lectionary.get_id())
+}
+
+/// e.g. "Sunday, July 14, 2024"
+fn long_date(lectionary: &Lectionary) -> String {
+    lectionary.get_id().to_naive_date().format("%A, %B %-d, %Y").to_string()
+}
+
+/// Escapes the characters that have a special meaning in HTML text and attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!("Tom &amp; Jerry &lt;b&gt; &quot;hi&quot;", escape("Tom & Jerry <b> \"hi\""));
+        assert_eq!("Alleluia, alleluia.", escape("Alleluia, alleluia."));
+    }
+
+    #[test]
+    fn paragraphs_with_and_without_line_breaks() {
+        let text = "line one\nline two\n\nsecond paragraph";
+
+        let mut out = Vec::new();
+        write_paragraphs(text, true, &mut out).unwrap();
+        assert_eq!(
+            "<p>line one<br>\nline two</p>\n<p>second paragraph</p>\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        let mut out = Vec::new();
+        write_paragraphs(text, false, &mut out).unwrap();
+        assert_eq!(
+            "<p>line one line two</p>\n<p>second paragraph</p>\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
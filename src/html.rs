@@ -1,7 +1,9 @@
-use std::collections::HashMap;
+//! Parsing of the readings pages of the USCCB site. Used through `source::Usccb`
+
 use std::sync::OnceLock;
 
 use log::*;
+use regex::Regex;
 use scraper::selectable::Selectable;
 use scraper::selector::ToCss;
 use scraper::ElementRef;
@@ -9,55 +11,115 @@ use scraper::Html;
 use scraper::Node;
 use scraper::Selector;
 
+use crate::config::SelectorsConfig;
 use crate::date::DateId;
 use crate::lectionary::Lectionary;
 use crate::lectionary::Reading;
 use crate::lectionary::ReadingName;
 
 /// Main container in which all other relevant elements are found
-fn container_selector() -> &'static Selector {
-    static CONTAINER_SELECTOR: OnceLock<Selector> = OnceLock::new();
-    CONTAINER_SELECTOR.get_or_init(|| Selector::parse("#block-usccb-readings-content div.page-container").unwrap())
-}
+pub(crate) const DEFAULT_CONTAINER_SELECTOR: &str = "#block-usccb-readings-content div.page-container";
 /// Use within element found by `CONTAINER_SELECTOR`. Finds the element that has the name of the day (e.g. Fourteenth Sunday in Ordinary Time )
-fn day_name_selector() -> &'static Selector {
-    static DAY_NAME_SELECTOR: OnceLock<Selector> = OnceLock::new();
-    DAY_NAME_SELECTOR.get_or_init(|| Selector::parse("div.b-lectionary div.innerblock :first-child").unwrap())
-}
+pub(crate) const DEFAULT_DAY_NAME_SELECTOR: &str = "div.b-lectionary div.innerblock :first-child";
 /// Use within element found by `CONTAINER_SELECTOR`. Finds all the verse(aka reading) containers
-fn readings_selector() -> &'static Selector {
-    static READINGS_SELECTOR: OnceLock<Selector> = OnceLock::new();
-    READINGS_SELECTOR.get_or_init(|| Selector::parse("div.b-verse").unwrap())
-}
+pub(crate) const DEFAULT_READINGS_SELECTOR: &str = "div.b-verse";
 /// Use within a element found by `READINGS_SELECTOR`
-fn reading_name_selector() -> &'static Selector {
-    static READING_NAME_SELECTOR: OnceLock<Selector> = OnceLock::new();
-    READING_NAME_SELECTOR.get_or_init(|| Selector::parse(".name").unwrap())
-}
+pub(crate) const DEFAULT_READING_NAME_SELECTOR: &str = ".name";
 /// Use within element found by `READINGS_SELECTOR`. The container with the actual text of the reading.
-fn reading_content_selector() -> &'static Selector {
-    static READING_CONTENT_SELECTOR: OnceLock<Selector> = OnceLock::new();
-    READING_CONTENT_SELECTOR.get_or_init(|| Selector::parse("div.content-body").unwrap())
-}
+pub(crate) const DEFAULT_READING_CONTENT_SELECTOR: &str = "div.content-body";
 /// Use within element found by `READINGS_SELECTOR`. Finds the address (book, chapter, verse(s)) of the reading
-fn reading_location_selector() -> &'static Selector {
-    static READING_LOCATION_SELECTOR: OnceLock<Selector> = OnceLock::new();
-    READING_LOCATION_SELECTOR.get_or_init(|| Selector::parse("div.content-header div.address a").unwrap())
+pub(crate) const DEFAULT_READING_LOCATION_SELECTOR: &str = "div.content-header div.address a";
+
+/// The selectors that find the readings on the page. Each can be overridden in the config in case the site changes its markup
+///
+/// The default is the selectors for the current markup of the site
+pub struct PageSelectors {
+    container: Selector,
+    day_name: Selector,
+    readings: Selector,
+    reading_name: Selector,
+    reading_content: Selector,
+    reading_location: Selector,
 }
+
+impl Default for PageSelectors {
+    fn default() -> Self {
+        Self::from_config(&SelectorsConfig::default())
+    }
+}
+
+impl PageSelectors {
+    /// Uses the selectors set in the `[selectors]` table of the config, and the default for any that are not set or invalid
+    pub(crate) fn from_config(config: &SelectorsConfig) -> Self {
+        Self {
+            container: selector_or_default("container", &config.container, DEFAULT_CONTAINER_SELECTOR),
+            day_name: selector_or_default("day_name", &config.day_name, DEFAULT_DAY_NAME_SELECTOR),
+            readings: selector_or_default("readings", &config.readings, DEFAULT_READINGS_SELECTOR),
+            reading_name: selector_or_default("reading_name", &config.reading_name, DEFAULT_READING_NAME_SELECTOR),
+            reading_content: selector_or_default("reading_content", &config.reading_content, DEFAULT_READING_CONTENT_SELECTOR),
+            reading_location: selector_or_default("reading_location", &config.reading_location, DEFAULT_READING_LOCATION_SELECTOR),
+        }
+    }
+}
+
+/// Parses the configured selector, or the default if none is configured. An invalid selector is logged and the default is used
+fn selector_or_default(key: &str, configured: &str, default: &str) -> Selector {
+    if !configured.is_empty() {
+        match Selector::parse(configured) {
+            Ok(selector) => {
+                info!("Using configured selector '{configured}' for '{key}'");
+                return selector;
+            }
+            Err(e) => error!("Invalid selector '{configured}' for 'selectors.{key}' ({e}); Using the default '{default}'"),
+        }
+    }
+    Selector::parse(default).expect("Default selectors are valid")
+}
+
 /// Use within element found by `CONTAINER_SELECTOR`. On a holiday page, finds the link for the day time reading
 fn day_link_selector() -> &'static Selector {
     static DAY_LINK_SELECTOR: OnceLock<Selector> = OnceLock::new();
     DAY_LINK_SELECTOR.get_or_init(|| Selector::parse("div.b-lectionary div.innerblock a[href$=\"day.cfm\" i ]").unwrap())
 }
 
+/// Use within element found by `CONTAINER_SELECTOR`. On a holiday page, finds the links for every Mass (e.g. Vigil, Day)
+fn mass_link_selector() -> &'static Selector {
+    static MASS_LINK_SELECTOR: OnceLock<Selector> = OnceLock::new();
+    MASS_LINK_SELECTOR.get_or_init(|| Selector::parse("div.b-lectionary div.innerblock li a[href$=\".cfm\" i ]").unwrap())
+}
+
+/// Matches the "OR:" line that separates a reading from its alternate
+fn alternate_separator_pattern() -> &'static Regex {
+    static ALTERNATE_SEPARATOR_PATTERN: OnceLock<Regex> = OnceLock::new();
+    ALTERNATE_SEPARATOR_PATTERN.get_or_init(|| Regex::new(r"\n\s*OR:[^\S\n]*\n").unwrap())
+}
+
 impl Lectionary {
-    pub fn create_from_html(id: DateId, document: &Html) -> Result<Self, LectionaryHtmlError> {
+    /// Parses a `Lectionary` from the readings page for the given day
+    ///
+    /// # Errors
+    /// Returns a `LectionaryHtmlError` if a required element of the page could not be found or parsed
+    pub fn create_from_html(id: DateId, document: &Html, selectors: &PageSelectors) -> Result<Self, LectionaryHtmlError> {
+        Self::parse_html(id, document, selectors, false)
+    }
+
+    /// Like `create_from_html`, but keeps whatever readings were found instead of failing when a required reading is missing
+    ///
+    /// A warning is logged for each missing reading
+    ///
+    /// # Errors
+    /// Returns a `LectionaryHtmlError` if the container or the name of the day could not be found
+    pub fn create_from_html_lenient(id: DateId, document: &Html, selectors: &PageSelectors) -> Result<Self, LectionaryHtmlError> {
+        Self::parse_html(id, document, selectors, true)
+    }
+
+    fn parse_html(id: DateId, document: &Html, selectors: &PageSelectors, lenient: bool) -> Result<Self, LectionaryHtmlError> {
         let container = document
-            .select(container_selector())
+            .select(&selectors.container)
             .next()
             .ok_or_else(|| LectionaryHtmlError::NoContainerFound { date: id.clone() })?;
         let day_name_elmnt = container
-            .select(day_name_selector())
+            .select(&selectors.day_name)
             .next()
             .ok_or_else(|| LectionaryHtmlError::NoDayNameElementFound { date: id.clone() })?;
         // First line of the inner text
@@ -67,47 +129,52 @@ impl Lectionary {
             .expect("Will always have at least 1 line")
             .to_owned();
 
-        let readings = ParsedReadings::extract_from_container(container);
-        let reading_1 = readings.reading_1.ok_or_else(|| LectionaryHtmlError::MissingReading {
-            reading: ReadingName::Reading1,
-            date: id.clone(),
-        })?;
-        let reading_2 = readings.reading_2;
-        let resp_psalm = readings.resp_psalm.ok_or_else(|| LectionaryHtmlError::MissingReading {
-            reading: ReadingName::Psalm,
-            date: id.clone(),
-        })?;
-        let gospel = readings.gospel.ok_or_else(|| LectionaryHtmlError::MissingReading {
-            reading: ReadingName::Gospel,
-            date: id.clone(),
-        })?;
-        let alleluia = readings.allelia.ok_or_else(|| LectionaryHtmlError::MissingReading {
-            reading: ReadingName::Alleluia,
-            date: id.clone(),
-        })?;
-
-        Ok(Lectionary::new(id, day_name, reading_1, reading_2, resp_psalm, gospel, alleluia))
+        let readings = ParsedReadings::extract_from_container(container, selectors);
+        let lectionary = Lectionary::new(
+            id,
+            day_name,
+            readings.reading_1,
+            readings.reading_2,
+            readings.resp_psalm,
+            readings.gospel,
+            readings.allelia,
+        );
+
+        for reading in lectionary.missing_readings() {
+            if !lenient {
+                return Err(LectionaryHtmlError::MissingReading {
+                    reading,
+                    date: lectionary.get_id().clone(),
+                });
+            }
+            warn!("Could not find the {reading} for '{}'; Continuing without it", lectionary.get_id());
+        }
+
+        Ok(lectionary)
     }
 }
 
 impl Reading {
-    fn from_container(reading_container: ElementRef<'_>) -> Result<Self, ReadingHtmlError> {
-        let location = if let Some(location_elmt) = reading_container.select(reading_location_selector()).next() {
-            replace_entities(location_elmt.inner_html()).trim().to_owned()
+    fn from_container(reading_container: ElementRef<'_>, selectors: &PageSelectors) -> Result<Self, ReadingHtmlError> {
+        let location = if let Some(location_elmt) = reading_container.select(&selectors.reading_location).next() {
+            element_text(&location_elmt).trim().to_owned()
         } else {
             warn!("No location element found for reading");
             String::new()
         };
         let content = reading_container
-            .select(reading_content_selector())
+            .select(&selectors.reading_content)
             .next()
             .ok_or(ReadingHtmlError)?;
-        let full_text = element_to_plain_text(&content);
+        let mut footnotes = Vec::new();
+        let full_text = element_to_plain_text_collecting_footnotes(&content, &mut footnotes);
 
-        // Some reading will have alternates noted with "OR:". only take first
-        let text = full_text.split("OR:\n").next().expect("Split will always have at least 1 element");
+        // Some reading will have alternates noted with "OR:". Keep the first as the text and the second as the alternate
+        let mut forms = alternate_separator_pattern().splitn(&full_text, 2);
+        let text = forms.next().expect("Split will always have at least 1 element").trim().to_owned();
+        let alternate = forms.next().map(|alternate| alternate.trim().to_owned());
 
-        Ok(Reading::new(location, text.to_owned()))
+        Ok(Reading::new(location, text, alternate).with_footnotes(footnotes))
     }
 }
 
@@ -122,19 +189,19 @@ struct ParsedReadings {
 }
 
 impl ParsedReadings {
-    fn extract_from_container(container: ElementRef<'_>) -> Self {
+    fn extract_from_container(container: ElementRef<'_>, selectors: &PageSelectors) -> Self {
         let mut out = ParsedReadings::default();
 
-        let readings = container.select(readings_selector());
+        let readings = container.select(&selectors.readings);
         for reading_elmt in readings {
             debug!("parsing reading {:?} in container {:?}", reading_elmt, container);
             trace!("full reading elmnt: \n{}", reading_elmt.inner_html());
-            if let Some(name_elmnt) = reading_elmt.select(reading_name_selector()).next() {
+            if let Some(name_elmnt) = reading_elmt.select(&selectors.reading_name).next() {
                 debug!("Extracting reading name from reading name element {}", name_elmnt.html());
-                match ReadingName::try_from(replace_entities(name_elmnt.inner_html())) {
+                match ReadingName::try_from(normalize_reading_name(&element_text(&name_elmnt))) {
                     Ok(name) => {
                         info!("Idenitfied reading name as '{name}'. Parsing reading...");
-                        match Reading::from_container(reading_elmt) {
+                        match Reading::from_container(reading_elmt, selectors) {
                             Ok(reading) => match name {
                                 ReadingName::Reading1 => out.reading_1 = Some(reading),
                                 ReadingName::Reading2 => out.reading_2 = Some(reading),
@@ -146,7 +213,7 @@ impl ParsedReadings {
                         }
                     }
                     Err(e) => warn!("Unable to identify reading name: {e}"),
-                };
+                }
             } else {
                 error!("Found reading element with no name element");
             }
@@ -156,24 +223,61 @@ impl ParsedReadings {
     }
 }
 
+/// Prepares the text of a reading name element for `ReadingName::try_from`
+///
+/// Collapses runs of whitespace (including line breaks left by markup) to single spaces and removes trailing annotations,
+/// e.g. "Reading II (Year B)" or "Alleluia:" become "Reading II" and "Alleluia"
+fn normalize_reading_name(name: &str) -> String {
+    let collapsed = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let without_annotation = match collapsed.find(['(', '[', ':']) {
+        Some(index) => &collapsed[..index],
+        None => &collapsed,
+    };
+    without_annotation
+        .trim_end_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '\u{2013}' | '\u{2014}' | ',' | '.'))
+        .to_owned()
+}
+
 /// Converts an element to plain text, removing tags like '\<strong\>' while keeping the text within those elements
 fn element_to_plain_text(element: &ElementRef) -> String {
+    element_to_plain_text_collecting_footnotes(element, &mut Vec::new())
+}
+
+/// True if the element is a footnote marker: a superscript or a link to another part of the page
+fn is_footnote_marker(element: &scraper::node::Element) -> bool {
+    match element.name() {
+        "sup" => true,
+        "a" => element.attr("href").is_some_and(|href| href.starts_with('#')),
+        _ => false,
+    }
+}
+
+/// Like `element_to_plain_text`, but footnote markers are left out of the text and their text is added to `footnotes` instead
+fn element_to_plain_text_collecting_footnotes(element: &ElementRef, footnotes: &mut Vec<String>) -> String {
     let mut plain_text = String::new();
     for node in element.children() {
         match node.value() {
             Node::Text(text) => {
                 plain_text.push_str(text.trim_matches('\n'));
             }
+            Node::Element(element) if is_footnote_marker(element) => {
+                let elmt_ref = ElementRef::wrap(node).expect("Node of value Element will always wrap to ElementRef");
+                let marker = element_text(&elmt_ref).trim().to_owned();
+                debug!("Removing footnote marker '{marker}' from text");
+                if !marker.is_empty() {
+                    footnotes.push(marker);
+                }
+            }
             Node::Element(element) => match element.name() {
                 "br" => plain_text.push('\n'),
                 "p" => {
                     plain_text.push('\n');
                     let elmt_ref = ElementRef::wrap(node).expect("Node of value Element will always wrap to ElementRef");
-                    plain_text.push_str(&element_to_plain_text(&elmt_ref));
+                    plain_text.push_str(&element_to_plain_text_collecting_footnotes(&elmt_ref, footnotes));
                 }
                 _ => {
                     let elmt_ref = ElementRef::wrap(node).expect("Node of value Element will always wrap to ElementRef");
-                    plain_text.push_str(&element_to_plain_text(&elmt_ref));
+                    plain_text.push_str(&element_to_plain_text_collecting_footnotes(&elmt_ref, footnotes));
                 }
             },
             _ => {}
@@ -183,35 +287,22 @@ fn element_to_plain_text(element: &ElementRef) -> String {
     plain_text.trim().to_string()
 }
 
-/// `HashMap` of expected html entities with their replacement character
-fn html_entites() -> &'static HashMap<&'static str, &'static str> {
-    static HTML_ENTITIES: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
-    HTML_ENTITIES.get_or_init(|| {
-        let mut map = HashMap::new();
-        map.insert("&nbsp;", " ");
-        map.insert("&amp;", "&");
-        map.insert("&lt;", "<");
-        map.insert("&gt;", ">");
-        map.insert("&quot;", "\"");
-        map.insert("&#39;", "'");
-        map.insert("&apos;", "'");
-        map
-    })
+/// Use when getting the inner text of an html element
+///
+/// The text is decoded by the HTML parser, so every named and numeric entity is replaced. Non-breaking spaces are replaced
+/// with plain spaces so that names and locations can be compared
+fn element_text(element: &ElementRef) -> String {
+    element.text().collect::<String>().replace('\u{a0}', " ")
 }
 
-/// Use when getting the inner text of an html element
-pub fn replace_entities(mut value: String) -> String {
-    for (entity, target) in html_entites() {
-        if value.contains(entity) {
-            value = value.replace(entity, target);
-        }
-    }
-    value
+/// Gets the inner HTML of the main container, exactly as it is on the page. None if there is no container
+pub fn container_inner_html(doc: &Html, selectors: &PageSelectors) -> Option<String> {
+    doc.select(&selectors.container).next().map(|container| container.inner_html())
 }
 
 /// If html doc is a holiday page, returns the endpoint for the day reading
-pub fn get_holiday_day_reading_link(doc: &Html) -> Option<&str> {
-    if let Some(container) = doc.select(container_selector()).next() {
+pub fn get_holiday_day_reading_link<'a>(doc: &'a Html, selectors: &PageSelectors) -> Option<&'a str> {
+    if let Some(container) = doc.select(&selectors.container).next() {
         if let Some(day_link) = container.select(day_link_selector()).next() {
             info!("Found day reading for html document");
             Some(day_link.attr("href").expect("Found link must have href attribute"))
@@ -220,14 +311,37 @@ pub fn get_holiday_day_reading_link(doc: &Html) -> Option<&str> {
             None
         }
     } else {
-        error!(
-            "No main container ({}) found in html document",
-            container_selector().to_css_string()
-        );
+        error!("No main container ({}) found in html document", selectors.container.to_css_string());
         None
     }
 }
 
+/// A link to the readings for one of the Masses of a day
+#[derive(Debug, PartialEq, Eq)]
+pub struct MassLink<'a> {
+    /// Name of the Mass as shown on the page (e.g. "Vigil", "Mass during the Day")
+    pub label: String,
+    pub endpoint: &'a str,
+}
+
+/// If html doc is a holiday page, returns the links for each of the Masses in the order they are listed
+///
+/// Returns an empty `Vec` if the day only has one Mass
+pub fn get_mass_links<'a>(doc: &'a Html, selectors: &PageSelectors) -> Vec<MassLink<'a>> {
+    let Some(container) = doc.select(&selectors.container).next() else {
+        error!("No main container ({}) found in html document", selectors.container.to_css_string());
+        return Vec::new();
+    };
+    container
+        .select(mass_link_selector())
+        .filter_map(|link| {
+            let endpoint = link.attr("href")?;
+            let label = link.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ");
+            Some(MassLink { label, endpoint })
+        })
+        .collect()
+}
+
 /// Represents a failure to parse an HTML element into a Reading struct
 #[derive(thiserror::Error, Debug)]
 #[error("Missing Content from Reading")]
@@ -258,6 +372,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn configured_selectors_override_defaults() {
+        let config = SelectorsConfig {
+            readings: String::from("section.reading"),
+            reading_name: String::from("h3 >>> invalid"),
+            ..SelectorsConfig::default()
+        };
+        let selectors = PageSelectors::from_config(&config);
+        let html = Html::parse_fragment("<section class=\"reading\"><h3 class=\"name\">Gospel</h3></section>");
+        assert_eq!(1, html.select(&selectors.readings).count());
+        // Invalid selectors fall back to the default
+        assert_eq!("Gospel", html.select(&selectors.reading_name).next().unwrap().inner_html());
+        assert_eq!(DEFAULT_CONTAINER_SELECTOR, selectors.container.to_css_string());
+    }
+
+    #[test]
+    fn element_text_decodes_entities() {
+        let html = Html::parse_fragment(r"<a>Is&nbsp;40:1&#8211;5, 9&#x2d;11 &amp; <em>Ps</em> &lsquo;85&rsquo;&#8217;</a>");
+        assert_eq!(
+            "Is 40:1\u{2013}5, 9-11 & Ps \u{2018}85\u{2019}\u{2019}",
+            element_text(&html.root_element())
+        );
+    }
+
     #[test]
     fn element_to_plain_text_works_on_real() {
         let html = Html::parse_fragment(
@@ -319,21 +457,127 @@ and glorified God who had given such authority to men."#
     #[test]
     fn derialize_sunday_lectionary() {
         let html_doc = html_from_test_resource("sunday_or.html");
-        let lectionary = Lectionary::create_from_html(DateId::today(), &html_doc).unwrap();
+        let lectionary = Lectionary::create_from_html(DateId::today(), &html_doc, &PageSelectors::default()).unwrap();
         assert_eq!(&DateId::today(), lectionary.get_id());
         assert!(lectionary.get_reading_2().is_some());
     }
 
+    #[test]
+    fn alternate_reading_kept() {
+        let html_doc = html_from_test_resource("sunday_or.html");
+        let lectionary = Lectionary::create_from_html(DateId::today(), &html_doc, &PageSelectors::default()).unwrap();
+        let reading_2 = lectionary.get_reading_2().unwrap();
+        let alternate = reading_2.get_alternate().unwrap();
+        assert!(alternate.len() < reading_2.get_text().len());
+        assert!(!reading_2.get_text().contains("OR:"));
+    }
+
+    fn html_without_gospel() -> Html {
+        let mut path = PathBuf::from("tests");
+        path.push("resources");
+        path.push("sunday_or.html");
+        let html_string = std::fs::read_to_string(path).unwrap();
+        Html::parse_document(&html_string.replace(r#"<h3 class="name">Gospel</h3>"#, r#"<h3 class="name">Unknown</h3>"#))
+    }
+
+    #[test]
+    fn missing_reading_fails_strict() {
+        let result = Lectionary::create_from_html(DateId::today(), &html_without_gospel(), &PageSelectors::default());
+        assert!(matches!(
+            result,
+            Err(LectionaryHtmlError::MissingReading {
+                reading: ReadingName::Gospel,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn missing_reading_kept_lenient() {
+        let lectionary = Lectionary::create_from_html_lenient(DateId::today(), &html_without_gospel(), &PageSelectors::default()).unwrap();
+        assert!(lectionary.get_gospel().is_none());
+        assert!(lectionary.get_reading_1().is_some());
+        assert_eq!(vec![ReadingName::Gospel], lectionary.missing_readings());
+        assert!(!lectionary.is_complete());
+    }
+
+    #[test]
+    fn reading_names_normalized() {
+        assert_eq!("Reading II", normalize_reading_name("\n   Reading II\n   (Year B)\n"));
+        assert_eq!("Reading 2", normalize_reading_name("Reading   2 -"));
+        assert_eq!("Alleluia", normalize_reading_name("Alleluia:"));
+        assert_eq!("Alleluia See", normalize_reading_name("Alleluia See [Jn 14:23]"));
+        assert_eq!("Gospel", normalize_reading_name("Gospel"));
+    }
+
+    #[test]
+    fn solemnity_with_annotated_names_parses() {
+        let html_doc = html_from_test_resource("solemnity.html");
+        let lectionary = Lectionary::create_from_html(DateId::today(), &html_doc, &PageSelectors::default()).unwrap();
+        assert_eq!("The Most Holy Trinity", lectionary.get_day_name());
+        assert_eq!("Rom 8:14-17", lectionary.get_reading_2().unwrap().get_location());
+        assert_eq!("Dt 4:32-34, 39-40", lectionary.get_reading_1().unwrap().get_location());
+        assert_eq!("Cf. Rv 1:8", lectionary.get_alleluia().unwrap().get_location());
+    }
+
+    #[test]
+    fn lenten_day_parses() {
+        let html_doc = html_from_test_resource("lent.html");
+        let lectionary = Lectionary::create_from_html(DateId::today(), &html_doc, &PageSelectors::default()).unwrap();
+        assert_eq!("Tuesday of the First Week of Lent", lectionary.get_day_name());
+        assert!(lectionary.is_complete());
+        let acclamation = lectionary.get_alleluia().unwrap();
+        assert_eq!("Mt 4:4b", acclamation.get_location());
+        assert!(acclamation.get_text().starts_with("One does not live on bread alone,"));
+    }
+
+    #[test]
+    fn footnote_markers_removed_from_text() {
+        let html_doc = html_from_test_resource("footnotes.html");
+        let selectors = PageSelectors::default();
+        let reading_elmt = html_doc.select(&selectors.readings).next().unwrap();
+        let reading = Reading::from_container(reading_elmt, &selectors).unwrap();
+        assert_eq!(vec!["a", "b", "*"], reading.get_footnotes());
+        assert!(reading
+            .get_text()
+            .starts_with("In the beginning was the Word,\nand the Word was with God,"));
+        assert!(!reading.get_text().contains('*'));
+        assert!(!reading.get_text().contains("God,a"));
+    }
+
     #[test]
     fn find_holiday_reading_link() {
         let html_doc = html_from_test_resource("assumption.html");
-        let link = get_holiday_day_reading_link(&html_doc).unwrap();
+        let link = get_holiday_day_reading_link(&html_doc, &PageSelectors::default()).unwrap();
         assert_eq!("/bible/readings/081524-day.cfm", link);
     }
 
+    #[test]
+    fn find_all_mass_links() {
+        let html_doc = html_from_test_resource("christmas.html");
+        let labels: Vec<String> = get_mass_links(&html_doc, &PageSelectors::default())
+            .into_iter()
+            .map(|link| link.label)
+            .collect();
+        assert_eq!(
+            vec!["Vigil Mass", "Mass during the Night", "Mass at Dawn", "Mass during the Day"],
+            labels
+        );
+        assert_eq!(
+            "/bible/readings/122524-day.cfm",
+            get_holiday_day_reading_link(&html_doc, &PageSelectors::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn no_mass_links_on_regular_day() {
+        let html_doc = html_from_test_resource("sunday_or.html");
+        assert!(get_mass_links(&html_doc, &PageSelectors::default()).is_empty());
+    }
+
     #[test]
     fn dont_find_holiday_reading_link() {
         let html_doc = html_from_test_resource("sunday_or.html");
-        assert!(get_holiday_day_reading_link(&html_doc).is_none());
+        assert!(get_holiday_day_reading_link(&html_doc, &PageSelectors::default()).is_none());
     }
 }
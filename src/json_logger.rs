@@ -0,0 +1,126 @@
+//! Module for the `JsonLogger`, which writes each record as a single line of JSON for log aggregators
+
+use std::io::{Error, Write};
+use std::sync::Mutex;
+
+use chrono::{SecondsFormat, Utc};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+use simplelog::{Config, SharedLogger};
+
+/// Writes each record as a JSON object on its own line with the timestamp, level, target, and message
+///
+/// Like the `WriteLogger` from simplelog, only records from this crate are written
+pub struct JsonLogger<W: Write + Send + 'static> {
+    level: LevelFilter,
+    writable: Mutex<W>,
+}
+
+impl<W: Write + Send + 'static> JsonLogger<W> {
+    /// Allows to create a new logger, that can be independently used, no matter whats globally set.
+    /// Intended for use in a combined logger
+    ///
+    /// Returns a `Box`ed JSON Logger
+    // reason: match simplelog API
+    #[allow(clippy::unnecessary_box_returns)]
+    pub fn new(log_level: LevelFilter, writable: W) -> Box<JsonLogger<W>> {
+        Box::new(JsonLogger {
+            level: log_level,
+            writable: Mutex::new(writable),
+        })
+    }
+
+    fn try_log(&self, record: &Record<'_>) -> Result<(), Error> {
+        if self.enabled(record.metadata()) {
+            let mut writable = self.writable.lock().unwrap();
+            writeln!(writable, "{}", Self::to_json_line(record))?;
+            writable.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn to_json_line(record: &Record<'_>) -> String {
+        json!({
+            "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        })
+        .to_string()
+    }
+}
+
+impl<W: Write + Send + 'static> Log for JsonLogger<W> {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let _ = self.try_log(record);
+    }
+
+    fn flush(&self) {
+        let _ = self.writable.lock().unwrap().flush();
+    }
+}
+
+impl<W: Write + Send + 'static> SharedLogger for JsonLogger<W> {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::*;
+
+    #[test]
+    fn record_written_as_json_line() {
+        let logger = JsonLogger::new(LevelFilter::Info, Vec::new());
+        logger.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target(concat!(env!("CARGO_CRATE_NAME"), "::test"))
+                .args(format_args!("Quote \" and newline\n"))
+                .build(),
+        );
+        let written = String::from_utf8(logger.writable.into_inner().unwrap()).unwrap();
+        assert_eq!(1, written.lines().count());
+        let value: serde_json::Value = serde_json::from_str(&written).unwrap();
+        assert_eq!("WARN", value["level"]);
+        assert_eq!(concat!(env!("CARGO_CRATE_NAME"), "::test"), value["target"]);
+        assert_eq!("Quote \" and newline\n", value["message"]);
+        assert!(value["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn other_crates_and_levels_filtered() {
+        let logger = JsonLogger::new(LevelFilter::Info, Vec::new());
+        logger.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("sqlx::query")
+                .args(format_args!("other"))
+                .build(),
+        );
+        logger.log(
+            &Record::builder()
+                .level(Level::Debug)
+                .target(env!("CARGO_CRATE_NAME"))
+                .args(format_args!("too detailed"))
+                .build(),
+        );
+        assert!(logger.writable.into_inner().unwrap().is_empty());
+    }
+}
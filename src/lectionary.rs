@@ -2,29 +2,39 @@ use std::fmt::{self, Display, Formatter};
 
 use log::*;
 
+use crate::args::ReadingLength;
 use crate::date::DateId;
 use crate::db::{LectionaryDbEntity, ReadingRow};
+use crate::liturgy::Color;
+use crate::rank::Rank;
 
+/// The readings for a day
+///
+/// Every reading other than the second reading is normally present. They are only missing when the lectionary was parsed
+/// leniently from a page that is missing them
 #[derive(Debug)]
 pub struct Lectionary {
     id: DateId,
     day_name: String,
-    reading_1: Reading,
+    reading_1: Option<Reading>,
     reading_2: Option<Reading>,
-    resp_psalm: Reading,
-    gospel: Reading,
-    alleluia: Reading,
+    resp_psalm: Option<Reading>,
+    gospel: Option<Reading>,
+    alleluia: Option<Reading>,
+    /// URL of the page the readings were parsed from. Not stored in the database, so only known when retrieved from the web
+    source_url: Option<String>,
 }
 
 impl Lectionary {
+    #[must_use]
     pub fn new(
         id: DateId,
         day_name: String,
-        reading_1: Reading,
+        reading_1: Option<Reading>,
         reading_2: Option<Reading>,
-        resp_psalm: Reading,
-        gospel: Reading,
-        alleluia: Reading,
+        resp_psalm: Option<Reading>,
+        gospel: Option<Reading>,
+        alleluia: Option<Reading>,
     ) -> Self {
         Self {
             id,
@@ -34,29 +44,105 @@ impl Lectionary {
             resp_psalm,
             gospel,
             alleluia,
+            source_url: None,
         }
     }
 
+    #[must_use]
+    pub fn with_source_url(mut self, source_url: String) -> Self {
+        self.source_url = Some(source_url);
+        self
+    }
+
+    #[must_use]
     pub fn get_id(&self) -> &DateId {
         &self.id
     }
+    #[must_use]
     pub fn get_day_name(&self) -> &str {
         &self.day_name
     }
-    pub fn get_reading_1(&self) -> &Reading {
-        &self.reading_1
+    #[must_use]
+    pub fn get_reading_1(&self) -> Option<&Reading> {
+        self.reading_1.as_ref()
     }
-    pub fn get_resp_psalm(&self) -> &Reading {
-        &self.resp_psalm
+    #[must_use]
+    pub fn get_resp_psalm(&self) -> Option<&Reading> {
+        self.resp_psalm.as_ref()
     }
-    pub fn get_gospel(&self) -> &Reading {
-        &self.gospel
+    #[must_use]
+    pub fn get_gospel(&self) -> Option<&Reading> {
+        self.gospel.as_ref()
     }
+    #[must_use]
     pub fn get_reading_2(&self) -> Option<&Reading> {
         self.reading_2.as_ref()
     }
-    pub fn get_alleluia(&self) -> &Reading {
-        &self.alleluia
+    #[must_use]
+    pub fn get_alleluia(&self) -> Option<&Reading> {
+        self.alleluia.as_ref()
+    }
+    /// The URL the readings were retrieved from. None if they were read from the database
+    #[must_use]
+    pub fn get_source_url(&self) -> Option<&str> {
+        self.source_url.as_deref()
+    }
+
+    /// Every reading that is present, in the order they are read
+    pub fn readings(&self) -> impl Iterator<Item = (ReadingName, &Reading)> {
+        [
+            (ReadingName::Reading1, &self.reading_1),
+            (ReadingName::Psalm, &self.resp_psalm),
+            (ReadingName::Reading2, &self.reading_2),
+            (ReadingName::Alleluia, &self.alleluia),
+            (ReadingName::Gospel, &self.gospel),
+        ]
+        .into_iter()
+        .filter_map(|(name, reading)| reading.as_ref().map(|reading| (name, reading)))
+    }
+
+    /// Gets the names of the required readings (every reading but the second reading) that are missing
+    #[must_use]
+    pub fn missing_readings(&self) -> Vec<ReadingName> {
+        let required = [
+            (ReadingName::Reading1, &self.reading_1),
+            (ReadingName::Psalm, &self.resp_psalm),
+            (ReadingName::Gospel, &self.gospel),
+            (ReadingName::Alleluia, &self.alleluia),
+        ];
+        required
+            .into_iter()
+            .filter(|(_, reading)| reading.is_none())
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// True if none of the required readings are missing. Only complete lectionaries are stored in the database
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.missing_readings().is_empty()
+    }
+
+    /// Best-effort guess at the liturgical color, derived from the day name
+    #[must_use]
+    pub fn liturgical_color(&self) -> Color {
+        Color::from_day_name(&self.day_name)
+    }
+
+    /// Best-effort guess at the rank of the day (e.g. solemnity, memorial), derived from the day name
+    #[must_use]
+    pub fn rank(&self) -> Option<Rank> {
+        Rank::from_day_name(&self.day_name)
+    }
+}
+
+/// One-line summary for logs: the date, the name of the day, and the short labels of the readings present
+///
+/// e.g. "071424 Fifteenth Sunday in Ordinary Time [R1,Ps,R2,All,Gsp]"
+impl Display for Lectionary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let labels: Vec<&str> = self.readings().map(|(name, _)| name.short_str()).collect();
+        write!(f, "{} {} [{}]", self.id, self.day_name, labels.join(","))
     }
 }
 
@@ -65,16 +151,17 @@ impl From<LectionaryDbEntity> for Lectionary {
         Lectionary {
             id: entity.lect_row.id,
             day_name: entity.lect_row.name,
-            reading_1: Reading::from(entity.first_reading_row),
+            reading_1: Some(Reading::from(entity.first_reading_row)),
             reading_2: entity.second_reading_row.map(Reading::from),
-            resp_psalm: Reading::from(entity.psalm_row),
-            gospel: Reading::from(entity.gospel_row),
-            alleluia: Reading::from(entity.alleluia_row),
+            resp_psalm: Some(Reading::from(entity.psalm_row)),
+            gospel: Some(Reading::from(entity.gospel_row)),
+            alleluia: Some(Reading::from(entity.alleluia_row)),
+            source_url: None,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum ReadingName {
     Reading1,
     Reading2,
@@ -88,6 +175,11 @@ impl ReadingName {
     const PSALM: &'static str = "Responsorial Psalm";
     const GOSPEL: &'static str = "Gospel";
     const ALLELUIA: &'static str = "Alleluia";
+    const READING1_SHORT: &'static str = "R1";
+    const READING2_SHORT: &'static str = "R2";
+    const PSALM_SHORT: &'static str = "Ps";
+    const GOSPEL_SHORT: &'static str = "Gsp";
+    const ALLELUIA_SHORT: &'static str = "All";
 
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -98,6 +190,17 @@ impl ReadingName {
             Self::Alleluia => Self::ALLELUIA,
         }
     }
+
+    /// Abbreviated name, for compact headings
+    pub fn short_str(&self) -> &'static str {
+        match self {
+            Self::Reading1 => Self::READING1_SHORT,
+            Self::Reading2 => Self::READING2_SHORT,
+            Self::Psalm => Self::PSALM_SHORT,
+            Self::Gospel => Self::GOSPEL_SHORT,
+            Self::Alleluia => Self::ALLELUIA_SHORT,
+        }
+    }
 }
 impl Display for ReadingName {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
@@ -115,7 +218,10 @@ impl TryFrom<String> for ReadingName {
             Self::READING2 | "Reading 2" => Ok(Self::Reading2),
             Self::PSALM | "Responsorial" => Ok(Self::Psalm),
             Self::GOSPEL => Ok(Self::Gospel),
-            Self::ALLELUIA | "Alleluia See" => Ok(Self::Alleluia),
+            // During Lent the gospel acclamation is not an alleluia and has a different label
+            Self::ALLELUIA | "Alleluia See" | "Verse Before the Gospel" | "Verse before the Gospel" | "Gospel Acclamation" => {
+                Ok(Self::Alleluia)
+            }
             _ => Err(Self::Error::from(value)),
         }
     }
@@ -138,25 +244,160 @@ impl From<String> for ReadingNameFromStringError {
 pub struct Reading {
     location: String,
     text: String,
+    /// Some readings have an alternate form (noted with "OR:"), typically a shorter version of the same reading
+    alternate: Option<String>,
+    /// Footnote markers (e.g. superscript letters) removed from the text, in the order they appeared
+    footnotes: Vec<String>,
 }
 impl Reading {
-    pub fn new(location: String, text: String) -> Self {
-        Self { location, text }
+    #[must_use]
+    pub fn new(location: String, text: String, alternate: Option<String>) -> Self {
+        Self {
+            location,
+            text,
+            alternate,
+            footnotes: Vec::new(),
+        }
     }
 
+    #[must_use]
+    pub fn with_footnotes(mut self, footnotes: Vec<String>) -> Self {
+        self.footnotes = footnotes;
+        self
+    }
+
+    #[must_use]
     pub fn get_location(&self) -> &str {
         &self.location
     }
 
+    /// Gets the text of the reading. If there is an alternate, this is the one listed first
+    #[must_use]
     pub fn get_text(&self) -> &str {
         &self.text
     }
+
+    /// Number of whitespace separated words in the text
+    #[must_use]
+    pub fn word_count(&self) -> usize {
+        self.text.split_whitespace().count()
+    }
+
+    /// Number of characters in the text, including whitespace
+    #[must_use]
+    pub fn char_count(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    #[must_use]
+    pub fn get_alternate(&self) -> Option<&str> {
+        self.alternate.as_deref()
+    }
+
+    #[must_use]
+    pub fn get_footnotes(&self) -> &[String] {
+        &self.footnotes
+    }
+
+    /// Gets either the longer or the shorter form of the reading
+    ///
+    /// If there is no alternate, the text is returned regardless of the length requested
+    #[must_use]
+    pub fn get_text_of_length(&self, length: ReadingLength) -> &str {
+        match &self.alternate {
+            None => &self.text,
+            Some(alternate) => {
+                let (long, short) = if alternate.len() > self.text.len() {
+                    (alternate, &self.text)
+                } else {
+                    (&self.text, alternate)
+                };
+                match length {
+                    ReadingLength::Long => long,
+                    ReadingLength::Short => short,
+                }
+            }
+        }
+    }
 }
 impl From<ReadingRow> for Reading {
     fn from(row: ReadingRow) -> Self {
         Self {
             location: row.location,
             text: row.content,
+            alternate: row.alternate,
+            footnotes: row
+                .footnotes
+                .map(|footnotes| footnotes.lines().map(str::to_owned).collect())
+                .unwrap_or_default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_of_length_chooses_by_length() {
+        let reading = Reading::new(String::new(), String::from("short"), Some(String::from("much longer")));
+        assert_eq!("much longer", reading.get_text_of_length(ReadingLength::Long));
+        assert_eq!("short", reading.get_text_of_length(ReadingLength::Short));
+    }
+
+    #[test]
+    fn short_labels() {
+        assert_eq!("R1", ReadingName::Reading1.short_str());
+        assert_eq!("R2", ReadingName::Reading2.short_str());
+        assert_eq!("Ps", ReadingName::Psalm.short_str());
+        assert_eq!("Gsp", ReadingName::Gospel.short_str());
+        assert_eq!("All", ReadingName::Alleluia.short_str());
+    }
+
+    #[test]
+    fn word_and_char_counts() {
+        let text = String::from("In the beginning  was the Word,\nand the Word was with God,\n\n and the Word was God.\n");
+        let reading = Reading::new(String::new(), text, None);
+        assert_eq!(17, reading.word_count());
+        assert_eq!(83, reading.char_count());
+
+        let empty = Reading::new(String::new(), String::new(), None);
+        assert_eq!(0, empty.word_count());
+        assert_eq!(0, empty.char_count());
+    }
+
+    #[test]
+    fn lenten_acclamation_is_alleluia() {
+        assert_eq!(
+            ReadingName::Alleluia,
+            ReadingName::try_from(String::from("Verse Before the Gospel")).unwrap()
+        );
+        assert_eq!(
+            ReadingName::Alleluia,
+            ReadingName::try_from(String::from(" Verse before the Gospel ")).unwrap()
+        );
+        assert_eq!(ReadingName::Alleluia, ReadingName::try_from(String::from("Alleluia See")).unwrap());
+    }
+
+    #[test]
+    fn display_summary() {
+        let reading = || Some(Reading::new(String::new(), String::from("text"), None));
+        let id = DateId::checked_from_str("071424").unwrap();
+        let name = String::from("Fifteenth Sunday in Ordinary Time");
+        let lectionary = Lectionary::new(id.clone(), name.clone(), reading(), reading(), reading(), reading(), reading());
+        assert_eq!(
+            "071424 Fifteenth Sunday in Ordinary Time [R1,Ps,R2,All,Gsp]",
+            lectionary.to_string()
+        );
+
+        let weekday = Lectionary::new(id, name, reading(), None, reading(), None, None);
+        assert_eq!("071424 Fifteenth Sunday in Ordinary Time [R1,Ps]", weekday.to_string());
+    }
+
+    #[test]
+    fn text_of_length_without_alternate() {
+        let reading = Reading::new(String::new(), String::from("only"), None);
+        assert_eq!("only", reading.get_text_of_length(ReadingLength::Long));
+        assert_eq!("only", reading.get_text_of_length(ReadingLength::Short));
+    }
+}
@@ -8,7 +8,23 @@ mod config;
 mod date;
 mod db;
 mod display;
+mod export;
 mod html;
+mod json_logger;
 mod lectionary;
+mod liturgy;
 mod orchestration;
 mod path;
+mod rank;
+mod scripture;
+mod source;
+
+pub use client::{WebClient, WebGetError};
+pub use date::{DateFormat, DateId, InvalidDateError, InvalidDateFormatError, InvalidDateIdError};
+pub use db::DatabaseHandle;
+pub use html::PageSelectors;
+pub use lectionary::{Lectionary, Reading};
+pub use orchestration::{
+    ensure_stored, ensure_stored_fresh, fetch, fetch_cached, parse_file, DbUpdateError, ParseFileError, RetrievalError, StoreOutcome,
+};
+pub use rank::Rank;
@@ -0,0 +1,159 @@
+//! Heuristics for deriving liturgical information from the name of the day
+
+use std::fmt::{self, Display, Formatter};
+
+/// The liturgical color of a day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Green,
+    Violet,
+    White,
+    Red,
+    Rose,
+    /// The color could not be determined from the day name
+    Unknown,
+}
+
+/// Keywords (lowercase) that identify the color of a day. Checked in order, so more specific keywords must come first
+///
+/// e.g. "Third Sunday of Advent" must be matched as rose before "advent" is matched as violet
+const COLOR_KEYWORDS: [(&str, Color); 38] = [
+    // Gaudete and Laetare Sundays
+    ("third sunday of advent", Color::Rose),
+    ("fourth sunday of lent", Color::Rose),
+    // Feasts of apostles that are not celebrated in red
+    ("conversion of saint paul", Color::White),
+    ("chair of saint peter", Color::White),
+    ("saint john, apostle", Color::White),
+    // Passion, Holy Spirit, and martyrs
+    ("palm sunday", Color::Red),
+    ("passion of the lord", Color::Red),
+    ("good friday", Color::Red),
+    ("pentecost", Color::Red),
+    ("holy cross", Color::Red),
+    ("martyr", Color::Red),
+    ("apostle", Color::Red),
+    ("evangelist", Color::Red),
+    // Solemnities and feasts of the Lord and of Mary
+    ("lord's supper", Color::White),
+    ("christmas", Color::White),
+    ("nativity", Color::White),
+    ("holy family", Color::White),
+    ("mother of god", Color::White),
+    ("epiphany", Color::White),
+    ("baptism of the lord", Color::White),
+    ("presentation of the lord", Color::White),
+    ("annunciation", Color::White),
+    ("easter", Color::White),
+    ("ascension", Color::White),
+    ("trinity", Color::White),
+    ("body and blood", Color::White),
+    ("sacred heart", Color::White),
+    ("transfiguration", Color::White),
+    ("assumption", Color::White),
+    ("immaculate", Color::White),
+    ("all saints", Color::White),
+    ("king of the universe", Color::White),
+    // Seasons
+    ("ash wednesday", Color::Violet),
+    ("holy week", Color::Violet),
+    ("advent", Color::Violet),
+    ("lent", Color::Violet),
+    ("ordinary time", Color::Green),
+    // Remaining saints (non-martyrs)
+    ("saint", Color::White),
+];
+
+impl Color {
+    /// Makes a best-effort guess at the liturgical color from the name of the day
+    ///
+    /// Returns `Color::Unknown` if none of the known keywords are present
+    pub fn from_day_name(day_name: &str) -> Self {
+        let lowercase = day_name.to_lowercase();
+        COLOR_KEYWORDS
+            .iter()
+            .find(|(keyword, _)| contains_word_start(&lowercase, keyword))
+            .map_or(Self::Unknown, |(_, color)| *color)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Green => "Green",
+            Self::Violet => "Violet",
+            Self::White => "White",
+            Self::Red => "Red",
+            Self::Rose => "Rose",
+            Self::Unknown => "Unknown",
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// True if `keyword` appears in `text` at the start of a word (e.g. "lent" is found in "Lenten" but not in "Valentine")
+fn contains_word_start(text: &str, keyword: &str) -> bool {
+    text.match_indices(keyword)
+        .any(|(index, _)| !text[..index].chars().next_back().is_some_and(char::is_alphabetic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordinary_time_is_green() {
+        assert_eq!(Color::Green, Color::from_day_name("Fifteenth Sunday in Ordinary Time"));
+        assert_eq!(
+            Color::Green,
+            Color::from_day_name("Tuesday of the Nineteenth Week in Ordinary Time")
+        );
+    }
+
+    #[test]
+    fn penitential_seasons_are_violet() {
+        assert_eq!(Color::Violet, Color::from_day_name("Ash Wednesday"));
+        assert_eq!(Color::Violet, Color::from_day_name("Monday of the First Week of Advent"));
+        assert_eq!(Color::Violet, Color::from_day_name("Second Sunday of Lent"));
+        assert_eq!(Color::Violet, Color::from_day_name("Monday of Holy Week"));
+    }
+
+    #[test]
+    fn gaudete_and_laetare_are_rose() {
+        assert_eq!(Color::Rose, Color::from_day_name("Third Sunday of Advent"));
+        assert_eq!(Color::Rose, Color::from_day_name("Fourth Sunday of Lent"));
+    }
+
+    #[test]
+    fn martyrs_and_passion_are_red() {
+        assert_eq!(Color::Red, Color::from_day_name("Memorial of Saint Lawrence, deacon and martyr"));
+        assert_eq!(Color::Red, Color::from_day_name("Palm Sunday of the Passion of the Lord"));
+        assert_eq!(Color::Red, Color::from_day_name("Pentecost Sunday"));
+        assert_eq!(Color::Red, Color::from_day_name("Feast of Saint Andrew, Apostle"));
+    }
+
+    #[test]
+    fn solemnities_are_white() {
+        assert_eq!(
+            Color::White,
+            Color::from_day_name("Solemnity of the Assumption of the Blessed Virgin Mary")
+        );
+        assert_eq!(Color::White, Color::from_day_name("The Nativity of the Lord (Christmas)"));
+        assert_eq!(Color::White, Color::from_day_name("Third Sunday of Easter"));
+        assert_eq!(Color::White, Color::from_day_name("Feast of the Conversion of Saint Paul, Apostle"));
+    }
+
+    #[test]
+    fn keywords_match_word_start() {
+        assert_eq!(Color::White, Color::from_day_name("Memorial of Saint Valentine"));
+    }
+
+    #[test]
+    fn unrecognized_is_unknown() {
+        assert_eq!(Color::Unknown, Color::from_day_name("Daily Readings"));
+        assert_eq!(Color::Unknown, Color::from_day_name(""));
+    }
+}
@@ -1,5 +1,9 @@
-use std::fs::File;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs::{self, File};
 use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use log::*;
 use simplelog::{
@@ -9,32 +13,93 @@ use simplelog::{
 
 use crate::{
     colorful_logger::{ColorConfig, ColorfulLogger},
+    config::{Config, LogFormat, LoggingConfig},
+    json_logger::JsonLogger,
     path::{self, PathError},
 };
 
+/// Target of the records logged by `log_timing`, which are only written if `LoggingOptions::timings` is set
+const TIMINGS_TARGET: &str = concat!(env!("CARGO_CRATE_NAME"), "::timings");
+
+/// Logs the time since `start` taken by `step` at info level
+pub(crate) fn log_timing(step: impl Display, start: Instant) {
+    info!(target: TIMINGS_TARGET, "Timing: {step} took {:.1} ms", start.elapsed().as_secs_f64() * 1000.0);
+}
+
 /// Initializes a combined logger included a terminal logger and a file logger. If file logger fails to be created, still initializes the terminal logger
-pub fn init_logger(options: LoggingOptions) {
+///
+/// Reads the logging settings from the config file at `config_path`, or the default config file if not given. Since the logger
+/// isn't initialized yet, anything logged while reading the config is lost
+///
+/// If `no_log_file` is set, only the terminal logger is initialized
+pub fn init_logger(options: &LoggingOptions, config_path: Option<&Path>) {
     let mut loggers: Vec<Box<dyn SharedLogger>> = Vec::new();
     loggers.push(color_logger(options));
-    match file_logger() {
+    if options.no_log_file {
+        init_combined(loggers, options.timings);
+        return;
+    }
+    let logging_config = Config::from_file_or_default(config_path).logging;
+    match file_logger(logging_config, options.level, options.log_file.as_deref()) {
         Ok(file_logger) => {
             loggers.push(file_logger);
-            init_combined(loggers);
+            init_combined(loggers, options.timings);
         }
         Err(e) => {
-            init_combined(loggers);
+            init_combined(loggers, options.timings);
             error!("Failed to initialize file log: {e}");
         }
     }
 }
 
-/// Tries to initialize the given loggers into a combined logger
-fn init_combined(loggers: Vec<Box<dyn SharedLogger>>) {
+/// Tries to initialize the given loggers into a combined logger. Unless `timings` is true, records from `log_timing` are left out
+fn init_combined(loggers: Vec<Box<dyn SharedLogger>>, timings: bool) {
+    let loggers = if timings {
+        loggers
+    } else {
+        loggers
+            .into_iter()
+            .map(|logger| Box::new(WithoutTimings(logger)) as Box<dyn SharedLogger>)
+            .collect()
+    };
     if CombinedLogger::init(loggers).is_err() {
         error!("Tried to initialize logger after already initialized");
     }
 }
 
+/// Wraps a logger to leave out the records logged by `log_timing`
+struct WithoutTimings(Box<dyn SharedLogger>);
+
+impl Log for WithoutTimings {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() != TIMINGS_TARGET && self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if record.target() != TIMINGS_TARGET {
+            self.0.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+impl SharedLogger for WithoutTimings {
+    fn level(&self) -> LevelFilter {
+        self.0.level()
+    }
+
+    fn config(&self) -> Option<&simplelog::Config> {
+        self.0.config()
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        self
+    }
+}
+
 /// Creates an uninitialized terminal logger
 ///
 /// Unused but keeping it around for reference
@@ -56,21 +121,64 @@ fn _terminal_logger() -> Box<TermLogger> {
 
 // Reason: CombinedLogger::init needs boxed values
 #[allow(clippy::unnecessary_box_returns)]
-fn color_logger(options: LoggingOptions) -> Box<ColorfulLogger> {
+fn color_logger(options: &LoggingOptions) -> Box<ColorfulLogger> {
     let color_config = if options.no_color {
         ColorConfig::no_color()
     } else {
         ColorConfig::default()
     };
-    ColorfulLogger::new(LevelFilter::Warn, color_config)
+    ColorfulLogger::new(options.level, color_config)
 }
 
-/// Creates an uninitialized file logger
-fn file_logger() -> Result<Box<WriteLogger<File>>, FileLoggerError> {
-    let path = path::create_and_get_log_path()?;
-    let file = File::options().create(true).append(true).open(path)?;
-    Ok(WriteLogger::new(
+/// The configured file level applies when the terminal is at the default level (Warn)
+///
+/// Each step the terminal level is raised or lowered by --quiet or --verbose shifts the file level by the same amount.
+/// A configured level of Off always stays Off
+fn file_level(configured_level: LevelFilter, terminal_level: LevelFilter) -> LevelFilter {
+    const LEVELS: [LevelFilter; 6] = [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
         LevelFilter::Debug,
+        LevelFilter::Trace,
+    ];
+    if configured_level == LevelFilter::Off {
+        return LevelFilter::Off;
+    }
+    let index_of = |level: LevelFilter| LEVELS.iter().position(|l| *l == level).expect("All levels are in array");
+    let shifted = (index_of(configured_level) + index_of(terminal_level)).saturating_sub(index_of(LevelFilter::Warn));
+    LEVELS[shifted.clamp(1, LEVELS.len() - 1)]
+}
+
+/// Creates an uninitialized file logger, writing either human readable lines or JSON lines depending on the config
+///
+/// Writes to `log_file` if given, otherwise to the default path. Rotates the existing log file first if it has grown too large
+fn file_logger(
+    config: LoggingConfig,
+    terminal_level: LevelFilter,
+    log_file: Option<&Path>,
+) -> Result<Box<dyn SharedLogger>, FileLoggerError> {
+    let path = match log_file {
+        Some(log_file) => path::create_and_resolve_path(log_file)?,
+        None => path::create_and_get_log_path()?,
+    };
+    if config.max_file_size > 0 {
+        rotate_if_too_large(&path, u64::from(config.max_file_size) * 1024 * 1024, config.max_backups)?;
+    }
+    let file = File::options().create(true).append(true).open(path)?;
+    let level = file_level(config.file_level, terminal_level);
+    match config.file_format {
+        LogFormat::Text => Ok(text_file_logger(level, file)),
+        LogFormat::Json => Ok(JsonLogger::new(level, file)),
+    }
+}
+
+// Reason: CombinedLogger::init needs boxed values
+#[allow(clippy::unnecessary_box_returns)]
+fn text_file_logger(level: LevelFilter, file: File) -> Box<WriteLogger<File>> {
+    WriteLogger::new(
+        level,
         ConfigBuilder::new()
             .set_time_format_custom(format_description!(
                 "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z"
@@ -82,12 +190,56 @@ fn file_logger() -> Result<Box<WriteLogger<File>>, FileLoggerError> {
             .add_filter_allow_str(env!("CARGO_CRATE_NAME"))
             .build(),
         file,
-    ))
+    )
 }
 
-#[derive(Copy, Clone)]
+/// If the log file is larger than `max_bytes`, renames it to '.log.1', shifting existing backups up by one
+///
+/// Backups beyond `max_backups` are deleted. If `max_backups` is 0, the log file is simply removed
+fn rotate_if_too_large(path: &Path, max_bytes: u64, max_backups: u32) -> Result<(), io::Error> {
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if size <= max_bytes {
+        return Ok(());
+    }
+
+    if max_backups == 0 {
+        return fs::remove_file(path);
+    }
+    let oldest = backup_path(path, max_backups);
+    if oldest.exists() {
+        fs::remove_file(oldest)?;
+    }
+    for number in (1..max_backups).rev() {
+        let backup = backup_path(path, number);
+        if backup.exists() {
+            fs::rename(backup, backup_path(path, number + 1))?;
+        }
+    }
+    fs::rename(path, backup_path(path, 1))
+}
+
+/// Path of the numbered backup for the log file (e.g. 'lectio-diei.log.2')
+fn backup_path(path: &Path, number: u32) -> PathBuf {
+    let mut backup = OsString::from(path.as_os_str());
+    backup.push(format!(".{number}"));
+    PathBuf::from(backup)
+}
+
+#[derive(Clone)]
 pub struct LoggingOptions {
     pub no_color: bool,
+    /// Level for the terminal logger. The file logger level is shifted along with this
+    pub level: LevelFilter,
+    /// Writes the log file here instead of the default path
+    pub log_file: Option<PathBuf>,
+    /// Skips the log file entirely
+    pub no_log_file: bool,
+    /// Logs how long each step of retrieving readings takes (e.g. the request, parsing, and database queries) at info level
+    pub timings: bool,
 }
 
 /// Represents a failure to open a file for the purpose of writing logs to it
@@ -98,3 +250,41 @@ enum FileLoggerError {
     #[error("Failed to open log file: ({0})")]
     FileOpenError(#[from] io::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn file_level_follows_terminal() {
+        assert_eq!(LevelFilter::Debug, file_level(LevelFilter::Debug, LevelFilter::Warn));
+        assert_eq!(LevelFilter::Info, file_level(LevelFilter::Debug, LevelFilter::Error));
+        assert_eq!(LevelFilter::Trace, file_level(LevelFilter::Debug, LevelFilter::Trace));
+        assert_eq!(LevelFilter::Error, file_level(LevelFilter::Error, LevelFilter::Error));
+        assert_eq!(LevelFilter::Off, file_level(LevelFilter::Off, LevelFilter::Trace));
+    }
+
+    #[test]
+    fn rotates_large_log() {
+        let dir = env::temp_dir().join(concat!(env!("CARGO_PKG_NAME"), "-rotation-test"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("test.log");
+
+        fs::write(&log, "first").unwrap();
+        rotate_if_too_large(&log, 1, 2).unwrap();
+        fs::write(&log, "second").unwrap();
+        rotate_if_too_large(&log, 1, 2).unwrap();
+        fs::write(&log, "third").unwrap();
+        rotate_if_too_large(&log, 1, 2).unwrap();
+
+        assert!(!log.exists());
+        assert_eq!("third", fs::read_to_string(backup_path(&log, 1)).unwrap());
+        assert_eq!("second", fs::read_to_string(backup_path(&log, 2)).unwrap());
+        assert!(!backup_path(&log, 3).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,35 +1,73 @@
 use std::process::ExitCode;
 
 use clap::Parser;
-use lectio_diei::args::{Arguments, Command};
+use lectio_diei::args::{Arguments, Command, ErrorFormat};
 use lectio_diei::commands::{self, ApplicationError};
 use lectio_diei::logging::{self, LoggingOptions};
 use log::*;
 
 #[tokio::main]
 async fn main() -> ExitCode {
-    if let Err(e) = run().await {
-        error!("{}", e);
+    let args = Arguments::parse();
+    let error_format = args.error_format;
+    if let Err(e) = run(args).await {
+        match error_format {
+            ErrorFormat::Text => error!("{}", e),
+            ErrorFormat::Json => eprintln!("{}", e.to_json()),
+        }
         return ExitCode::from(e.exit_code());
     }
 
     ExitCode::SUCCESS
 }
 
-async fn run() -> Result<(), ApplicationError> {
-    let args = Arguments::parse();
+async fn run(mut args: Arguments) -> Result<(), ApplicationError> {
+    let command = args.command.unwrap_or_default();
+
+    // Must be resolved before the logger reads the config, but the logger must exist before the error can be reported
+    let config_path_result = args
+        .common_args
+        .config
+        .as_deref()
+        .map(|path| commands::use_config_path(path, &command))
+        .transpose();
+    if let Ok(resolved) = &config_path_result {
+        args.common_args.config.clone_from(resolved);
+    }
 
-    logging::init_logger(LoggingOptions {
-        no_color: args.common_args.no_color,
-    });
+    logging::init_logger(
+        &LoggingOptions {
+            no_color: args.common_args.no_color,
+            level: args.common_args.log_level(),
+            log_file: args.log_file.clone(),
+            no_log_file: args.no_log_file,
+            timings: args.timings,
+        },
+        args.common_args.config.as_deref(),
+    );
+    config_path_result?;
+    let config_path = args.common_args.config.clone();
+    commands::create_missing_config(args.no_create_config, config_path.as_deref(), &command);
 
-    match args.command {
+    match command {
         Command::Display {
             date,
             readings,
             formatting,
-        } => commands::display(date, readings, formatting, args.common_args).await,
-        Command::Db { command } => commands::handle_db_command(command).await,
-        Command::Config { command } => commands::handle_config_command(command),
+            retrieval,
+            length,
+            output,
+        } => commands::display(date, readings, formatting, retrieval, length, output, args.common_args).await,
+        Command::Db { command } => commands::handle_db_command(command, args.common_args).await,
+        Command::Config { command } => commands::handle_config_command(command, config_path.as_deref()),
+        Command::Completions { shell } => {
+            commands::print_completions(shell);
+            Ok(())
+        }
+        Command::Doctor { offline } => {
+            commands::doctor(config_path.as_deref(), args.log_file.as_deref(), args.no_log_file, offline).await;
+            Ok(())
+        }
+        Command::Man { dir } => commands::write_man_pages(&dir).map_err(ApplicationError::from),
     }
 }
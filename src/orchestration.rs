@@ -1,49 +1,186 @@
 use core::fmt;
 use std::fmt::Display;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
+use chrono::{TimeDelta, Utc};
 use log::*;
+use scraper::Html;
 
 use crate::client::{WebClient, WebGetError};
 use crate::date::DateId;
-use crate::db::{DatabaseGetError, DatabaseHandle, DatabaseInitError};
-use crate::display::DisplaySettings;
+use crate::db::{self, DatabaseGetError, DatabaseHandle, DatabaseInitError};
+use crate::html::{LectionaryHtmlError, PageSelectors};
 use crate::lectionary::Lectionary;
+use crate::logging;
 
-/// Retrieves lectionary from db and web and attempts to store it before printing to STDOUT
-pub async fn retrieve_and_display(date_id: DateId, settings: DisplaySettings) -> Result<(), RetrievalError> {
-    let lectionary = retrieve_lectionary(date_id).await?;
-    lectionary.pretty_print(&settings);
-    Ok(())
+/// Retrieves a lectionary directly from the web with `client`, without touching the database
+///
+/// # Errors
+/// Returns a `RetrievalError` if the lectionary could not be retrieved or parsed
+pub async fn fetch(date_id: DateId, client: &WebClient) -> Result<Lectionary, RetrievalError> {
+    client.get_for_date_id(date_id).await.map_err(RetrievalError::from)
+}
+
+/// Retrieves a lectionary from the database. If it isn't present, retrieves it from the web with `client` and stores it in the
+/// database
+///
+/// # Errors
+/// Returns a `RetrievalError` if the lectionary could not be retrieved from either the database or the web
+pub async fn fetch_cached(date_id: DateId, db: &DatabaseHandle, client: &WebClient) -> Result<Lectionary, RetrievalError> {
+    retrieve_and_store(date_id, db, client, false, true).await
+}
+
+/// Retrieves the lectionary for one of the Masses of a day directly from the web, without touching the database
+///
+/// The database only holds the Mass during the Day, so other Masses are never stored
+pub async fn fetch_mass(date_id: DateId, mass: &str, client: &WebClient) -> Result<Lectionary, RetrievalError> {
+    client
+        .get_for_date_id_and_mass(date_id, Some(mass))
+        .await
+        .map_err(RetrievalError::from)
+}
+
+/// Parses a lectionary from a readings page saved to the file at `path`, or from STDIN if `path` is "-"
+///
+/// Never touches the web or the database. If lenient is true, missing readings are left out instead of failing
+///
+/// # Errors
+/// Returns a `ParseFileError` if the file can't be read or is not a readings page
+pub fn parse_file(date_id: DateId, path: &Path, selectors: &PageSelectors, lenient: bool) -> Result<Lectionary, ParseFileError> {
+    let read_error = |source| ParseFileError::Read {
+        path: path.to_owned(),
+        source,
+    };
+    let html = if path == Path::new("-") {
+        let mut html = String::new();
+        io::stdin().read_to_string(&mut html).map_err(read_error)?;
+        html
+    } else {
+        std::fs::read_to_string(path).map_err(read_error)?
+    };
+    parse_html(date_id, &html, selectors, lenient)
+}
+
+fn parse_html(date_id: DateId, html: &str, selectors: &PageSelectors, lenient: bool) -> Result<Lectionary, ParseFileError> {
+    let document = Html::parse_document(html);
+    let lectionary = if lenient {
+        Lectionary::create_from_html_lenient(date_id, &document, selectors)?
+    } else {
+        Lectionary::create_from_html(date_id, &document, selectors)?
+    };
+    Ok(lectionary)
 }
 
-/// Attempts to retrieve Lectionary, first from DB and then from web
-async fn retrieve_lectionary(date_id: DateId) -> Result<Lectionary, RetrievalError> {
-    match DatabaseHandle::new().await {
-        Ok(db) => retrieve_and_store(date_id, &db).await,
-        //TODO handle case where db init fails
-        Err(e) => Err(RetrievalError::from(DatabaseError::from(e))),
+/// Like `retrieve_lectionary`, but if there is no readings page for the date, tries each previous day up to `max_days_back` days
+///
+/// The id of the returned lectionary is the date that was actually used
+pub async fn retrieve_nearest_lectionary(
+    date_id: DateId,
+    client: &WebClient,
+    offline: bool,
+    ephemeral: bool,
+    store: bool,
+    max_days_back: u16,
+    config_path: Option<&Path>,
+) -> Result<Lectionary, RetrievalError> {
+    let mut current = date_id;
+    for _ in 0..max_days_back {
+        match retrieve_lectionary(current.clone(), client, offline, ephemeral, store, config_path).await {
+            Err(e) if e.is_missing_page() => {
+                let previous = current.previous_day();
+                warn!("No readings found for '{current}' ({e}); Trying the previous day '{previous}'");
+                current = previous;
+            }
+            result => return result,
+        }
+    }
+    retrieve_lectionary(current, client, offline, ephemeral, store, config_path).await
+}
+
+/// Attempts to retrieve Lectionary, first from DB and then from web with `client`
+///
+/// If the database can't be initialized, retrieves directly from the web without storing.
+/// If offline is true, never attempts to retrieve from the web.
+/// If the client is lenient, a page missing required readings is kept with whatever readings were found, but is not stored
+/// If ephemeral is true, an empty in-memory database is used instead, so the database file is never read or written
+/// If store is false, a lectionary retrieved from the web is not stored in the database
+/// The database location is read from the config file at `config_path`, or the default config file if not given
+pub async fn retrieve_lectionary(
+    date_id: DateId,
+    client: &WebClient,
+    offline: bool,
+    ephemeral: bool,
+    store: bool,
+    config_path: Option<&Path>,
+) -> Result<Lectionary, RetrievalError> {
+    let db_result = if ephemeral {
+        DatabaseHandle::new_in_memory().await
+    } else {
+        DatabaseHandle::new(config_path).await
+    };
+    match db_result {
+        Ok(db) => retrieve_and_store(date_id, &db, client, offline, store).await,
+        Err(db_error) if offline => Err(RetrievalError::from(DatabaseError::from(db_error))),
+        Err(db_error) => {
+            warn!("Failed to initialize database ({db_error}); Retrieving '{date_id}' from web without storing");
+            client.get_for_date_id(date_id).await.map_err(|web_error| {
+                error!("Failed to retrieve from web ({web_error}) after failing to initialize database");
+                RetrievalError {
+                    db_error: Some(DatabaseError::from(db_error)),
+                    web_error: Some(web_error),
+                }
+            })
+        }
     }
 }
 
 /// Returns a Lectionary for displaying. First tries the database. If that fails, retrieves from the web and stores in to database.
-async fn retrieve_and_store(date_id: DateId, db: &DatabaseHandle) -> Result<Lectionary, RetrievalError> {
-    let lectionary = match db.get_lectionary(&date_id).await {
+///
+/// If offline is true, returns the database error instead of retrieving from the web.
+/// A lectionary missing required readings (only returned by a lenient client) is returned but not stored
+/// If store is false, the lectionary is returned without storing it
+async fn retrieve_and_store(
+    date_id: DateId,
+    db: &DatabaseHandle,
+    client: &WebClient,
+    offline: bool,
+    store: bool,
+) -> Result<Lectionary, RetrievalError> {
+    let start = Instant::now();
+    let db_result = db.get_lectionary(&date_id).await;
+    logging::log_timing(format_args!("Getting {date_id} from the database"), start);
+    let lectionary = match db_result {
         Ok(lectionary) => {
-            info!("lectionary '{}' present in database", date_id);
+            info!("Lectionary present in database: {lectionary}");
             lectionary
         }
+        Err(db_error) if offline => {
+            warn!("Could not find lectionary '{date_id}' in Database ({db_error}); Offline mode is enabled");
+            return Err(RetrievalError::from(DatabaseError::from(db_error)));
+        }
         Err(db_error) => {
             warn!(
                 "Could not find lectionary '{}' in Database ({}); Retrieving from Web",
                 &date_id, db_error
             );
-            let client = WebClient::default();
             match client.get_for_date_id(date_id).await {
+                Ok(lectionary) if !lectionary.is_complete() => {
+                    warn!("Lectionary '{}' is missing readings; Not adding to database", lectionary.get_id());
+                    lectionary
+                }
+                Ok(lectionary) if !store => {
+                    info!("Retrieved lectionary {lectionary}; Not adding to database since storing is disabled");
+                    lectionary
+                }
                 Ok(lectionary) => {
-                    info!("Retrieved lectionary '{}'; Adding to database", lectionary.get_id());
+                    info!("Retrieved lectionary {lectionary}; Adding to database");
+                    let start = Instant::now();
                     if let Err(e) = db.insert_lectionary(&lectionary).await {
                         warn!("Failed to store lectionary '{}' in database: {}", lectionary.get_id(), e);
                     }
+                    logging::log_timing(format_args!("Storing {} in the database", lectionary.get_id()), start);
                     lectionary
                 }
                 Err(web_error) => {
@@ -51,7 +188,10 @@ async fn retrieve_and_store(date_id: DateId, db: &DatabaseHandle) -> Result<Lect
                         "Failed to retrieve from web ({}) after failing to retrieve from database",
                         web_error
                     );
-                    return Err(RetrievalError::from(web_error));
+                    return Err(RetrievalError {
+                        db_error: Some(DatabaseError::from(db_error)),
+                        web_error: Some(web_error),
+                    });
                 }
             }
         }
@@ -59,10 +199,39 @@ async fn retrieve_and_store(date_id: DateId, db: &DatabaseHandle) -> Result<Lect
     Ok(lectionary)
 }
 
+/// What `ensure_stored_fresh` did for a lectionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreOutcome {
+    /// Already present and not stale, so no action was taken
+    Present,
+    /// Not present, so it was retrieved and stored
+    Added,
+    /// Present but stale, so it was retrieved again and replaced
+    Refetched,
+}
+
 /// Stores a lectionary to the database, if it is not stored already
 ///
 /// Returns true if new lectionary was stored, false if no action taken
+/// # Errors
+/// Returns a `DbUpdateError` if the lectionary could not be retrieved from the web or inserted in to the database
 pub async fn ensure_stored(date_id: DateId, db: &DatabaseHandle, client: &WebClient) -> Result<bool, DbUpdateError> {
+    ensure_stored_fresh(date_id, db, client, None)
+        .await
+        .map(|outcome| outcome == StoreOutcome::Added)
+}
+
+/// Like `ensure_stored`, but if `max_age` is given, a present lectionary fetched longer ago than that (or at an unknown time) is
+/// retrieved again and replaced
+///
+/// # Errors
+/// Returns a `DbUpdateError` if the lectionary could not be retrieved from the web or inserted in to the database
+pub async fn ensure_stored_fresh(
+    date_id: DateId,
+    db: &DatabaseHandle,
+    client: &WebClient,
+    max_age: Option<TimeDelta>,
+) -> Result<StoreOutcome, DbUpdateError> {
     let is_present = match db.lectionary_present(&date_id).await {
         Ok(is_present) => is_present,
         Err(e) => {
@@ -73,19 +242,35 @@ pub async fn ensure_stored(date_id: DateId, db: &DatabaseHandle, client: &WebCli
             false
         }
     };
-    if is_present {
-        info!("Lectionary '{}' is already present in the database", date_id);
-        Ok(false)
-    } else {
+    if !is_present {
         debug!("Retrieving lectionary with id '{}' from web", &date_id);
-        retrieve_for_database(date_id, db, client).await.map(|()| true)
+        return retrieve_for_database(date_id, db, client).await.map(|()| StoreOutcome::Added);
+    }
+    if let Some(max_age) = max_age {
+        if is_stale(db, &date_id, max_age).await {
+            info!("Lectionary '{date_id}' is stale; Retrieving it again from web");
+            return retrieve_for_database(date_id, db, client).await.map(|()| StoreOutcome::Refetched);
+        }
+    }
+    info!("Lectionary '{}' is already present in the database", date_id);
+    Ok(StoreOutcome::Present)
+}
+
+/// True if the lectionary was fetched longer ago than `max_age`, or if it isn't known when it was fetched
+async fn is_stale(db: &DatabaseHandle, date_id: &DateId, max_age: TimeDelta) -> bool {
+    match db.get_fetched_at(date_id).await {
+        Ok(fetched_at) => is_older_than(fetched_at, Utc::now().timestamp(), max_age),
+        Err(e) => {
+            warn!("Could not determine when '{date_id}' was fetched ({e}); Not retrieving it again");
+            false
+        }
     }
 }
 
 async fn retrieve_for_database(date_id: DateId, db: &DatabaseHandle, client: &WebClient) -> Result<(), DbUpdateError> {
     match client.get_for_date_id(date_id).await {
         Ok(lectionary) => {
-            info!("Retrieved lectionary '{}'; Adding to database", lectionary.get_id());
+            info!("Retrieved lectionary {lectionary}; Adding to database");
             match db.insert_lectionary(&lectionary).await.map_err(DbUpdateError::from) {
                 Ok(()) => {
                     info!("Successfully stored new lectionary '{}' to database", lectionary.get_id());
@@ -101,6 +286,11 @@ async fn retrieve_for_database(date_id: DateId, db: &DatabaseHandle, client: &We
     }
 }
 
+/// True if `fetched_at` is more than `max_age` before `now` (both in seconds since the Unix epoch), or if it is unknown
+fn is_older_than(fetched_at: Option<i64>, now: i64, max_age: TimeDelta) -> bool {
+    fetched_at.is_none_or(|fetched_at| now - fetched_at > max_age.num_seconds())
+}
+
 /// A failure to retrieve a lectionary from the database, web, or both
 ///
 /// Used when trying to display a Lectionary
@@ -110,9 +300,31 @@ pub struct RetrievalError {
     web_error: Option<WebGetError>,
 }
 
+impl RetrievalError {
+    /// True if retrieving from the web failed because the site could not be reached
+    #[must_use]
+    pub fn is_network_unreachable(&self) -> bool {
+        self.web_error.as_ref().is_some_and(WebGetError::is_network_unreachable)
+    }
+
+    /// True if the readings for the date have not been posted on the web yet
+    #[must_use]
+    pub fn is_not_available(&self) -> bool {
+        self.web_error.as_ref().is_some_and(WebGetError::is_not_available)
+    }
+
+    /// True if the web has no readings page for the date
+    #[must_use]
+    pub fn is_missing_page(&self) -> bool {
+        self.web_error.as_ref().is_some_and(WebGetError::is_missing_page)
+    }
+}
+
 impl Display for RetrievalError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match (self.db_error.as_ref(), self.web_error.as_ref()) {
+            // Whether or not it was in the database, this is the reason it can't be displayed
+            (_, Some(web_error)) if web_error.is_not_available() => write!(f, "{web_error}"),
             (Some(db_error), Some(web_error)) => write!(
                 f,
                 "Failed to retrieve from db ({db_error}) and failed to retrieve from web ({web_error})"
@@ -148,9 +360,31 @@ pub enum DbUpdateError {
     /// Failure to retrieve from Web
     #[error("Could not retrieve lectionary for storage: ({0})")]
     RetrieveError(#[from] WebGetError),
+    /// Another process held a lock on the database for too long
+    #[error("Could not store retrieved lectionary: The database is locked by another process. Try again once it finishes")]
+    Locked(#[source] sqlx::Error),
     /// Failure to insert the retrieved lectionary in to database
     #[error("Could not store retrieved lectionary in database: ({0})")]
-    InsertError(#[from] sqlx::Error),
+    InsertError(#[source] sqlx::Error),
+}
+
+impl From<sqlx::Error> for DbUpdateError {
+    fn from(value: sqlx::Error) -> Self {
+        if db::is_locked_error(&value) {
+            Self::Locked(value)
+        } else {
+            Self::InsertError(value)
+        }
+    }
+}
+
+/// Represents a failure to parse a lectionary from a saved page
+#[derive(thiserror::Error, Debug)]
+pub enum ParseFileError {
+    #[error("Could not read '{}': ({source})", path.to_string_lossy())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("Error creating lectionary from html: ({0})")]
+    Parse(#[from] LectionaryHtmlError),
 }
 
 /// Represents a critical failure while working with the database
@@ -163,3 +397,37 @@ pub enum DatabaseError {
     #[error("Failed to delete row(s) from the database: ({0})")]
     DeleteError(#[from] sqlx::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_saved_page() {
+        let date_id = DateId::checked_from_str("052624").unwrap();
+        let lectionary = parse_file(
+            date_id.clone(),
+            Path::new("tests/resources/solemnity.html"),
+            &PageSelectors::default(),
+            false,
+        )
+        .unwrap();
+        assert_eq!("The Most Holy Trinity", lectionary.get_day_name());
+        assert_eq!(&date_id, lectionary.get_id());
+
+        let error = parse_html(date_id.clone(), "<html><body></body></html>", &PageSelectors::default(), false).unwrap_err();
+        assert!(matches!(error, ParseFileError::Parse(LectionaryHtmlError::NoContainerFound { .. })));
+        let error = parse_file(date_id, Path::new("tests/resources/missing.html"), &PageSelectors::default(), false).unwrap_err();
+        assert!(matches!(error, ParseFileError::Read { .. }));
+    }
+
+    #[test]
+    fn stale_when_older_than_max_age_or_unknown() {
+        let now = 1_000_000;
+        let max_age = TimeDelta::days(7);
+        assert!(!is_older_than(Some(now - 60), now, max_age));
+        assert!(!is_older_than(Some(now - max_age.num_seconds()), now, max_age));
+        assert!(is_older_than(Some(now - max_age.num_seconds() - 1), now, max_age));
+        assert!(is_older_than(None, now, max_age));
+    }
+}
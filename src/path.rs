@@ -1,6 +1,6 @@
 //TODO Windows support
 use std::env::{self, VarError};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 use log::*;
@@ -12,7 +12,7 @@ pub fn create_and_get_db_path() -> Result<PathBuf, PathError> {
     path.push(env!("CARGO_PKG_NAME"));
     path.push(concat!(env!("CARGO_PKG_NAME"), ".db"));
 
-    fs::create_dir_all(path.parent().expect("Created path must have parent")).map_err(PathError::PathCreateFailure)?;
+    create_dir_all(path.parent().expect("Created path must have parent"))?;
 
     Ok(path)
 }
@@ -23,7 +23,30 @@ pub fn create_and_get_log_path() -> Result<PathBuf, PathError> {
     path.push(env!("CARGO_PKG_NAME"));
     path.push(concat!(env!("CARGO_PKG_NAME"), ".log"));
 
-    fs::create_dir_all(path.parent().expect("Created path must have parent")).map_err(PathError::PathCreateFailure)?;
+    create_dir_all(path.parent().expect("Created path must have parent"))?;
+
+    Ok(path)
+}
+
+/// Returns the path of the file listing the dates that db update failed to store, after ensuring all parent directories have
+/// been created
+pub fn create_and_get_failed_updates_path() -> Result<PathBuf, PathError> {
+    let mut path = get_xdg_state_home().map_err(PathError::NoHome)?;
+    path.push(env!("CARGO_PKG_NAME"));
+    path.push("failed_updates.txt");
+
+    create_dir_all(path.parent().expect("Created path must have parent"))?;
+
+    Ok(path)
+}
+
+/// Returns the directory that retrieved pages are saved to, after ensuring it has been created
+pub fn create_and_get_cache_path() -> Result<PathBuf, PathError> {
+    let mut path = get_xdg_cache_home().map_err(PathError::NoHome)?;
+    path.push(env!("CARGO_PKG_NAME"));
+    path.push("html");
+
+    create_dir_all(&path)?;
 
     Ok(path)
 }
@@ -34,11 +57,41 @@ pub fn create_and_get_config_path() -> Result<PathBuf, PathError> {
     config_path.push(env!("CARGO_PKG_NAME"));
     config_path.push("config.toml");
 
-    fs::create_dir_all(config_path.parent().expect("Created path must have parent")).map_err(PathError::PathCreateFailure)?;
+    create_dir_all(config_path.parent().expect("Created path must have parent"))?;
 
     Ok(config_path)
 }
 
+/// Resolves a user-provided path against the current directory (if relative), after ensuring all parent directories have been created
+pub fn create_and_resolve_path(path: &Path) -> Result<PathBuf, PathError> {
+    let resolved = env::current_dir().map_err(PathError::NoCurrentDir)?.join(path);
+
+    if let Some(parent) = resolved.parent() {
+        create_dir_all(parent)?;
+    }
+
+    Ok(resolved)
+}
+
+/// Creates the directory and its parents. Permission failures are reported with the directory, so that the user can fix them
+fn create_dir_all(path: &Path) -> Result<(), PathError> {
+    fs::create_dir_all(path).map_err(|source| {
+        if is_permission_denied(&source) {
+            PathError::PermissionDenied {
+                path: path.to_owned(),
+                source,
+            }
+        } else {
+            PathError::PathCreateFailure(source)
+        }
+    })
+}
+
+/// True if the error was caused by not being allowed to write, including to a read-only filesystem
+pub fn is_permission_denied(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem)
+}
+
 /// First trie `$XDG_STATE_HOME`, then tries $HOME/.local/state
 fn get_xdg_state_home() -> Result<PathBuf, VarError> {
     const STATE_ENV_VAR: &str = "XDG_STATE_HOME";
@@ -98,6 +151,25 @@ fn get_xdg_config_home() -> Result<PathBuf, VarError> {
     Ok(xdg_path)
 }
 
+/// First tries `$XDG_CACHE_HOME`, then tries $HOME/.cache
+fn get_xdg_cache_home() -> Result<PathBuf, VarError> {
+    const CACHE_ENV_VAR: &str = "XDG_CACHE_HOME";
+    let xdg_path = match env::var(CACHE_ENV_VAR) {
+        Ok(path_str) => PathBuf::from(path_str),
+        Err(no_xdg_error) => {
+            debug!("Failed to read environment variable '{CACHE_ENV_VAR}': {no_xdg_error}");
+            match get_home_path() {
+                Ok(mut path) => {
+                    path.push(".cache");
+                    path
+                }
+                Err(no_home_error) => return Err(no_home_error),
+            }
+        }
+    };
+    Ok(xdg_path)
+}
+
 fn get_home_path() -> Result<PathBuf, VarError> {
     Ok(PathBuf::from(env::var("HOME")?))
 }
@@ -120,6 +192,35 @@ mod tests {
         assert_eq!("log", log_path.extension().unwrap().to_string_lossy());
     }
 
+    #[test]
+    fn failed_updates_path_present() {
+        let path = create_and_get_failed_updates_path().unwrap();
+        assert!(path.parent().unwrap().is_dir());
+        assert_eq!("txt", path.extension().unwrap().to_string_lossy());
+    }
+
+    #[test]
+    fn cache_path_present() {
+        let cache_path = create_and_get_cache_path().unwrap();
+        assert!(cache_path.is_dir());
+        assert_eq!("html", cache_path.file_name().unwrap().to_string_lossy());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn permission_denied_has_path() {
+        // Nothing can be created in /sys, even by root
+        let error = create_and_resolve_path(Path::new("/sys/lectio-diei/test.db")).unwrap_err();
+        assert!(matches!(error, PathError::PermissionDenied { ref path, .. } if path == Path::new("/sys/lectio-diei")));
+    }
+
+    #[test]
+    fn relative_path_resolved() {
+        let path = create_and_resolve_path(Path::new("relative.db")).unwrap();
+        assert!(path.is_absolute());
+        assert_eq!(env::current_dir().unwrap().join("relative.db"), path);
+    }
+
     #[test]
     fn config_path_present() {
         let config_path = create_and_get_config_path().unwrap();
@@ -135,4 +236,8 @@ pub enum PathError {
     NoHome(#[from] VarError),
     #[error("Failed to create parent directory: ({0})")]
     PathCreateFailure(#[from] io::Error),
+    #[error("Could not get current directory to resolve relative path: ({0})")]
+    NoCurrentDir(#[source] io::Error),
+    #[error("Not allowed to create directory '{}': ({source})", path.to_string_lossy())]
+    PermissionDenied { path: PathBuf, source: io::Error },
 }
@@ -0,0 +1,136 @@
+//! Heuristics for deriving the rank of a day (e.g. solemnity, memorial) from the name of the day
+
+use std::fmt::{self, Display, Formatter};
+
+use clap::ValueEnum;
+
+/// The rank of a liturgical day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Rank {
+    Solemnity,
+    Feast,
+    Memorial,
+    OptionalMemorial,
+    Sunday,
+    Weekday,
+}
+
+/// Keywords (lowercase) that identify the rank of a day. Checked in order, so more specific keywords must come first
+///
+/// e.g. "Optional Memorial" must be matched before "memorial", and any named celebration before "sunday"
+const RANK_KEYWORDS: [(&str, Rank); 19] = [
+    ("solemnity", Rank::Solemnity),
+    // Solemnities that the USCCB does not always name as such
+    ("nativity of the lord", Rank::Solemnity),
+    ("easter sunday", Rank::Solemnity),
+    ("pentecost sunday", Rank::Solemnity),
+    ("ascension of the lord", Rank::Solemnity),
+    ("most holy trinity", Rank::Solemnity),
+    ("king of the universe", Rank::Solemnity),
+    ("feast", Rank::Feast),
+    ("optional memorial", Rank::OptionalMemorial),
+    ("memorial", Rank::Memorial),
+    ("sunday", Rank::Sunday),
+    ("monday", Rank::Weekday),
+    ("tuesday", Rank::Weekday),
+    ("wednesday", Rank::Weekday),
+    ("thursday", Rank::Weekday),
+    ("friday", Rank::Weekday),
+    ("saturday", Rank::Weekday),
+    // Some days in the octave of Christmas are named by their place in the octave instead of the day of the week
+    ("within the octave", Rank::Weekday),
+    ("day in the octave", Rank::Weekday),
+];
+
+impl Rank {
+    /// Makes a best-effort guess at the rank from the name of the day
+    ///
+    /// Returns None if none of the known keywords are present
+    #[must_use]
+    pub fn from_day_name(day_name: &str) -> Option<Self> {
+        let lowercase = day_name.to_lowercase();
+        RANK_KEYWORDS
+            .iter()
+            .find(|(keyword, _)| lowercase.contains(keyword))
+            .map(|(_, rank)| *rank)
+    }
+
+    /// Name used for storing in the database. Matches the name used for arguments
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Solemnity => "solemnity",
+            Self::Feast => "feast",
+            Self::Memorial => "memorial",
+            Self::OptionalMemorial => "optional-memorial",
+            Self::Sunday => "sunday",
+            Self::Weekday => "weekday",
+        }
+    }
+}
+
+impl Display for Rank {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solemnities() {
+        assert_eq!(
+            Some(Rank::Solemnity),
+            Rank::from_day_name("Solemnity of the Assumption of the Blessed Virgin Mary")
+        );
+        assert_eq!(Some(Rank::Solemnity), Rank::from_day_name("The Nativity of the Lord (Christmas)"));
+        assert_eq!(
+            Some(Rank::Solemnity),
+            Rank::from_day_name("Easter Sunday of the Resurrection of the Lord")
+        );
+        assert_eq!(
+            Some(Rank::Solemnity),
+            Rank::from_day_name("Our Lord Jesus Christ, King of the Universe")
+        );
+    }
+
+    #[test]
+    fn feasts() {
+        assert_eq!(Some(Rank::Feast), Rank::from_day_name("Feast of Saint Andrew, Apostle"));
+        assert_eq!(
+            Some(Rank::Feast),
+            Rank::from_day_name("Feast of the Holy Family of Jesus, Mary and Joseph")
+        );
+    }
+
+    #[test]
+    fn memorials() {
+        assert_eq!(
+            Some(Rank::Memorial),
+            Rank::from_day_name("Memorial of Saint Lawrence, deacon and martyr")
+        );
+        assert_eq!(
+            Some(Rank::OptionalMemorial),
+            Rank::from_day_name("Optional Memorial of Saint Blaise, Bishop and Martyr")
+        );
+    }
+
+    #[test]
+    fn sundays_and_weekdays() {
+        assert_eq!(Some(Rank::Sunday), Rank::from_day_name("Fifteenth Sunday in Ordinary Time"));
+        assert_eq!(Some(Rank::Sunday), Rank::from_day_name("Palm Sunday of the Passion of the Lord"));
+        assert_eq!(
+            Some(Rank::Weekday),
+            Rank::from_day_name("Tuesday of the Nineteenth Week in Ordinary Time")
+        );
+        assert_eq!(Some(Rank::Weekday), Rank::from_day_name("Thursday after Ash Wednesday"));
+    }
+
+    #[test]
+    fn unrecognized_is_none() {
+        assert_eq!(None, Rank::from_day_name("Daily Readings"));
+        assert_eq!(None, Rank::from_day_name(""));
+    }
+}
@@ -0,0 +1,143 @@
+//! Names of the books of the Bible as abbreviated in the reading locations on the USCCB site
+
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+
+/// Abbreviations used by the USCCB (from the NABRE) and the full name of each book of the Catholic canon
+const BOOK_ABBREVIATIONS: [(&str, &str); 74] = [
+    // Old Testament
+    ("Gn", "Genesis"),
+    ("Ex", "Exodus"),
+    ("Lv", "Leviticus"),
+    ("Nm", "Numbers"),
+    ("Dt", "Deuteronomy"),
+    ("Jos", "Joshua"),
+    ("Jgs", "Judges"),
+    ("Ru", "Ruth"),
+    ("1 Sm", "1 Samuel"),
+    ("2 Sm", "2 Samuel"),
+    ("1 Kgs", "1 Kings"),
+    ("2 Kgs", "2 Kings"),
+    ("1 Chr", "1 Chronicles"),
+    ("2 Chr", "2 Chronicles"),
+    ("Ezr", "Ezra"),
+    ("Neh", "Nehemiah"),
+    ("Tb", "Tobit"),
+    ("Jdt", "Judith"),
+    ("Est", "Esther"),
+    ("1 Mc", "1 Maccabees"),
+    ("2 Mc", "2 Maccabees"),
+    ("Jb", "Job"),
+    ("Ps", "Psalms"),
+    ("Pss", "Psalms"),
+    ("Prv", "Proverbs"),
+    ("Eccl", "Ecclesiastes"),
+    ("Sg", "Song of Songs"),
+    ("Wis", "Wisdom"),
+    ("Sir", "Sirach"),
+    ("Is", "Isaiah"),
+    ("Jer", "Jeremiah"),
+    ("Lam", "Lamentations"),
+    ("Bar", "Baruch"),
+    ("Ez", "Ezekiel"),
+    ("Dn", "Daniel"),
+    ("Hos", "Hosea"),
+    ("Jl", "Joel"),
+    ("Am", "Amos"),
+    ("Ob", "Obadiah"),
+    ("Jon", "Jonah"),
+    ("Mi", "Micah"),
+    ("Na", "Nahum"),
+    ("Hb", "Habakkuk"),
+    ("Zep", "Zephaniah"),
+    ("Hg", "Haggai"),
+    ("Zec", "Zechariah"),
+    ("Mal", "Malachi"),
+    // New Testament
+    ("Mt", "Matthew"),
+    ("Mk", "Mark"),
+    ("Lk", "Luke"),
+    ("Jn", "John"),
+    ("Acts", "Acts"),
+    ("Rom", "Romans"),
+    ("1 Cor", "1 Corinthians"),
+    ("2 Cor", "2 Corinthians"),
+    ("Gal", "Galatians"),
+    ("Eph", "Ephesians"),
+    ("Phil", "Philippians"),
+    ("Col", "Colossians"),
+    ("1 Thes", "1 Thessalonians"),
+    ("2 Thes", "2 Thessalonians"),
+    ("1 Tm", "1 Timothy"),
+    ("2 Tm", "2 Timothy"),
+    ("Ti", "Titus"),
+    ("Phlm", "Philemon"),
+    ("Heb", "Hebrews"),
+    ("Jas", "James"),
+    ("1 Pt", "1 Peter"),
+    ("2 Pt", "2 Peter"),
+    ("1 Jn", "1 John"),
+    ("2 Jn", "2 John"),
+    ("3 Jn", "3 John"),
+    ("Jude", "Jude"),
+    ("Rv", "Revelation"),
+];
+
+/// Matches a book abbreviation, optionally preceded by the number of the book (e.g. "Mt", "1 Cor")
+fn abbreviation_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\b(?:[1-3]\s+)?[A-Z][a-z]*\b").expect("Should be valid regex"))
+}
+
+/// Gets the full name of the book for an abbreviation, if it is known
+fn full_name(abbreviation: &str) -> Option<&'static str> {
+    let normalized = abbreviation.split_whitespace().collect::<Vec<_>>().join(" ");
+    BOOK_ABBREVIATIONS
+        .iter()
+        .find(|(abbr, _)| *abbr == normalized)
+        .map(|(_, name)| *name)
+}
+
+/// Replaces each known book abbreviation in a reading location with the full name of the book
+///
+/// e.g. "Mt 9:1-8" becomes "Matthew 9:1-8". Unknown abbreviations are left as they are
+pub fn expand_location(location: &str) -> String {
+    abbreviation_regex()
+        .replace_all(location, |captures: &Captures| {
+            let abbreviation = &captures[0];
+            full_name(abbreviation).map_or_else(|| abbreviation.to_owned(), str::to_owned)
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_single_word_books() {
+        assert_eq!("Matthew 9:1-8", expand_location("Mt 9:1-8"));
+        assert_eq!("Amos 7:12-15", expand_location("Am 7:12-15"));
+        assert_eq!("Psalms 85:9-10, 11-12, 13-14", expand_location("Ps 85:9-10, 11-12, 13-14"));
+        assert_eq!("Revelation 21:1-5a", expand_location("Rv 21:1-5a"));
+    }
+
+    #[test]
+    fn expands_numbered_books() {
+        assert_eq!("1 Corinthians 12:3b-7, 12-13", expand_location("1 Cor 12:3b-7, 12-13"));
+        assert_eq!("2 Kings 4:42-44", expand_location("2 Kgs 4:42-44"));
+        assert_eq!("3 John 5-8", expand_location("3 Jn 5-8"));
+    }
+
+    #[test]
+    fn expands_every_book_in_location() {
+        assert_eq!("Isaiah 9:1-6 and Luke 2:1-14", expand_location("Is 9:1-6 and Lk 2:1-14"));
+    }
+
+    #[test]
+    fn unknown_abbreviation_unchanged() {
+        assert_eq!("Xyz 1:1", expand_location("Xyz 1:1"));
+        assert_eq!("", expand_location(""));
+    }
+}
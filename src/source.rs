@@ -0,0 +1,75 @@
+//! Sites the readings can be retrieved from. A source knows the URL scheme of its site and how to parse its pages,
+//! so that the `WebClient` and orchestration do not depend on any one site
+
+use scraper::Html;
+
+use crate::date::DateId;
+use crate::html::{self, LectionaryHtmlError, MassLink, PageSelectors};
+use crate::lectionary::Lectionary;
+
+/// A site that publishes the readings for each day
+pub(crate) trait LectionarySource: Send + Sync {
+    /// Endpoint of the readings page for the date, relative to the base URL of the site
+    fn endpoint_for_date(&self, date_id: &DateId) -> String;
+
+    /// Parses a readings page. If `lenient` is true, missing required readings are allowed
+    fn parse_lectionary(&self, date_id: DateId, document: &Html, lenient: bool) -> Result<Lectionary, LectionaryHtmlError>;
+
+    /// Links to each of the Masses on days with more than one, in the order they are listed. Empty if the day only has one
+    fn mass_links<'a>(&self, document: &'a Html) -> Vec<MassLink<'a>>;
+
+    /// On days with more than one Mass, the endpoint of the Mass during the Day
+    fn day_mass_endpoint<'a>(&self, document: &'a Html) -> Option<&'a str>;
+
+    /// Inner HTML of the element that contains the readings, exactly as it is on the page. None if there is none
+    fn container_html(&self, document: &Html) -> Option<String>;
+}
+
+/// The site of the United States Conference of Catholic Bishops (bible.usccb.org)
+#[derive(Default)]
+pub(crate) struct Usccb {
+    selectors: PageSelectors,
+}
+
+impl Usccb {
+    pub(crate) fn new(selectors: PageSelectors) -> Self {
+        Self { selectors }
+    }
+}
+
+impl LectionarySource for Usccb {
+    fn endpoint_for_date(&self, date_id: &DateId) -> String {
+        format!("/bible/readings/{date_id}.cfm")
+    }
+
+    fn parse_lectionary(&self, date_id: DateId, document: &Html, lenient: bool) -> Result<Lectionary, LectionaryHtmlError> {
+        if lenient {
+            Lectionary::create_from_html_lenient(date_id, document, &self.selectors)
+        } else {
+            Lectionary::create_from_html(date_id, document, &self.selectors)
+        }
+    }
+
+    fn mass_links<'a>(&self, document: &'a Html) -> Vec<MassLink<'a>> {
+        html::get_mass_links(document, &self.selectors)
+    }
+
+    fn day_mass_endpoint<'a>(&self, document: &'a Html) -> Option<&'a str> {
+        html::get_holiday_day_reading_link(document, &self.selectors)
+    }
+
+    fn container_html(&self, document: &Html) -> Option<String> {
+        html::container_inner_html(document, &self.selectors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usccb_endpoint_for_date() {
+        let date_id = DateId::checked_from_str("072024").unwrap();
+        assert_eq!("/bible/readings/072024.cfm", Usccb::default().endpoint_for_date(&date_id));
+    }
+}
@@ -1,6 +1,17 @@
 use std::{env, fs, path::Path};
 
-use lectio_diei::{args::ConfigCommand, commands};
+use lectio_diei::{
+    args::{ColorWhen, CommonArguments, ConfigCommand, DisplayReadingsArgs, FormattingArgs, OutputArgs, ReadingLengthArgs, RetrievalArgs},
+    commands, ensure_stored, DatabaseHandle, DateId, WebClient,
+};
+use tokio::runtime::Runtime;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// Date served by the mock server using the `sunday_or.html` resource
+const SUNDAY_DATE: &str = "071424";
 
 #[test]
 fn full_thread() {
@@ -21,15 +32,150 @@ fn full_thread() {
     fs::create_dir_all(&config_dir).unwrap();
     fs::remove_dir_all(&config_dir).unwrap();
 
-    //TODO more of full thread
     test_config_init_no_force(&config_dir);
 
+    let runtime = Runtime::new().unwrap();
+    let server = runtime.block_on(start_mock_server());
+    // SAFETY: integration test runs serially in single thread
+    #[allow(unused_unsafe)]
+    unsafe {
+        env::set_var("LECTIO_DIEI_BASE_URL", server.uri());
+    }
+    runtime.block_on(test_store_from_mock(&server));
+    runtime.block_on(test_display_stored(&temp_dir_root));
+    runtime.block_on(test_display_no_store(&temp_dir_root));
+
     // Cleanup
     fs::remove_dir_all(temp_dir_root).unwrap();
 }
 
 fn test_config_init_no_force(config_dir: &Path) {
-    assert!(commands::handle_config_command(ConfigCommand::Init { force: false }).is_ok());
+    assert!(commands::handle_config_command(ConfigCommand::Init { force: false }, None).is_ok());
     let config = config_dir.join(env!("CARGO_PKG_NAME")).join("config.toml");
     assert!(config.is_file());
 }
+
+/// Starts a server that serves the html in tests/resources in place of the USCCB site. Any other page is a 404
+async fn start_mock_server() -> MockServer {
+    let server = MockServer::start().await;
+    let body = fs::read_to_string(Path::new("tests").join("resources").join("sunday_or.html")).unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("/bible/readings/{SUNDAY_DATE}.cfm")))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn test_store_from_mock(server: &MockServer) {
+    let db = DatabaseHandle::new(None).await.unwrap();
+    let client = WebClient::new(&server.uri().parse().unwrap());
+    let date_id = DateId::checked_from_str(SUNDAY_DATE).unwrap();
+    assert!(ensure_stored(date_id.clone(), &db, &client).await.unwrap());
+    assert_eq!(1, db.get_lectionary_count().await.unwrap());
+
+    // Already stored, so nothing new is added
+    assert!(!ensure_stored(date_id, &db, &client).await.unwrap());
+    assert_eq!(1, db.get_lectionary_count().await.unwrap());
+
+    // Not served by the mock server
+    let missing_id = DateId::checked_from_str("010125").unwrap();
+    assert!(ensure_stored(missing_id, &db, &client).await.is_err());
+    assert_eq!(1, db.get_lectionary_count().await.unwrap());
+}
+
+async fn test_display_stored(temp_dir_root: &Path) {
+    let output = temp_dir_root.join("display.txt");
+    commands::display(
+        vec![SUNDAY_DATE.to_owned()],
+        DisplayReadingsArgs {
+            readings: None,
+            all: true,
+            day_only: false,
+            ..DisplayReadingsArgs::default()
+        },
+        FormattingArgs {
+            max_width: None,
+            original_linebreaks: true,
+        },
+        RetrievalArgs {
+            offline: true,
+            nearest: None,
+            mass: None,
+            lenient: false,
+            until: None,
+            date_format: None,
+            ephemeral: false,
+            find: None,
+            use_cache: false,
+            no_store: false,
+            from_file: None,
+        },
+        ReadingLengthArgs { long: false, short: false },
+        OutputArgs {
+            output: Some(output.clone()),
+            web: false,
+            color: ColorWhen::Auto,
+            raw: false,
+            headings: false,
+            format: None,
+            compact: false,
+            raw_html: false,
+            profile: None,
+            expand_refs: false,
+            footnotes: false,
+            separator: None,
+            ascii: false,
+            no_responses: false,
+            show_source: false,
+            number_lines: false,
+        },
+        CommonArguments {
+            config: None,
+            no_color: true,
+            quiet: true,
+            verbose: 0,
+        },
+    )
+    .await
+    .unwrap();
+
+    let displayed = fs::read_to_string(output).unwrap();
+    assert!(displayed.contains("Fifteenth Sunday in Ordinary Time"));
+    assert!(displayed.contains("Gospel"));
+}
+
+/// Displays readings retrieved from the mock server with --no-store, which must leave the database as it was
+async fn test_display_no_store(temp_dir_root: &Path) {
+    let db = DatabaseHandle::new(None).await.unwrap();
+    let date_id = DateId::checked_from_str(SUNDAY_DATE).unwrap();
+    assert!(db.remove_lectionary(&date_id).await.unwrap());
+
+    let output = temp_dir_root.join("no_store.txt");
+    commands::display(
+        vec![SUNDAY_DATE.to_owned()],
+        DisplayReadingsArgs::default(),
+        FormattingArgs::default(),
+        RetrievalArgs {
+            no_store: true,
+            from_file: None,
+            ..RetrievalArgs::default()
+        },
+        ReadingLengthArgs::default(),
+        OutputArgs {
+            output: Some(output.clone()),
+            ..OutputArgs::default()
+        },
+        CommonArguments {
+            config: None,
+            no_color: true,
+            quiet: true,
+            verbose: 0,
+        },
+    )
+    .await
+    .unwrap();
+
+    assert!(fs::read_to_string(output).unwrap().contains("Fifteenth Sunday in Ordinary Time"));
+    assert_eq!(0, db.get_lectionary_count().await.unwrap());
+}